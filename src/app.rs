@@ -13,53 +13,236 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::asciicast::Asciicast;
-use crate::sequence::{Command, Sequence, Timings, DEFAULT_PROMPT};
+use self::build::Build;
+use self::cat::Cat;
+use self::completions::Completions;
+use self::concat::Concat;
+use self::convert::Convert;
+use self::cut::Cut;
+use self::info::Info;
+use self::normalize::Normalize;
+use self::png::Png;
+use self::verify::Verify;
+use self::video::Video;
+use crate::asciicast::{self, Asciicast, CommentStyles, Header, Sizes, TypingUnits};
+use crate::backend::{Backend, ExecBackends};
+use crate::captions;
+use crate::chapters;
+use crate::compress::{self, Compressions, Encoder};
+use crate::delay::{self, Delay};
+use crate::diagnostic;
+use crate::embed::Embed;
+use crate::highlight;
+use crate::interrupt;
+use crate::keyboard::KeyboardLayouts;
+use crate::manifest::{self, Artifact, Manifest};
+use crate::prompt::{self, Flavors, PromptPresets};
+use crate::recorder::Recorder;
+use crate::redact::{self, RedactPresets};
+use crate::sequence::{
+    self, Command, EnvExpansions, Output, Progress, Response, Secret, Sequence, Spinner, Timings,
+    TypingProfiles, DEFAULT_PROMPT, DEFAULT_SECRET_LENGTH, SECONDS_UNITS,
+};
 use crate::termsheets;
-use anyhow::Result;
+use crate::theme::ThemePresets;
+use crate::transcript;
+use crate::websocket::WebSocket;
+use anyhow::{anyhow, bail, Context, Result};
 use atty::Stream;
 use clap::value_t;
-use std::fs::File;
+use log::{debug, info, warn, LevelFilter};
+use regex::Regex;
+use std::fs::{File, OpenOptions};
 use std::io;
-use std::io::{BufRead, BufReader, Read, Write};
-use std::path::PathBuf;
-use std::process;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 use structopt::clap::{self, ArgMatches};
 use structopt::StructOpt;
 use strum::{Display, EnumString, EnumVariantNames, VariantNames};
 
+mod build;
+mod cat;
+mod completions;
+mod concat;
+mod convert;
+mod cut;
+mod info;
+mod normalize;
+mod png;
+mod verify;
+mod video;
+
 pub const DEFAULT_INTERACTIVE_PROMPT: &str = ">>> ";
 
-#[derive(Display, Debug, EnumString, EnumVariantNames)]
+/// The number of bytes read at a time from the output of an executed command.
+const CAPTURE_CHUNK_SIZE: usize = 8192;
+
+/// The time between checks for modified input files with the --watch flag.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The time in seconds to wait before executing a failed input again.
+const DEFAULT_RETRY_DELAY: &str = "1.0";
+
+/// The time in seconds to wait for the prompt of an --expect option.
+const DEFAULT_RESPONSE_TIMEOUT: &str = "10";
+
+/// The environment variable with the log filters, which overrides the
+/// -v,--verbose and -q,--quiet flags.
+const LOG_VAR_NAME: &str = "GERM_LOG";
+
+#[derive(Display, Debug, Default, Clone, Copy, EnumString, EnumVariantNames)]
 #[strum(serialize_all = "lowercase")]
 enum InputFormats {
+    #[default]
     Germ,
     TermSheets,
+    Asciicast,
 }
 
-impl Default for InputFormats {
-    fn default() -> Self {
-        Self::Germ
+impl InputFormats {
+    /// Detects the format of a file from its extension and content.
+    ///
+    /// The extension of a compressed file, i.e. "demo.cast.gz", is the one
+    /// before the extension of the compression.
+    fn detect(path: &Path, buffer: &[u8]) -> Self {
+        let path = match Compressions::detect(path) {
+            Some(_) => Path::new(path.file_stem().unwrap_or_default()),
+            None => path,
+        };
+        if path.extension().is_some_and(|e| e == "cast") {
+            return Self::Asciicast;
+        }
+        let first_line = buffer.split(|b| *b == b'\n').next().unwrap_or_default();
+        match buffer.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'[') => Self::TermSheets,
+            _ if serde_json::from_slice::<Header>(first_line)
+                .is_ok_and(|h| h.version() == asciicast::VERSION) =>
+            {
+                Self::Asciicast
+            }
+            _ => Self::Germ,
+        }
     }
 }
 
-#[derive(Display, Debug, EnumString, EnumVariantNames)]
+#[derive(Display, Debug, Default, Clone, Copy, EnumString, EnumVariantNames)]
 #[strum(serialize_all = "lowercase")]
 enum OutputFormats {
     Germ,
     TermSheets,
+    #[default]
     Asciicast,
+    Embed,
+    WebVtt,
+    Srt,
+    Chapters,
+    FfMetadata,
+    Transcript,
 }
 
-impl Default for OutputFormats {
-    fn default() -> Self {
-        Self::Asciicast
+impl OutputFormats {
+    /// The conventional file extension for the format.
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Germ | Self::TermSheets => "json",
+            Self::Asciicast => "cast",
+            Self::Embed => "html",
+            Self::WebVtt => "vtt",
+            Self::Srt => "srt",
+            Self::Chapters => "chapters.json",
+            Self::FfMetadata => "ffmetadata",
+            Self::Transcript => "txt",
+        }
     }
 }
 
+#[derive(Debug, Clone, StructOpt)]
+enum Subcommand {
+    /// Converts many files, or whole directories, in one invocation.
+    ///
+    /// Options given before the subcommand, such as timings and the terminal
+    /// size, are applied to every converted file.
+    Convert(Convert),
+
+    /// Prints statistics about the pacing of a sequence or asciicast file.
+    Info(Info),
+
+    /// Joins sequence and asciicast files into one asciicast file.
+    ///
+    /// The events of each file follow the last event of the file before it,
+    /// and the terminal is as large as the largest of the files.
+    Concat(Concat),
+
+    /// Keeps the events of a sequence or asciicast file within a time range.
+    ///
+    /// The events before --from and after --to are dropped and the remaining
+    /// events are rebased to start at zero.
+    Cut(Cut),
+
+    /// Prints the screen at the end of a sequence or asciicast file.
+    ///
+    /// The events are played through a terminal emulator, without waiting,
+    /// and the final contents of the screen are printed, so the end of a demo
+    /// can be checked without playing it.
+    Cat(Cat),
+
+    /// Renders frames of a sequence or asciicast file to PNG images.
+    ///
+    /// The events are played through a terminal emulator and the screen at
+    /// each --at time, or --every interval, is drawn with a bitmap font and
+    /// the colors of the theme, so screenshots in documentation match the
+    /// demo exactly.
+    Png(Png),
+
+    /// Renders a sequence or asciicast file to an mp4 or webm video.
+    ///
+    /// The frames are rendered like the png subcommand and piped to ffmpeg,
+    /// which must be installed, to encode the video.
+    Video(Video),
+
+    /// Re-times a recorded asciicast file with the pacing of germ.
+    ///
+    /// The commands are recovered from the lines of the recording containing
+    /// the -p,--prompt, and the rest of each line is the input. The commands
+    /// are written in the output format with the timings of the options given
+    /// before the subcommand, instead of the pauses and typos of a person.
+    Normalize(Normalize),
+
+    /// Executes the commands of a sequence file and compares their outputs.
+    ///
+    /// Each command is executed again and its output is compared with the
+    /// stored outputs, ignoring trailing newlines, as well as the exit code
+    /// with its status. A difference is printed as a diff and the exit code
+    /// is non-zero, so sequences can be used as tests of the documentation.
+    /// Remote, interrupted, secret, and REPL commands are skipped.
+    Verify(Verify),
+
+    /// Renders every sequence of a project into an output directory.
+    ///
+    /// The sources are discovered in the demos directory and each is written
+    /// in the formats declared by its render settings, or the project
+    /// configuration, into the build directory.
+    Build(Build),
+
+    /// Prints a completion script for a shell.
+    ///
+    /// The script completes the flags, options, and subcommands, as well as
+    /// the values of options with a fixed set, such as the -I,--input-format
+    /// and -O,--output-format formats and the --theme presets, i.e.
+    /// `germ completions bash > /etc/bash_completion.d/germ`.
+    Completions(Completions),
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(settings(&[
     clap::AppSettings::NoBinaryName,
+    clap::AppSettings::AllowExternalSubcommands,
     clap::AppSettings::DisableHelpFlags,
     clap::AppSettings::DisableVersion,
     clap::AppSettings::NextLineHelp]),
@@ -89,8 +272,13 @@ struct Interactive {
     version: bool,
 }
 
-#[derive(Debug, StructOpt)]
-#[structopt(about = "Generate terminal session recording files without rehearsing and recording")]
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(
+    about = "Generate terminal session recording files without rehearsing and recording",
+    // Only allows an exact subcommand name as the first positional argument,
+    // so inputs similar to a subcommand name are not rejected as typos.
+    setting = clap::AppSettings::AllowExternalSubcommands
+)]
 pub struct Cli {
     #[structopt(flatten)]
     timings: Timings,
@@ -98,16 +286,258 @@ pub struct Cli {
     #[structopt(flatten)]
     asciicast: Asciicast,
 
+    #[structopt(flatten)]
+    backend: Backend,
+
+    #[structopt(flatten)]
+    embed: Embed,
+
     /// A comment about the command.
     ///
     /// A line will be "printed" in the terminal session above the prompt and input.
     #[structopt(short, long)]
     comment: Option<String>,
 
+    /// The number of columns needed by the command.
+    ///
+    /// A resize event is added before the command and the terminal is resized
+    /// back to the -W,--width value afterwards, so only a wide command uses a
+    /// wide terminal.
+    #[structopt(long, value_name = "cols")]
+    command_width: Option<usize>,
+
+    /// The number of rows needed by the command.
+    ///
+    /// Like the --command-width option, the terminal is resized for the
+    /// command and back to the -H,--height value afterwards.
+    #[structopt(long, value_name = "rows")]
+    command_height: Option<usize>,
+
     /// The prompt to display before the command.
-    #[structopt(short = "p", long, default_value = DEFAULT_PROMPT, env = "GERM_PROMPT")]
+    ///
+    /// The prompt can be a template. Any "{cwd}" or "{dir}" is replaced with
+    /// the working directory, or its last component, tracking the `cd`
+    /// commands of the sequence from "~". Any "{git_branch}" is replaced
+    /// with the branch, tracking `git checkout` and `git switch` from "main".
+    /// Any "{status}" is replaced with the exit code of the previous command
+    /// and any "{status_mark}" with a red "✗ " if it failed.
+    ///
+    /// ANSI colors can be used by writing the escape character as "\e",
+    /// "\033", or "\x1b", i.e. "\e[32m$\e[0m ".
+    ///
+    /// The prompt is only for the appended command. In interactive mode, a
+    /// line without an input sets the prompt of the commands that follow.
+    #[structopt(
+        short = "p",
+        long,
+        default_value = DEFAULT_PROMPT,
+        parse(from_str = prompt::unescape),
+        env = "GERM_PROMPT"
+    )]
     prompt: String,
 
+    /// A built-in colored prompt to display before the command.
+    #[structopt(
+        long,
+        possible_values = PromptPresets::VARIANTS,
+        case_insensitive = true,
+        conflicts_with = "prompt",
+        value_name = "name",
+        env = "GERM_PROMPT_PRESET"
+    )]
+    prompt_preset: Option<PromptPresets>,
+
+    /// Displays the conventions of another shell, like PowerShell.
+    ///
+    /// The "powershell" flavor has the "PS C:\> " prompt, the ">> "
+    /// continuation prompt, the "xterm-256color" TERM of Windows Terminal,
+    /// and pasted inputs with CRLF line endings, so documentation for Windows
+    /// looks native.
+    #[structopt(
+        long,
+        possible_values = Flavors::VARIANTS,
+        case_insensitive = true,
+        conflicts_with_all = &["prompt", "prompt-preset", "continuation-prompt", "term"],
+        value_name = "shell",
+        env = "GERM_FLAVOR"
+    )]
+    flavor: Option<Flavors>,
+
+    /// Defines a named prompt, i.e. "name=prompt".
+    ///
+    /// Commands assigned to the persona with the --as option are displayed
+    /// with its prompt, so a demo can show more than one user or machine.
+    /// This can be used multiple times.
+    #[structopt(long, value_name = "name=prompt", parse(try_from_str = parse_persona), number_of_values = 1)]
+    persona: Vec<(String, String)>,
+
+    /// Runs the command on a remote host over SSH, i.e. "host" or "user@host".
+    ///
+    /// Consecutive remote commands for the same host are displayed as one SSH
+    /// session: `ssh host` is typed before the session, the commands use the
+    /// remote prompt, and `exit` is typed after the session.
+    #[structopt(long, value_name = "host")]
+    remote: Option<String>,
+
+    /// The working directory where the command is executed.
+    ///
+    /// The "{cwd}" and "{dir}" segments of the prompt display this directory,
+    /// so a demo can move between directories without typing `cd`.
+    #[structopt(long, value_name = "dir")]
+    cwd: Option<String>,
+
+    /// Displays the input all at once, like pasting it, instead of typing it.
+    ///
+    /// The input appears after the --delay-type-start delay, which is useful
+    /// for long inputs that would be tedious to watch being typed.
+    #[structopt(long)]
+    paste: bool,
+
+    /// Ends the command with Ctrl+C, i.e. "^C", and returns to the prompt.
+    ///
+    /// If there are no outputs, then the input is typed but canceled instead
+    /// of executed. Otherwise, the outputs are printed before the interrupt,
+    /// like canceling a long-running process.
+    #[structopt(long)]
+    interrupt: bool,
+
+    /// The exit code of the command.
+    ///
+    /// By default, this is the exit code of the executed input, if executed.
+    /// A non-zero exit code is displayed in the next prompt by the "{status}"
+    /// and "{status_mark}" segments, like a failed command in modern shells.
+    #[structopt(long, value_name = "code", allow_hyphen_values = true)]
+    status: Option<i32>,
+
+    /// Types a hidden input, like a password, after this prompt.
+    ///
+    /// The prompt is printed after the command is submitted, i.e. "Password:
+    /// ", and then the --secret-length characters are typed without printing
+    /// them, before the outputs.
+    #[structopt(long, value_name = "prompt")]
+    secret_prompt: Option<String>,
+
+    /// The number of characters of the hidden input.
+    #[structopt(long, default_value = DEFAULT_SECRET_LENGTH, value_name = "count")]
+    secret_length: usize,
+
+    /// Prints this character for each character of the hidden input, i.e. "*".
+    #[structopt(long, value_name = "char")]
+    secret_mask: Option<char>,
+
+    /// Waits for this prompt in the output of the executed input, i.e.
+    /// "[y/n]", and then types the response of the matching --send option.
+    ///
+    /// This can be used multiple times for a command that prompts more than
+    /// once. The prompts and the typed responses are displayed like the
+    /// input, so the interaction appears in the recording.
+    #[structopt(long, value_name = "text", number_of_values = 1, requires = "send")]
+    expect: Vec<String>,
+
+    /// The response typed, followed by a new line, for the matching --expect
+    /// prompt.
+    #[structopt(long, value_name = "text", number_of_values = 1, requires = "expect")]
+    send: Vec<String>,
+
+    /// The time to wait for the prompt of an --expect option, after the
+    /// last output of the executed input.
+    ///
+    /// The input is killed and fails if the prompt is not printed in time,
+    /// instead of waiting forever.
+    #[structopt(
+        long,
+        default_value = DEFAULT_RESPONSE_TIMEOUT,
+        value_name = SECONDS_UNITS,
+        env = "GERM_RESPONSE_TIMEOUT",
+        parse(try_from_str = delay::parse_seconds)
+    )]
+    response_timeout: Delay,
+
+    /// Adds a progress bar after the outputs filled over this duration.
+    ///
+    /// Each frame of the bar overwrites the previous one, like the progress
+    /// of a download or build.
+    #[structopt(
+        long,
+        value_name = SECONDS_UNITS,
+        parse(try_from_str = delay::parse_seconds)
+    )]
+    progress: Option<Delay>,
+
+    /// The text printed before the progress bar, i.e. "Downloading".
+    #[structopt(long, value_name = "label", default_value = "")]
+    progress_label: String,
+
+    /// Adds a spinner after the outputs displayed for this duration.
+    ///
+    /// The spinner is erased when done, like the spinner of a command that is
+    /// waiting on a server.
+    #[structopt(
+        long,
+        value_name = SECONDS_UNITS,
+        parse(try_from_str = delay::parse_seconds)
+    )]
+    spinner: Option<Delay>,
+
+    /// The text printed after the spinner, i.e. "Waiting".
+    #[structopt(long, value_name = "label", default_value = "")]
+    spinner_label: String,
+
+    /// The characters of the spinner in order, i.e. "|/-\".
+    #[structopt(long, value_name = "chars")]
+    spinner_frames: Option<String>,
+
+    /// Replaces each match of this regular expression with "[REDACTED]".
+    ///
+    /// The comments, inputs, and outputs are redacted before anything is
+    /// written, so credentials never land in a published recording. If the
+    /// pattern has a group named "secret", i.e. "token=(?P<secret>\w+)",
+    /// then only the group is replaced. This can be used multiple times.
+    #[structopt(long, value_name = "regex", number_of_values = 1)]
+    redact: Vec<Regex>,
+
+    /// Removes ANSI escape sequences, like colors, from the outputs.
+    ///
+    /// This is useful for formats without colors, like TermSheets, where the
+    /// escape sequences are displayed as garbage.
+    #[structopt(long)]
+    strip_ansi: bool,
+
+    /// Reads --highlight rules from a file, one per line.
+    ///
+    /// Empty lines and lines starting with "#" are skipped. The rules of the
+    /// file come after any --highlight options.
+    #[structopt(
+        long,
+        value_name = "file",
+        parse(from_os_str),
+        env = "GERM_HIGHLIGHT_FILE"
+    )]
+    highlight_file: Option<PathBuf>,
+
+    /// Adds the inputs to the captions of the webvtt and srt output formats.
+    ///
+    /// Each input is displayed with its prompt after the comment of its
+    /// command, if any, and commands without a comment are captioned too.
+    #[structopt(long)]
+    caption_inputs: bool,
+
+    /// Redacts built-in patterns for common credentials.
+    ///
+    /// This can be used multiple times.
+    #[structopt(
+        long,
+        possible_values = RedactPresets::VARIANTS,
+        case_insensitive = true,
+        value_name = "name",
+        number_of_values = 1
+    )]
+    redact_preset: Vec<RedactPresets>,
+
+    /// Assigns the command to a persona defined with the --persona option.
+    #[structopt(long = "as", value_name = "persona")]
+    as_persona: Option<String>,
+
     /// The prompt displayed in interactive mode.
     #[structopt(short ="P", long, default_value = DEFAULT_INTERACTIVE_PROMPT, env = "GERM_INTERACTIVE_PROMPT")]
     interactive_prompt: String,
@@ -130,6 +560,15 @@ pub struct Cli {
     #[structopt(long)]
     warranty: bool,
 
+    /// Checks the input without writing any output.
+    ///
+    /// The input file, or stdin, is parsed according to the input format and
+    /// any error is reported with its line, column, and field. Nothing is
+    /// written and the exit code is non-zero if the input is invalid. This is
+    /// useful for checking hand-edited files in CI.
+    #[structopt(long)]
+    validate: bool,
+
     /// The format of the input.
     #[structopt(
         short = "I",
@@ -144,22 +583,132 @@ pub struct Cli {
 
     /// Input file in the commands JSON format.
     ///
-    /// If not present, then stdin if it is piped or redirected.
-    #[structopt(short = "i", long = "input", value_name("file"), parse(from_os_str))]
-    input_file: Option<PathBuf>,
+    /// If not present, then stdin if it is piped or redirected. This can be
+    /// used multiple times to assemble a sequence from fragment files, where
+    /// the commands of each file are appended in order and the timings and
+    /// render settings of the first file are used.
+    #[structopt(
+        short = "i",
+        long = "input",
+        value_name("file"),
+        parse(from_os_str),
+        number_of_values = 1
+    )]
+    input_files: Vec<PathBuf>,
+
+    /// Reads the output of the command from a file, or stdin for "-".
+    ///
+    /// This is useful for piping the output of a program instead of quoting
+    /// it, i.e. `mytool --help | germ --outputs-from - "mytool --help"`. The
+    /// sequence is not read from stdin if it is used for the output.
+    #[structopt(
+        long,
+        value_name = "file",
+        parse(from_os_str),
+        requires = "input",
+        conflicts_with = "outputs"
+    )]
+    outputs_from: Option<PathBuf>,
+
+    /// Expands the environment variables in the input, i.e. "$HOME", with
+    /// their values in germ before executing it or typing it.
+    ///
+    /// With "execute", the input is typed literally but executed with the
+    /// values, which keeps the literal form on screen for tutorials. With
+    /// "all", the values are also typed. With "none", the input is passed to
+    /// the shell as written.
+    #[structopt(
+        long,
+        possible_values = EnvExpansions::VARIANTS,
+        case_insensitive = true,
+        value_name = "where",
+        env = "GERM_EXPAND_ENV",
+        default_value = "none"
+    )]
+    expand_env: EnvExpansions,
+
+    /// Fails when an executed input exits with a non-zero code.
+    ///
+    /// Nothing is written and germ exits with a non-zero code, so a broken
+    /// demo is not published by CI. An input with the --status option may
+    /// exit with any code. The verify subcommand stops at the first command
+    /// that does not match.
+    #[structopt(long, global = true)]
+    fail_fast: bool,
+
+    /// Prints what germ is doing to stderr.
+    ///
+    /// This can be used multiple times for more details: "-v" for each
+    /// executed input, the shell executing it, how long it took, and the
+    /// files written, "-vv" for the full command lines, and "-vvv" for
+    /// everything. The GERM_LOG environment variable, i.e. "germ=debug",
+    /// overrides this flag.
+    #[structopt(short, long, parse(from_occurrences), global = true)]
+    verbose: u8,
+
+    /// Prints only errors to stderr.
+    #[structopt(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Executes an input that fails, or exits with a non-zero code, again up
+    /// to this many times.
+    ///
+    /// This is useful for inputs that use the network, like installing a
+    /// package. A command in a sequence file can have its own "retries".
+    #[structopt(long, default_value = "0", value_name = "N", env = "GERM_RETRIES")]
+    retries: usize,
+
+    /// The time to wait before executing a failed input again.
+    #[structopt(
+        long,
+        default_value = DEFAULT_RETRY_DELAY,
+        value_name = SECONDS_UNITS,
+        env = "GERM_RETRY_DELAY",
+        parse(try_from_str = delay::parse_seconds)
+    )]
+    retry_delay: Delay,
+
+    /// Records the partial outputs of an input interrupted with Ctrl+C.
+    ///
+    /// Ctrl+C stops the executed input instead of germ. By default, the
+    /// interrupted input is not recorded, and germ returns to the prompt in
+    /// interactive mode or fails otherwise. With this flag, the outputs so far
+    /// are recorded and the command ends with "^C", like the --interrupt flag.
+    #[structopt(long, global = true)]
+    keep_interrupted: bool,
 
     /// The format for the output.
+    ///
+    /// If not present, then the formats declared in the "render" settings of
+    /// the input file, or asciicast. With more than one declared format and
+    /// the -o,--output option, a file is written for each format with the
+    /// extension of the format.
+    ///
+    /// The webvtt and srt formats are captions of the comments, timed like
+    /// the asciicast, for a video of the demo, see --caption-inputs.
+    ///
+    /// The chapters and ffmetadata formats are the start and end of each
+    /// command, titled with its comment or input, as JSON or FFmpeg metadata
+    /// for the chapters of a video of the demo.
+    ///
+    /// The transcript format is plain text with the time of each command
+    /// before its input, i.e. "[01:05] $ ls", followed by its outputs, for
+    /// screen readers and search engines.
+    ///
+    /// The embed format is an HTML snippet that plays the cast file with
+    /// asciinema-player, see the --embed-* options, so the "asciicast" and
+    /// "embed" render formats write a page-ready pair of files.
     #[structopt(
         short = "O",
         long,
         possible_values = OutputFormats::VARIANTS,
         case_insensitive = true,
-        default_value,
         default_value_if("use-germ-format", None, "germ"),
         value_name = "format",
-        env = "GERM_OUTPUT_FORMAT"
+        env = "GERM_OUTPUT_FORMAT",
+        global = true
     )]
-    output_format: OutputFormats,
+    output_format: Option<OutputFormats>,
 
     /// Output file, stdout if not present.
     ///
@@ -167,6 +716,80 @@ pub struct Cli {
     #[structopt(short = "o", long = "output", value_name("file"), parse(from_os_str))]
     output_file: Option<PathBuf>,
 
+    /// Compresses the output with gzip or zstd.
+    ///
+    /// If not present, then an output file with the ".gz" or ".zst" extension
+    /// is compressed with gzip or zstd, respectively.
+    #[structopt(
+        long,
+        possible_values = Compressions::VARIANTS,
+        case_insensitive = true,
+        value_name = "format",
+        env = "GERM_COMPRESS"
+    )]
+    compress: Option<Compressions>,
+
+    /// Appends to the output file, if it exists, instead of overwriting it.
+    ///
+    /// For the asciicast format, the new events follow the last event of the
+    /// file and its header is kept. For the other formats, the new commands
+    /// follow the commands of the file. This builds a demo one command at a
+    /// time across invocations.
+    #[structopt(short = "a", long, requires = "output-file")]
+    append: bool,
+
+    /// The number of files converted or built at the same time.
+    ///
+    /// The convert and build subcommands process their files on this many
+    /// threads. The files are still reported in order.
+    #[structopt(
+        short = "j",
+        long,
+        default_value = "1",
+        value_name = "N",
+        env = "GERM_JOBS",
+        global = true
+    )]
+    jobs: NonZeroUsize,
+
+    /// Writes the output again each time an input file is modified.
+    ///
+    /// The -i,--input files are checked for changes until interrupted with
+    /// CTRL+C (^C). An invalid input is reported and the previous output is
+    /// kept, so a sequence can be edited while previewing the output file in
+    /// a player.
+    #[structopt(
+        long,
+        requires_all = &["input-files", "output-file"],
+        conflicts_with_all = &["append", "validate"]
+    )]
+    watch: bool,
+
+    /// Writes a JSON manifest of every file written.
+    ///
+    /// Each entry has the path, format, playback duration in seconds, and the
+    /// SHA-256 hash of the source sequence, so build pipelines can consume the
+    /// generated files programmatically.
+    #[structopt(long, value_name("file"), parse(from_os_str))]
+    manifest: Option<PathBuf>,
+
+    /// Streams the asciicast events to a WebSocket as they are generated.
+    ///
+    /// The URL must be "ws://host[:port][/path]". Each line of the recording
+    /// is sent as a text message, so a live asciinema-player connected to the
+    /// same server updates while commands are entered in interactive mode.
+    /// germ is only a client, and the server relaying the messages to the
+    /// player is run separately.
+    #[structopt(long, value_name("url"), env = "GERM_STREAM")]
+    stream: Option<String>,
+
+    #[structopt(skip)]
+    artifacts: Manifest,
+
+    /// The inputs executed in a child shell process to obtain their outputs.
+    #[structopt(skip)]
+    executed: Vec<String>,
+
     /// The command entered at the prompt.
     ///
     /// If not present and the -i,--input option is not used, then the
@@ -176,17 +799,45 @@ pub struct Cli {
     ///
     /// Note, if present without any output, then the input will be executed
     /// within a child shell process and the execution output will be used.
+    ///
+    /// A "{tab}" or "⇥" marker simulates tab completion: the text
+    /// before it is typed, and the text after it, up to the next whitespace,
+    /// is printed at once after a pause.
     input: Option<String>,
 
     /// Output from the command.
     ///
     /// If no output is provided, then the input will be execute within a child
     /// shell process and execution output will be used.
+    ///
+    /// An output of the form "@path" is the contents of the file, i.e. output
+    /// captured earlier with colors. Use "@@" for an output that starts with
+    /// "@".
     outputs: Vec<String>,
+
+    #[structopt(subcommand)]
+    subcommand: Option<Subcommand>,
 }
 
 impl Cli {
     pub fn execute(mut self) -> Result<()> {
+        self.timings = self.timings.profiled();
+        if let Some(preset) = self.prompt_preset {
+            self.prompt = preset.prompt().to_owned();
+        }
+        self.init_logging();
+        interrupt::install();
+        if let Some(flavor) = self.flavor {
+            self.prompt = flavor.prompt().to_owned();
+            self.asciicast.continuation_prompt = flavor.continuation_prompt().to_owned();
+            self.asciicast.header.env.term = flavor.term().to_owned();
+            self.asciicast.crlf = flavor.crlf();
+        }
+        if let Some(highlight_file) = &self.highlight_file {
+            let rules = highlight::read_rules(highlight_file)
+                .with_context(|| format!("Failed to read '{}'", highlight_file.display()))?;
+            self.asciicast.highlight.extend(rules);
+        }
         if self.license {
             print_license();
             return Ok(());
@@ -195,74 +846,320 @@ impl Cli {
             print_warranty();
             return Ok(());
         }
+        if let Some(subcommand) = self.subcommand.take() {
+            match subcommand {
+                Subcommand::Convert(convert) => convert.execute(&mut self)?,
+                Subcommand::Info(info) => info.execute(&mut self)?,
+                Subcommand::Build(build) => build.execute(&mut self)?,
+                Subcommand::Concat(concat) => concat.execute(&mut self)?,
+                Subcommand::Cut(cut) => cut.execute(&mut self)?,
+                Subcommand::Cat(cat) => cat.execute(&mut self)?,
+                Subcommand::Png(png) => png.execute(&mut self)?,
+                Subcommand::Video(video) => video.execute(&mut self)?,
+                Subcommand::Normalize(normalize) => normalize.execute(&mut self)?,
+                Subcommand::Verify(verify) => verify.execute(&mut self)?,
+                Subcommand::Completions(completions) => return completions.execute(),
+            }
+            return self.write_manifest();
+        }
+        if self.validate {
+            return self.validate();
+        }
+        self.redact
+            .extend(self.redact_preset.iter().map(RedactPresets::regex));
+        if self.watch {
+            return self.watch();
+        }
+        self.generate()
+    }
+
+    /// Reads the input, appends any commands, and writes the output.
+    fn generate(&mut self) -> Result<()> {
         let mut sequence = self.read()?;
+        for (name, prompt) in &self.persona {
+            sequence.add_persona(name, prompt);
+        }
         self.append(&mut sequence)?;
-        self.write(sequence)
+        sequence.redact(&self.redact);
+        if self.strip_ansi {
+            sequence.strip_ansi();
+        }
+        if !self.is_interactive() {
+            if let Some(mut recorder) = self.stream(sequence.timings())? {
+                let framed = sequence.framed(&self.asciicast.ssh_banner);
+                for command in framed.iter() {
+                    recorder.record(&with_prompt_of(&framed, command))?;
+                }
+                recorder.finish()?;
+            }
+        }
+        if self.asciicast.header.command.is_none()
+            && self.executed.len() == 1
+            && sequence.iter().count() == 1
+        {
+            self.asciicast.header.command = self
+                .executed
+                .pop()
+                .map(|c| redact::redact(&c, &self.redact));
+        }
+        let formats = self.render(&sequence)?;
+        self.write(sequence, &formats)?;
+        self.write_manifest()
+    }
+
+    /// Generates the output, and again each time an input file is modified,
+    /// until interrupted.
+    fn watch(&mut self) -> Result<()> {
+        let output_file = self.output_file.clone().expect("Required output file");
+        let mut modified = self.modified_times();
+        loop {
+            match self.generate() {
+                Ok(()) => eprintln!("Wrote '{}'", output_file.display()),
+                Err(err) => eprintln!("Error: {:#}", err),
+            }
+            loop {
+                thread::sleep(WATCH_INTERVAL);
+                let current = self.modified_times();
+                if current != modified {
+                    // Editors may write a file in more than one step.
+                    thread::sleep(WATCH_INTERVAL);
+                    modified = self.modified_times();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The last modification time of each input file, if available.
+    fn modified_times(&self) -> Vec<Option<SystemTime>> {
+        self.input_files
+            .iter()
+            .map(|p| p.metadata().and_then(|m| m.modified()).ok())
+            .collect()
     }
 
     fn read(&self) -> Result<Sequence> {
-        if let Some(input_file) = &self.input_file {
-            self.read_from(File::open(input_file)?)
-        } else if atty::is(Stream::Stdin) {
+        if let Some((first, rest)) = self.input_files.split_first() {
+            let mut sequence = self.read_file(first)?;
+            for input_file in rest {
+                sequence.append_from(self.read_file(input_file)?);
+            }
+            Ok(sequence)
+        } else if atty::is(Stream::Stdin) || self.outputs_from_stdin() {
             Ok(Sequence::from(self.timings))
         } else {
             let stdin = io::stdin();
-            self.read_from(stdin)
+            self.read_from(stdin, None)
         }
     }
 
-    fn read_from<R: Read>(&self, mut r: R) -> Result<Sequence> {
+    fn read_file(&self, input_file: &Path) -> Result<Sequence> {
+        self.read_from(File::open(input_file)?, Some(input_file))
+            .with_context(|| format!("Failed to read '{}'", input_file.display()))
+    }
+
+    fn outputs_from_stdin(&self) -> bool {
+        self.outputs_from.as_deref() == Some(Path::new("-"))
+    }
+
+    fn read_from<R: Read>(&self, mut r: R, path: Option<&Path>) -> Result<Sequence> {
         let mut buffer = Vec::new();
         r.read_to_end(&mut buffer)?;
+        let buffer = compress::decode(buffer)?;
         if !buffer.is_empty() {
-            match self.input_format {
-                InputFormats::Germ => serde_json::from_slice(&buffer).map_err(anyhow::Error::from),
-                InputFormats::TermSheets => {
-                    let termsheets: Vec<crate::termsheets::Command> =
-                        serde_json::from_slice(&buffer)?;
-                    let mut sequence = Sequence::from(self.timings);
-                    sequence.append(
-                        &mut termsheets
-                            .into_iter()
-                            .map(|c| {
-                                let mut cmd = Command::from(c);
-                                cmd.set_prompt(&self.prompt);
-                                cmd
-                            })
-                            .collect(),
-                    );
-                    Ok(sequence)
-                }
-            }
+            self.parse(&buffer, path)
         } else {
             Ok(Sequence::from(self.timings))
         }
     }
 
+    /// Parses a sequence read from the path, or from stdin if there is no
+    /// path. The included files of a germ sequence are relative to the path,
+    /// or to the current working directory for stdin.
+    fn parse(&self, buffer: &[u8], path: Option<&Path>) -> Result<Sequence> {
+        match self.input_format {
+            InputFormats::Germ => {
+                let mut sequence =
+                    Sequence::from_slice(buffer).map_err(|err| diagnose(err, buffer, path))?;
+                let mut stack = path
+                    .and_then(|p| p.canonicalize().ok())
+                    .into_iter()
+                    .collect();
+                self.include(&mut sequence, path, &mut stack)?;
+                sequence.check_personas()?;
+                Ok(sequence)
+            }
+            InputFormats::TermSheets => {
+                let termsheets =
+                    termsheets::from_slice(buffer).map_err(|err| diagnose(err, buffer, path))?;
+                let mut sequence = Sequence::from(self.timings);
+                sequence.append(
+                    &mut termsheets
+                        .into_iter()
+                        .map(|c| {
+                            let mut cmd = Command::from(c);
+                            cmd.set_prompt(&self.prompt);
+                            cmd
+                        })
+                        .collect(),
+                );
+                Ok(sequence)
+            }
+            InputFormats::Asciicast => {
+                bail!("The asciicast input format is only supported with the --validate flag")
+            }
+        }
+    }
+
+    /// Inserts the commands of the included files before the commands of the
+    /// sequence, including the files of the included files.
+    ///
+    /// The stack is the canonical paths of the files being included, to
+    /// detect a file that includes itself.
+    fn include(
+        &self,
+        sequence: &mut Sequence,
+        path: Option<&Path>,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        let dir = path.and_then(Path::parent).unwrap_or_else(|| Path::new(""));
+        let mut included = Sequence::default();
+        for include in sequence.take_includes() {
+            let include = dir.join(include);
+            let canonical = include
+                .canonicalize()
+                .with_context(|| format!("Failed to include '{}'", include.display()))?;
+            if stack.contains(&canonical) {
+                bail!("'{}' includes itself", include.display());
+            }
+            let buffer = compress::decode(std::fs::read(&include)?)?;
+            let mut s = Sequence::from_slice(&buffer)
+                .with_context(|| format!("Failed to include '{}'", include.display()))?;
+            stack.push(canonical);
+            self.include(&mut s, Some(&include), stack)?;
+            stack.pop();
+            included.append_from(s);
+        }
+        sequence.prepend_from(included);
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.input_files.is_empty() {
+            return self.validate_input(None);
+        }
+        for input_file in &self.input_files {
+            self.validate_input(Some(input_file))?;
+        }
+        Ok(())
+    }
+
+    fn validate_input(&self, input_file: Option<&Path>) -> Result<()> {
+        let buffer = read_input(input_file)?;
+        match self.input_format {
+            InputFormats::Asciicast => Asciicast::read_from(&buffer[..])
+                .map(|_| ())
+                .map_err(anyhow::Error::from),
+            _ => self.parse(&buffer, input_file).map(|_| ()),
+        }
+        .with_context(|| match input_file {
+            Some(input_file) => format!("Invalid input '{}'", input_file.display()),
+            None => String::from("Invalid input"),
+        })
+    }
+
     fn append(&mut self, sequence: &mut Sequence) -> Result<()> {
-        if let Some(input) = self.input.as_ref() {
-            self.append_arguments(sequence, input)
-        } else if self.input_file.is_none() && atty::is(Stream::Stdin) {
+        if let Some(input) = self.input.clone() {
+            self.append_arguments(sequence, &input)
+        } else if self.is_interactive() {
             self.append_interactively(sequence)
         } else {
             Ok(())
         }
     }
 
-    fn append_arguments(&self, sequence: &mut Sequence, input: &str) -> Result<()> {
-        let mut outputs = if self.outputs.is_empty() {
-            let output = self.execute_cmd(input)?;
-            vec![std::str::from_utf8(&output.stdout)?.to_owned()]
+    fn is_interactive(&self) -> bool {
+        self.input.is_none() && self.input_files.is_empty() && atty::is(Stream::Stdin)
+    }
+
+    /// Connects a recorder to the --stream URL, if any.
+    fn stream(&self, timings: &Timings) -> Result<Option<Recorder<WebSocket>>> {
+        self.stream
+            .as_deref()
+            .map(|url| Recorder::new(self.asciicast.clone(), *timings, WebSocket::connect(url)?))
+            .transpose()
+    }
+
+    fn append_arguments(&mut self, sequence: &mut Sequence, input: &str) -> Result<()> {
+        let responses = responses(
+            self.expect.iter().map(String::as_str),
+            self.send.iter().map(String::as_str),
+        )?;
+        let mut status = self.status;
+        let mut interrupted = false;
+        let mut outputs = if let Some(path) = &self.outputs_from {
+            vec![read_outputs_from(path)?]
+        } else if self.interrupt && self.outputs.is_empty() {
+            Vec::new()
+        } else if self.outputs.is_empty() {
+            let executed = self.execute_cmd(input, self.cwd.as_deref(), &responses, None, false)?;
+            interrupted = executed.interrupted;
+            if interrupted && !self.keep_interrupted {
+                bail!("The input '{}' was interrupted", input);
+            }
+            if self.fail_fast && status.is_none() && !interrupted && executed.code != Some(0) {
+                bail!(
+                    "The input '{}' failed with {}",
+                    input,
+                    exit_code(executed.code, executed.signal)
+                );
+            }
+            self.executed.push(sequence::strip_tabs(input));
+            if !interrupted {
+                status = status.or(executed.code);
+            }
+            vec![executed.output]
         } else {
-            self.outputs.clone()
+            self.outputs
+                .iter()
+                .map(|o| read_output(o))
+                .collect::<Result<Vec<String>>>()?
         };
         sequence.add({
-            let mut cmd = Command::from(input);
+            let mut cmd = Command::from(self.typed(input));
             cmd.set_comment(self.comment.as_deref());
+            cmd.set_width(self.command_width);
+            cmd.set_height(self.command_height);
             cmd.set_prompt(&self.prompt);
-            cmd.append(&mut outputs);
+            cmd.set_persona(self.as_persona.as_deref());
+            cmd.set_remote(self.remote.as_deref());
+            cmd.set_cwd(self.cwd.as_deref());
+            cmd.set_paste(self.paste);
+            cmd.set_interrupt(self.interrupt || interrupted);
+            cmd.set_status(status);
+            cmd.set_secret(self.secret_prompt.as_ref().map(|prompt| Secret {
+                prompt: prompt.clone(),
+                length: self.secret_length,
+                mask: self.secret_mask,
+            }));
+            cmd.set_responses(responses);
+            cmd.append_responded(&mut outputs)?;
+            if let Some(duration) = self.progress {
+                cmd.add_output(Output::Progress(Progress::new(
+                    duration,
+                    &self.progress_label,
+                )));
+            }
+            if let Some(duration) = self.spinner {
+                let mut spinner = Spinner::new(duration, &self.spinner_label);
+                if let Some(frames) = &self.spinner_frames {
+                    spinner.set_frames(frames);
+                }
+                cmd.add_output(Output::Spinner(spinner));
+            }
             cmd
         });
+        sequence.check_personas()?;
         Ok(())
     }
 
@@ -270,6 +1167,8 @@ impl Cli {
         print_interactive_notice();
         println!();
         let mut stdout = io::stdout();
+        let mut recorder = self.stream(sequence.timings())?;
+        let mut recorded = record_new(recorder.as_mut(), sequence, 0)?;
         stdout.write_all(self.interactive_prompt.as_bytes())?;
         stdout.flush()?;
         for line in io::stdin().lock().lines() {
@@ -293,120 +1192,502 @@ impl Cli {
                         print_license();
                     } else if matches.is_present("warranty") {
                         print_warranty();
+                    } else if matches.subcommand_name().is_some() {
+                        eprintln!("Subcommands are not available in interactive mode");
                     } else if matches.is_present("print") {
-                        self.write_to(&mut stdout, sequence)?;
-                        if !matches!(self.output_format, OutputFormats::Asciicast) {
+                        self.write_to(&mut stdout, sequence, None)?;
+                        if !matches!(
+                            self.output_format.unwrap_or_default(),
+                            OutputFormats::Asciicast
+                        ) {
                             stdout.write_all(b"\n")?;
                         }
+                    } else if let Err(err) = self.update_from(&matches) {
+                        eprintln!("{:#}", err);
                     } else {
-                        self.update_from(&matches);
-                        if let Some(input_file) = matches.value_of("input-file").map(PathBuf::from)
-                        {
-                            sequence.append_from(
-                                self.read_from(BufReader::new(File::open(input_file)?))?,
-                            );
+                        for persona in matches.values_of("persona").into_iter().flatten() {
+                            let (name, prompt) = parse_persona(persona)?;
+                            sequence.add_persona(&name, &prompt);
+                        }
+                        for input_file in matches.values_of("input-files").into_iter().flatten() {
+                            sequence.append_from(self.read_file(Path::new(input_file))?);
                         }
-                        if let Some(input) = matches.value_of("input") {
-                            let mut outputs = if matches.is_present("outputs") {
+                        if let Some(persona) = matches
+                            .value_of("as-persona")
+                            .filter(|p| sequence.persona(p).is_none())
+                        {
+                            eprintln!("Unknown persona '{}'", persona);
+                        } else if matches.value_of("outputs-from") == Some("-") {
+                            eprintln!("Outputs from stdin are not available in interactive mode");
+                        } else if let Some(input) = matches.value_of("input") {
+                            let responses = responses(
+                                matches.values_of("expect").unwrap_or_default(),
+                                matches.values_of("send").unwrap_or_default(),
+                            )?;
+                            let mut status = value_t!(matches, "status", i32).ok();
+                            // The retries of a line are for its command only.
+                            let retries = Some(matches.occurrences_of("retries"))
+                                .filter(|n| *n != 0)
+                                .and_then(|_| value_t!(matches, "retries", usize).ok());
+                            let mut interrupted = false;
+                            let mut outputs = if let Some(path) = matches.value_of("outputs-from") {
+                                vec![read_outputs_from(Path::new(path))?]
+                            } else if matches.is_present("outputs") {
                                 matches
                                     .values_of("outputs")
                                     .unwrap()
-                                    .map(String::from)
-                                    .collect()
+                                    .map(read_output)
+                                    .collect::<Result<Vec<String>>>()?
+                            } else if matches.is_present("interrupt") {
+                                Vec::new()
                             } else {
-                                let output = self.execute_cmd(input)?;
-                                stdout.write_all(&output.stdout)?;
-                                vec![std::str::from_utf8(&output.stdout)?.to_owned()]
+                                let executed = self.execute_cmd(
+                                    input,
+                                    matches.value_of("cwd"),
+                                    &responses,
+                                    retries,
+                                    true,
+                                )?;
+                                self.executed.push(sequence::strip_tabs(input));
+                                interrupted = executed.interrupted;
+                                if !interrupted {
+                                    status = status.or(executed.code);
+                                }
+                                vec![executed.output]
                             };
-                            sequence.add({
-                                let mut cmd = Command::from(input);
-                                cmd.set_comment(
-                                    matches.value_of("comment").map(String::from).as_deref(),
-                                );
-                                cmd.set_prompt(&self.prompt);
-                                cmd.append(&mut outputs);
-                                cmd
-                            });
+                            if interrupted && !self.keep_interrupted {
+                                eprintln!("The input '{}' was interrupted and not recorded", input);
+                            } else {
+                                sequence.add({
+                                    let mut cmd = Command::from(self.typed(input));
+                                    cmd.set_comment(
+                                        matches.value_of("comment").map(String::from).as_deref(),
+                                    );
+                                    cmd.set_width(value_t!(matches, "command-width", usize).ok());
+                                    cmd.set_height(value_t!(matches, "command-height", usize).ok());
+                                    cmd.set_persona(matches.value_of("as-persona"));
+                                    cmd.set_remote(matches.value_of("remote"));
+                                    cmd.set_cwd(matches.value_of("cwd"));
+                                    cmd.set_retries(retries);
+                                    cmd.set_paste(matches.is_present("paste"));
+                                    cmd.set_interrupt(
+                                        matches.is_present("interrupt") || interrupted,
+                                    );
+                                    cmd.set_status(status);
+                                    cmd.set_secret(matches.value_of("secret-prompt").map(
+                                        |prompt| {
+                                            Secret {
+                                                prompt: prompt.to_owned(),
+                                                length: value_t!(matches, "secret-length", usize)
+                                                    .unwrap_or(self.secret_length),
+                                                mask: value_t!(matches, "secret-mask", char).ok(),
+                                            }
+                                        },
+                                    ));
+                                    cmd.set_prompt(
+                                        &prompt_from(&matches)
+                                            .unwrap_or_else(|| self.prompt.clone()),
+                                    );
+                                    cmd.set_responses(responses);
+                                    cmd.append_responded(&mut outputs)?;
+                                    if let Some(duration) = matches
+                                        .value_of("progress")
+                                        .and_then(|v| delay::parse_seconds(v).ok())
+                                    {
+                                        cmd.add_output(Output::Progress(Progress::new(
+                                            duration,
+                                            matches.value_of("progress-label").unwrap_or_default(),
+                                        )));
+                                    }
+                                    if let Some(duration) = matches
+                                        .value_of("spinner")
+                                        .and_then(|v| delay::parse_seconds(v).ok())
+                                    {
+                                        let mut spinner = Spinner::new(
+                                            duration,
+                                            matches.value_of("spinner-label").unwrap_or_default(),
+                                        );
+                                        if let Some(frames) = matches.value_of("spinner-frames") {
+                                            spinner.set_frames(frames);
+                                        }
+                                        cmd.add_output(Output::Spinner(spinner));
+                                    }
+                                    cmd
+                                });
+                            }
                         }
                     }
                 }
                 Err(err) => eprintln!("{}", err),
             }
+            sequence.redact(&self.redact);
+            if self.strip_ansi {
+                sequence.strip_ansi();
+            }
+            recorded = record_new(recorder.as_mut(), sequence, recorded)?;
             stdout.write_all(self.interactive_prompt.as_bytes())?;
             stdout.flush()?;
         }
+        if let Some(recorder) = recorder {
+            recorder.finish()?;
+        }
         stdout.write_all(b"\n")?;
         stdout.flush()?;
         Ok(())
     }
 
-    fn write(&mut self, sequence: Sequence) -> Result<()> {
-        let writer: Box<dyn Write> = if let Some(output_file) = &self.output_file {
-            Box::new(File::create(output_file)?)
+    /// Applies the render settings of the sequence that are not overridden
+    /// by options and returns the output formats.
+    fn render(&mut self, sequence: &Sequence) -> Result<Vec<OutputFormats>> {
+        let render = match sequence.render() {
+            Some(render) => render,
+            None => return Ok(vec![self.output_format.unwrap_or_default()]),
+        };
+        if let Some(theme) = &render.theme {
+            if self.asciicast.theme.theme.is_none() && self.asciicast.theme.theme_file.is_none() {
+                self.asciicast.theme.theme = Some(
+                    theme
+                        .parse()
+                        .map_err(|_| anyhow!("render: unknown theme '{}'", theme))?,
+                );
+            }
+        }
+        match self.output_format {
+            Some(format) => Ok(vec![format]),
+            None if render.formats.is_empty() => Ok(vec![OutputFormats::default()]),
+            None => render
+                .formats
+                .iter()
+                .map(|f| {
+                    f.parse()
+                        .map_err(|_| anyhow!("render: unsupported format '{}'", f))
+                })
+                .collect(),
+        }
+    }
+
+    fn write(&mut self, sequence: Sequence, formats: &[OutputFormats]) -> Result<()> {
+        for format in formats {
+            self.output_format = Some(*format);
+            match self.output_file.clone() {
+                Some(output_file) if formats.len() > 1 => {
+                    self.write_file(&output_file.with_extension(format.extension()), &sequence)?
+                }
+                Some(output_file) => self.write_file(&output_file, &sequence)?,
+                None => {
+                    let mut stdout = Encoder::new(io::stdout(), self.compress)?;
+                    self.write_to(&mut stdout, &sequence, None)?;
+                    stdout.finish()?;
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_file(&mut self, path: &Path, sequence: &Sequence) -> Result<()> {
+        // The snippet of an appended cast file is the same.
+        let embed = matches!(self.output_format, Some(OutputFormats::Embed));
+        if self.append && path.exists() && !embed {
+            if self.compression(path).is_some() {
+                bail!("Appending to a compressed file is not supported");
+            }
+            if let Some(
+                format @ (OutputFormats::WebVtt
+                | OutputFormats::Srt
+                | OutputFormats::Chapters
+                | OutputFormats::FfMetadata
+                | OutputFormats::Transcript),
+            ) = self.output_format
+            {
+                bail!("Appending to a {} file is not supported", format);
+            }
+            let duration = self
+                .append_file(path, sequence)
+                .with_context(|| format!("Failed to append to '{}'", path.display()))?;
+            if self.manifest.is_some() {
+                self.record(path, duration, &serde_json::to_vec(sequence)?);
+            }
+            return Ok(());
+        }
+        let mut file = self.create(path)?;
+        self.write_to(&mut file, sequence, Some(path))?;
+        file.finish()?;
+        if self.manifest.is_some() {
+            let duration = self.asciicast.clear().append_from(sequence).duration();
+            self.record(path, duration, &serde_json::to_vec(sequence)?);
+        }
+        Ok(())
+    }
+
+    /// Appends the sequence to the file and returns the duration of the
+    /// whole file.
+    fn append_file(&mut self, path: &Path, sequence: &Sequence) -> Result<f64> {
+        info!("Appending to '{}'", path.display());
+        let format = self.output_format.unwrap_or_default();
+        let duration = if let OutputFormats::Asciicast = format {
+            let existing = Asciicast::read_from(BufReader::new(File::open(path)?))?;
+            self.asciicast.header.width = existing.header.width;
+            self.asciicast.header.height = existing.header.height;
+            self.asciicast.clear().append_at(
+                sequence,
+                existing.duration() + sequence.timings().begin.as_secs_f64(),
+            );
+            if self.asciicast.merge_events {
+                self.asciicast.merge();
+            }
+            let duration = existing.duration().max(self.asciicast.duration());
+            let mut file = OpenOptions::new().append(true).open(path)?;
+            for mut event in self.asciicast.drain() {
+                event.write_to(&mut file)?;
+            }
+            duration
         } else {
-            Box::new(io::stdout())
+            let buffer = std::fs::read(path)?;
+            let mut existing = match format {
+                OutputFormats::TermSheets => Sequence::from(termsheets::from_slice(&buffer)?),
+                _ => Sequence::from_slice(&buffer)?,
+            };
+            existing.append_from(sequence.clone());
+            self.write_to(File::create(path)?, &existing, Some(path))?;
+            self.asciicast.clear().append_from(&existing).duration()
         };
-        self.write_to(writer, &sequence)
+        Ok(duration)
     }
 
-    fn write_to<W: Write>(&mut self, mut writer: W, sequence: &Sequence) -> Result<()> {
-        match self.output_format {
+    /// The compression of the output file, from the --compress option or the
+    /// extension of the file.
+    fn compression(&self, path: &Path) -> Option<Compressions> {
+        self.compress.or_else(|| Compressions::detect(path))
+    }
+
+    /// Creates an output file, compressed if needed.
+    fn create(&self, path: &Path) -> Result<Encoder<File>> {
+        info!("Writing '{}'", path.display());
+        let file =
+            File::create(path).with_context(|| format!("Failed to create '{}'", path.display()))?;
+        Encoder::new(file, self.compression(path))
+    }
+
+    /// Adds a written file to the manifest.
+    fn record(&mut self, path: &Path, duration: f64, source: &[u8]) {
+        self.artifacts.add(Artifact {
+            path: path.to_owned(),
+            format: self.output_format.unwrap_or_default().to_string(),
+            duration,
+            source_hash: manifest::hash(source),
+        });
+    }
+
+    fn write_manifest(&self) -> Result<()> {
+        if let Some(manifest) = &self.manifest {
+            info!("Writing the manifest '{}'", manifest.display());
+            let file = File::create(manifest)
+                .with_context(|| format!("Failed to create '{}'", manifest.display()))?;
+            self.artifacts.write_to(file)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the sequence in the output format to the file at the path, or
+    /// stdout if there is no path.
+    fn write_to<W: Write>(
+        &mut self,
+        mut writer: W,
+        sequence: &Sequence,
+        path: Option<&Path>,
+    ) -> Result<()> {
+        match self.output_format.unwrap_or_default() {
             OutputFormats::Germ => {
                 serde_json::to_writer(&mut writer, &sequence)?;
             }
             OutputFormats::TermSheets => {
-                let termsheets: Vec<termsheets::Command> = sequence.into();
-                serde_json::to_writer(&mut writer, &termsheets)?;
+                termsheets::write_to(sequence, &mut writer)?;
             }
             OutputFormats::Asciicast => {
-                self.asciicast.append_from(sequence).write_to(&mut writer)?;
+                let mut writer = BufWriter::new(writer);
+                self.asciicast
+                    .clear()
+                    .resize()?
+                    .stream_to(sequence, &mut writer)?;
+                writer.flush()?;
+            }
+            OutputFormats::Embed => {
+                let src = match (&self.embed.embed_src, path) {
+                    (Some(src), _) => src.clone(),
+                    (None, Some(path)) => path
+                        .with_extension(OutputFormats::Asciicast.extension())
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    (None, None) => {
+                        bail!("The embed format needs the --embed-src URL of the cast file")
+                    }
+                };
+                self.embed.write_to(writer, &src)?;
+            }
+            OutputFormats::WebVtt => {
+                let cues = captions::cues(&mut self.asciicast, sequence, self.caption_inputs);
+                captions::write_webvtt(&cues, writer)?;
+            }
+            OutputFormats::Srt => {
+                let cues = captions::cues(&mut self.asciicast, sequence, self.caption_inputs);
+                captions::write_srt(&cues, writer)?;
+            }
+            OutputFormats::Chapters => {
+                let chapters = chapters::chapters(&mut self.asciicast, sequence);
+                chapters::write_json(&chapters, writer)?;
+            }
+            OutputFormats::FfMetadata => {
+                let chapters = chapters::chapters(&mut self.asciicast, sequence);
+                chapters::write_ffmetadata(&chapters, writer)?;
+            }
+            OutputFormats::Transcript => {
+                transcript::write_to(&mut self.asciicast, sequence, writer)?;
             }
         }
         Ok(())
     }
 
-    fn update_from(&mut self, matches: &ArgMatches) {
+    /// Applies the options of a line in interactive mode, which fails for an
+    /// invalid --redact or --highlight value.
+    fn update_from(&mut self, matches: &ArgMatches) -> Result<()> {
         if matches.occurrences_of("interactive-prompt") != 0 {
             self.interactive_prompt = value_t!(matches, "interactive-prompt", String).unwrap();
         }
         if matches.occurrences_of("begin-delay") != 0 {
-            self.timings.begin = value_t!(matches, "begin-delay", f64).unwrap();
+            self.timings.begin =
+                delay::parse_seconds(matches.value_of("begin-delay").unwrap()).unwrap();
         }
         if matches.occurrences_of("delay-type-start") != 0 {
-            self.timings.type_start = value_t!(matches, "delay-type-start", usize).unwrap();
+            self.timings.type_start =
+                delay::parse_milliseconds(matches.value_of("delay-type-start").unwrap()).unwrap();
         }
         if matches.occurrences_of("delay-type-char") != 0 {
-            self.timings.type_char = value_t!(matches, "delay-type-char", usize).unwrap();
+            self.timings.type_char =
+                delay::parse_milliseconds(matches.value_of("delay-type-char").unwrap()).unwrap();
         }
         if matches.occurrences_of("delay-type-submit") != 0 {
-            self.timings.type_submit = value_t!(matches, "delay-type-start", usize).unwrap();
+            self.timings.type_submit =
+                delay::parse_milliseconds(matches.value_of("delay-type-submit").unwrap()).unwrap();
         }
         if matches.occurrences_of("delay-output-line") != 0 {
-            self.timings.output_line = value_t!(matches, "delay-output-line", usize).unwrap();
+            self.timings.output_line =
+                delay::parse_milliseconds(matches.value_of("delay-output-line").unwrap()).unwrap();
+        }
+        if matches.occurrences_of("delay-type-jitter") != 0 {
+            self.timings.type_jitter =
+                delay::parse_milliseconds(matches.value_of("delay-type-jitter").unwrap()).unwrap();
+        }
+        if matches.occurrences_of("comment-delay") != 0 {
+            self.timings.comment_delay =
+                delay::parse_milliseconds(matches.value_of("comment-delay").unwrap()).unwrap();
+        }
+        if matches.occurrences_of("echo-latency") != 0 {
+            self.timings.echo_latency =
+                delay::parse_milliseconds(matches.value_of("echo-latency").unwrap()).unwrap();
+        }
+        if matches.occurrences_of("typing-profile") != 0 {
+            self.timings.typing_profile = value_t!(matches, "typing-profile", TypingProfiles).ok();
+            self.timings = self.timings.profiled();
+        }
+        if matches.occurrences_of("typing-layout") != 0 {
+            self.timings.typing_layout = value_t!(matches, "typing-layout", KeyboardLayouts).ok();
         }
         if matches.occurrences_of("end-delay") != 0 {
-            self.timings.end = value_t!(matches, "end-delay", f64).unwrap();
+            self.timings.end =
+                delay::parse_seconds(matches.value_of("end-delay").unwrap()).unwrap();
         }
         if matches.occurrences_of("title") != 0 {
             self.asciicast.header.title = value_t!(matches, "title", String).ok();
         }
+        if matches.occurrences_of("idle-time-limit") != 0 {
+            self.asciicast.header.idle_time_limit = value_t!(matches, "idle-time-limit", f64).ok();
+        }
+        if matches.occurrences_of("intro-title") != 0 {
+            self.asciicast.intro_title = value_t!(matches, "intro-title", String).ok();
+        }
+        if matches.occurrences_of("intro-duration") != 0 {
+            self.asciicast.intro_duration =
+                delay::parse_seconds(matches.value_of("intro-duration").unwrap()).unwrap();
+        }
+        if matches.occurrences_of("intro-banner") != 0 {
+            self.asciicast.intro_banner = true;
+        }
+        if matches.occurrences_of("hold-last") != 0 {
+            self.asciicast.hold_last =
+                Some(delay::parse_seconds(matches.value_of("hold-last").unwrap()).unwrap());
+        }
+        if matches.occurrences_of("theme") != 0 {
+            self.asciicast.theme.theme = value_t!(matches, "theme", ThemePresets).ok();
+        }
+        if matches.occurrences_of("theme-file") != 0 {
+            self.asciicast.theme.theme_file = value_t!(matches, "theme-file", PathBuf).ok();
+        }
+        if matches.occurrences_of("theme-fg") != 0 {
+            self.asciicast.theme.theme_fg = value_t!(matches, "theme-fg", String).ok();
+        }
+        if matches.occurrences_of("theme-bg") != 0 {
+            self.asciicast.theme.theme_bg = value_t!(matches, "theme-bg", String).ok();
+        }
+        if matches.occurrences_of("theme-palette") != 0 {
+            self.asciicast.theme.theme_palette = value_t!(matches, "theme-palette", String).ok();
+        }
+        if matches.occurrences_of("command") != 0 {
+            self.asciicast.header.command = value_t!(matches, "command", String).ok();
+        }
+        if matches.occurrences_of("size") != 0 {
+            self.asciicast.size = value_t!(matches, "size", Sizes).ok();
+        }
         if matches.occurrences_of("width") != 0 {
             self.asciicast.header.width = value_t!(matches, "width", usize).unwrap();
+            self.asciicast.size = None;
         }
         if matches.occurrences_of("height") != 0 {
             self.asciicast.header.height = value_t!(matches, "height", usize).unwrap();
+            self.asciicast.size = None;
         }
         if matches.occurrences_of("input-format") != 0 {
             self.input_format = value_t!(matches, "input-format", InputFormats).unwrap();
         }
         if matches.occurrences_of("output-format") != 0 {
-            self.output_format = value_t!(matches, "output-format", OutputFormats).unwrap();
+            self.output_format = value_t!(matches, "output-format", OutputFormats).ok();
         }
         if matches.occurrences_of("output-file") != 0 {
             self.output_file = value_t!(matches, "output-file", PathBuf).ok();
         }
-        if matches.occurrences_of("prompt") != 0 {
-            self.prompt = value_t!(matches, "prompt", String).unwrap();
+        // With an input, the prompt is only for the command of the line.
+        if !matches.is_present("input") {
+            if let Some(prompt) = prompt_from(matches) {
+                self.prompt = prompt;
+            }
+        }
+        if matches.occurrences_of("expand-env") != 0 {
+            self.expand_env = value_t!(matches, "expand-env", EnvExpansions).unwrap();
+        }
+        if matches.occurrences_of("exec-backend") != 0 {
+            self.backend.exec_backend = value_t!(matches, "exec-backend", ExecBackends).unwrap();
+        }
+        if matches.occurrences_of("image") != 0 {
+            self.backend.image = value_t!(matches, "image", String).ok();
+        }
+        if matches.occurrences_of("ssh-host") != 0 {
+            self.backend.ssh_host = value_t!(matches, "ssh-host", String).ok();
+        }
+        if matches.occurrences_of("ssh-user") != 0 {
+            self.backend.ssh_user = value_t!(matches, "ssh-user", String).ok();
+        }
+        if matches.occurrences_of("ssh-key") != 0 {
+            self.backend.ssh_key = value_t!(matches, "ssh-key", PathBuf).ok();
+        }
+        if matches.occurrences_of("response-timeout") != 0 {
+            self.response_timeout =
+                delay::parse_seconds(matches.value_of("response-timeout").unwrap()).unwrap();
+        }
+        if matches.occurrences_of("retry-delay") != 0 {
+            self.retry_delay =
+                delay::parse_seconds(matches.value_of("retry-delay").unwrap()).unwrap();
         }
         if matches.occurrences_of("speed") != 0 {
             self.timings.speed = value_t!(matches, "speed", f64).unwrap();
@@ -415,25 +1696,490 @@ impl Cli {
             self.asciicast.header.env.shell = value_t!(matches, "shell", String).unwrap();
         }
         if matches.occurrences_of("term") != 0 {
-            self.asciicast.header.env.term = value_t!(matches, "shell", String).unwrap();
+            self.asciicast.header.env.term = value_t!(matches, "term", String).unwrap();
+        }
+        for var in matches.values_of("vars").into_iter().flatten() {
+            if let Some((key, value)) = var.split_once('=') {
+                self.asciicast
+                    .header
+                    .env
+                    .vars
+                    .push((key.to_owned(), value.to_owned()));
+            }
+        }
+        for key in matches.values_of("captures").into_iter().flatten() {
+            self.asciicast.header.env.captures.push(key.to_owned());
         }
         if matches.occurrences_of("stdin") != 0 {
             self.asciicast.stdin = true;
         }
+        if matches.occurrences_of("ssh-banner") != 0 {
+            self.asciicast.ssh_banner = value_t!(matches, "ssh-banner", String).unwrap();
+        }
+        if matches.occurrences_of("continuation-prompt") != 0 {
+            self.asciicast.continuation_prompt =
+                value_t!(matches, "continuation-prompt", String).unwrap();
+        }
+        for pattern in matches.values_of("redact").into_iter().flatten() {
+            let regex = Regex::new(pattern)
+                .with_context(|| format!("Invalid --redact pattern '{}'", pattern))?;
+            self.redact.push(regex);
+        }
+        for preset in matches.values_of("redact-preset").into_iter().flatten() {
+            self.redact
+                .extend(preset.parse::<RedactPresets>().ok().map(|p| p.regex()));
+        }
+        for rule in matches.values_of("highlight").into_iter().flatten() {
+            let highlight = highlight::parse_rule(rule)
+                .with_context(|| format!("Invalid --highlight rule '{}'", rule))?;
+            self.asciicast.highlight.push(highlight);
+        }
+        if matches.occurrences_of("comment-prefix") != 0 {
+            self.asciicast.comment_prefix = value_t!(matches, "comment-prefix", String).unwrap();
+        }
+        if matches.occurrences_of("comment-style") != 0 {
+            self.asciicast.comment_style =
+                value_t!(matches, "comment-style", CommentStyles).unwrap();
+        }
+        if matches.occurrences_of("typing-unit") != 0 {
+            self.asciicast.typing_unit = value_t!(matches, "typing-unit", TypingUnits).unwrap();
+        }
+        if matches.occurrences_of("strip-ansi") != 0 {
+            self.strip_ansi = true;
+        }
+        if matches.occurrences_of("max-output-lines") != 0 {
+            self.asciicast.max_output_lines = value_t!(matches, "max-output-lines", usize).ok();
+        }
+        if matches.occurrences_of("chunk-size") != 0 {
+            self.asciicast.chunk_size = value_t!(matches, "chunk-size", NonZeroUsize).ok();
+        }
+        if matches.occurrences_of("wrap") != 0 {
+            self.asciicast.wrap = true;
+        }
+        if matches.occurrences_of("merge-events") != 0 {
+            self.asciicast.merge_events = true;
+        }
+        if matches.occurrences_of("deterministic") != 0 {
+            self.asciicast.deterministic = true;
+        }
+        if matches.occurrences_of("markers") != 0 {
+            self.asciicast.markers = true;
+        }
         if matches.occurrences_of("use-germ-format") != 0 {
             self.use_germ_format = true;
         }
+        if matches.occurrences_of("no-execute") != 0 {
+            self.backend.no_execute = true;
+        }
+        for program in matches.values_of("allow").into_iter().flatten() {
+            self.backend.allow.push(program.to_owned());
+        }
+        Ok(())
     }
 
-    fn execute_cmd(&self, input: &str) -> Result<process::Output> {
-        process::Command::new(&self.asciicast.header.env.shell)
-            .args([
-                &format!("{}", self.asciicast.header.env.execute_string_flag),
+    /// The input as it is typed, with the environment variables expanded for
+    /// --expand-env all.
+    fn typed(&self, input: &str) -> String {
+        match self.expand_env {
+            EnvExpansions::All => sequence::expand_env(input),
+            _ => input.to_owned(),
+        }
+    }
+
+    /// Logs to stderr at the level of the -v,--verbose and -q,--quiet flags.
+    fn init_logging(&self) {
+        let level = match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Error,
+            (false, 0) => LevelFilter::Warn,
+            (false, 1) => LevelFilter::Info,
+            (false, 2) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        };
+        // The logger is already initialized when germ is used as a library.
+        let _ = env_logger::Builder::new()
+            .filter_level(level)
+            .parse_env(LOG_VAR_NAME)
+            .try_init();
+    }
+
+    /// Executes the input with the shell, in the working directory if any, and
+    /// returns its output and exit code.
+    ///
+    /// An input that fails or exits with a non-zero code is executed again up
+    /// to the retries of its command, or --retries, times, after the
+    /// --retry-delay, and the last attempt is returned.
+    fn execute_cmd(
+        &self,
+        input: &str,
+        cwd: Option<&str>,
+        responses: &[Response],
+        retries: Option<usize>,
+        echo: bool,
+    ) -> Result<Executed> {
+        let retries = retries.unwrap_or(self.retries);
+        let mut input = sequence::strip_tabs(input);
+        if self.expand_env != EnvExpansions::None {
+            input = sequence::expand_env(&input);
+        }
+        self.backend.permit(&input, cwd)?;
+        let mut attempt = 0;
+        loop {
+            let result = self.execute_once(&input, cwd, responses, echo);
+            match &result {
+                Ok(executed) if executed.interrupted || executed.code == Some(0) => return result,
+                _ if attempt == retries => return result,
+                _ => {}
+            }
+            attempt += 1;
+            warn!(
+                "Retrying '{}' ({} of {}) after {}",
                 input,
-            ])
-            .output()
-            .map_err(anyhow::Error::from)
+                attempt,
+                retries,
+                match &result {
+                    Ok(executed) => exit_code(executed.code, executed.signal),
+                    Err(err) => format!("'{}'", err),
+                }
+            );
+            thread::sleep(self.retry_delay.into());
+        }
+    }
+
+    /// Executes the input once and returns its output and exit code.
+    ///
+    /// Ctrl+C kills the input instead of germ, and the output so far is
+    /// returned.
+    ///
+    /// The output is read as it is produced and, when echoing, printed as it
+    /// is read, so a long-running command shows progress in interactive mode.
+    fn execute_once(
+        &self,
+        input: &str,
+        cwd: Option<&str>,
+        responses: &[Response],
+        echo: bool,
+    ) -> Result<Executed> {
+        let mut command = self.backend.command(&self.asciicast.header.env, input, cwd);
+        info!(
+            "Executing '{}' with '{}'",
+            input,
+            command.get_program().to_string_lossy()
+        );
+        debug!("Running {:?}", command);
+        let start = Instant::now();
+        let mut child = command
+            .stdin(if responses.is_empty() {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut reader = child.stdout.take().expect("Piped stdout");
+        // The input is closed after the last response, so a command waiting
+        // for more input ends instead of hanging.
+        let mut writer = child.stdin.take();
+        interrupt::watch(child);
+        // The output is read on another thread, so waiting for a prompt that
+        // is never printed can time out.
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut chunk = [0; CAPTURE_CHUNK_SIZE];
+            loop {
+                let read = match reader.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(read) => Ok(chunk[..read].to_vec()),
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => Err(err),
+                };
+                let failed = read.is_err();
+                if sender.send(read).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        let timeout = Duration::from(self.response_timeout);
+        let mut pending = responses.iter();
+        let mut next = pending.next();
+        let mut searched = 0;
+        let mut output = Vec::new();
+        loop {
+            // The prompts are searched before the first read, too, since a
+            // program can read its input before printing anything.
+            while let Some(response) = next {
+                let expect = response.expect.as_bytes();
+                let found = match expect.len() {
+                    0 => Some(0),
+                    len => output[searched..].windows(len).position(|w| w == expect),
+                };
+                let start = match found {
+                    Some(start) => searched + start,
+                    None => break,
+                };
+                searched = start + expect.len();
+                if let Some(stdin) = writer.as_mut() {
+                    writeln!(stdin, "{}", response.send)?;
+                }
+                if echo {
+                    println!("{}", response.send);
+                }
+                next = pending.next();
+                if next.is_none() {
+                    writer = None;
+                }
+            }
+            let received = match next {
+                Some(response) => match receiver.recv_timeout(timeout) {
+                    Ok(received) => received,
+                    Err(RecvTimeoutError::Timeout) => {
+                        interrupt::kill()?;
+                        bail!(
+                            "The input '{}' timed out, prompt '{}' not seen after {}s",
+                            input,
+                            response.expect,
+                            timeout.as_secs_f64()
+                        );
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match receiver.recv() {
+                    Ok(received) => received,
+                    Err(_) => break,
+                },
+            };
+            let chunk = received?;
+            if echo {
+                io::stdout().write_all(&chunk)?;
+            }
+            output.extend_from_slice(&chunk);
+        }
+        drop(writer);
+        let (status, interrupted) = interrupt::wait()?;
+        info!(
+            "Executed '{}' in {:.3}s with {}",
+            input,
+            start.elapsed().as_secs_f64(),
+            exit_code(status.code(), signal(&status))
+        );
+        if interrupted && echo {
+            println!("^C");
+        }
+        self.backend.check(status.code())?;
+        Ok(Executed {
+            output: String::from_utf8(output)?,
+            code: status.code(),
+            signal: signal(&status),
+            interrupted,
+        })
+    }
+}
+
+/// The output and exit code of an executed input.
+struct Executed {
+    output: String,
+    code: Option<i32>,
+    /// The signal that killed the input, if any and the platform has them.
+    signal: Option<i32>,
+    /// The input was killed with Ctrl+C, so the output is partial.
+    interrupted: bool,
+}
+
+/// The error with a report pointing at its location in the buffer, if it has
+/// one.
+fn diagnose(err: crate::Error, buffer: &[u8], path: Option<&Path>) -> anyhow::Error {
+    match diagnostic::report(&err, buffer, path) {
+        Some(report) => anyhow!(report),
+        None => err.into(),
+    }
+}
+
+/// The exit code for messages, which is missing if the process was killed by a
+/// signal.
+fn exit_code(code: Option<i32>, signal: Option<i32>) -> String {
+    match (code, signal) {
+        (Some(code), _) => format!("exit code {}", code),
+        (None, Some(signal)) => format!("signal {}", signal),
+        (None, None) => String::from("a signal"),
+    }
+}
+
+/// The signal that killed the process, if any.
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+/// The signal that killed the process, which Windows does not have.
+#[cfg(not(unix))]
+fn signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Pairs the prompts of the --expect options with the responses of the --send
+/// options.
+fn responses<'a>(
+    expects: impl ExactSizeIterator<Item = &'a str>,
+    sends: impl ExactSizeIterator<Item = &'a str>,
+) -> Result<Vec<Response>> {
+    if expects.len() != sends.len() {
+        bail!("Each --expect prompt must have one --send response");
+    }
+    Ok(expects
+        .zip(sends)
+        .map(|(expect, send)| Response::new(expect, send))
+        .collect())
+}
+
+fn parse_persona(src: &str) -> Result<(String, String)> {
+    match src.split_once('=') {
+        Some((name, prompt)) if !name.is_empty() => Ok((name.to_owned(), prompt::unescape(prompt))),
+        _ => bail!("'{}' is not a persona in the name=prompt format", src),
+    }
+}
+
+/// The prompt from the -p,--prompt or --prompt-preset option of a line in
+/// interactive mode, if any.
+fn prompt_from(matches: &ArgMatches) -> Option<String> {
+    if matches.occurrences_of("prompt") != 0 {
+        value_t!(matches, "prompt", String)
+            .ok()
+            .map(|p| prompt::unescape(&p))
+    } else {
+        value_t!(matches, "prompt-preset", PromptPresets)
+            .ok()
+            .map(|p| p.prompt().to_owned())
+    }
+}
+
+/// Uses the prompt of the persona, if any, as the prompt of the command.
+fn with_prompt_of(sequence: &Sequence, command: &Command) -> Command {
+    let mut cmd = command.clone();
+    cmd.set_prompt(sequence.prompt_of(command));
+    cmd
+}
+
+/// Records the commands added to the sequence since the number already
+/// recorded and returns the new number recorded.
+fn record_new(
+    recorder: Option<&mut Recorder<WebSocket>>,
+    sequence: &Sequence,
+    recorded: usize,
+) -> Result<usize> {
+    if let Some(recorder) = recorder {
+        for command in sequence.iter().skip(recorded) {
+            recorder.record(&with_prompt_of(sequence, command))?;
+        }
+    }
+    Ok(sequence.iter().count())
+}
+
+/// The output, or the contents of the file for an "@path" output.
+fn read_output(output: &str) -> Result<String> {
+    match output.strip_prefix('@') {
+        Some(literal) if literal.starts_with('@') => Ok(literal.to_owned()),
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the output from '{}'", path)),
+        None => Ok(output.to_owned()),
+    }
+}
+
+/// Reads the output of a command from a file, or stdin for "-".
+fn read_outputs_from(path: &Path) -> Result<String> {
+    let path = Some(path).filter(|p| *p != Path::new("-"));
+    Ok(String::from_utf8(read_input(path)?)?)
+}
+
+/// Reads all of a file, or stdin if no file is provided, decompressed if
+/// needed.
+fn read_input(path: Option<&Path>) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    if let Some(path) = path {
+        File::open(path)
+            .with_context(|| format!("Failed to open '{}'", path.display()))?
+            .read_to_end(&mut buffer)?;
+    } else if atty::is(Stream::Stdin) {
+        bail!("No input, use a file or pipe to stdin");
+    } else {
+        io::stdin().read_to_end(&mut buffer)?;
+    }
+    compress::decode(buffer)
+}
+
+/// Runs the job for each item on up to --jobs threads, each with a copy of
+/// the options, and returns the results in the order of the items.
+///
+/// The files recorded by each job are added to the manifest in the order of
+/// the items, too.
+fn run_jobs<T, R, F>(cli: &mut Cli, items: &[T], job: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&mut Cli, &T) -> R + Sync,
+{
+    let jobs = cli.jobs.get().min(items.len());
+    if jobs <= 1 {
+        return items.iter().map(|item| job(cli, item)).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut done = thread::scope(|scope| {
+        let workers = (0..jobs)
+            .map(|_| {
+                let mut worker = cli.clone();
+                let (next, job) = (&next, &job);
+                scope.spawn(move || {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let item = match items.get(index) {
+                            Some(item) => item,
+                            None => break,
+                        };
+                        let result = job(&mut worker, item);
+                        done.push((index, result, std::mem::take(&mut worker.artifacts)));
+                    }
+                    done
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Job thread panicked"))
+            .collect::<Vec<_>>()
+    });
+    done.sort_by_key(|(index, ..)| *index);
+    done.into_iter()
+        .map(|(_, result, artifacts)| {
+            cli.artifacts.append(artifacts);
+            result
+        })
+        .collect()
+}
+
+/// Reads an asciicast file, or renders a sequence file, as an asciicast.
+fn read_asciicast(cli: &mut Cli, path: &Path, buffer: &[u8]) -> Result<Asciicast> {
+    cli.input_format = InputFormats::detect(path, buffer);
+    if let InputFormats::Asciicast = cli.input_format {
+        return read_cast(cli, buffer);
+    }
+    let sequence = cli.parse(buffer, Some(path))?;
+    let mut asciicast = cli.asciicast.clone();
+    asciicast.clear().resize()?.append_from(&sequence);
+    if let Some(Sizes::Fit) = asciicast.size {
+        asciicast.fit();
+    }
+    Ok(asciicast)
+}
+
+/// Reads an asciicast file with the options applicable to asciicast input.
+fn read_cast(cli: &Cli, buffer: &[u8]) -> Result<Asciicast> {
+    let mut asciicast = Asciicast::read_from(buffer)?;
+    if let Some(max_idle) = cli.asciicast.max_idle {
+        asciicast.clamp_idle(max_idle);
     }
+    asciicast.merge_events = cli.asciicast.merge_events;
+    Ok(asciicast)
 }
 
 fn print_interactive_notice() {
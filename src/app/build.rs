@@ -0,0 +1,165 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::convert::{source_extension, walk};
+use super::{run_jobs, Cli, InputFormats};
+use crate::compress::{self, Compressions};
+use crate::sequence::Render;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// The conventional directory of the sequence sources.
+pub const DEFAULT_SOURCE_DIR: &str = "demos";
+
+/// The conventional directory of the rendered files.
+pub const DEFAULT_OUT_DIR: &str = "build";
+
+/// The conventional project configuration file.
+pub const DEFAULT_CONFIG_FILE: &str = "germ.toml";
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Build {
+    /// The project configuration file.
+    ///
+    /// The TOML file may set the "source" directory, the "out-dir" directory,
+    /// and the default "render" settings, i.e. "formats" and "theme", for
+    /// sources without their own. It is optional if the default is used.
+    #[structopt(long, value_name = "file", parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// The directory of the sequence sources [default: demos].
+    ///
+    /// Every .json and .germ file below the directory is rendered.
+    #[structopt(short = "s", long, value_name = "dir", parse(from_os_str))]
+    source: Option<PathBuf>,
+
+    /// The directory for the rendered files [default: build].
+    ///
+    /// The directory structure of the sources is mirrored.
+    #[structopt(short = "d", long, value_name = "dir", parse(from_os_str))]
+    out_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    source: Option<PathBuf>,
+    out_dir: Option<PathBuf>,
+    render: Option<Render>,
+}
+
+impl Build {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        let config = self.config()?;
+        let source_dir = self
+            .source
+            .or(config.source)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SOURCE_DIR));
+        let out_dir = self
+            .out_dir
+            .or(config.out_dir)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_OUT_DIR));
+        if !source_dir.is_dir() {
+            bail!(
+                "The source directory '{}' does not exist",
+                source_dir.display()
+            );
+        }
+        let mut sources = Vec::new();
+        walk(&source_dir, Path::new(""), true, &mut sources)?;
+        sources.retain(|(source, _)| source_extension(source) != Some("cast"));
+        let defaults = config.render.as_ref();
+        let results = run_jobs(cli, &sources, |cli, (source, relative)| {
+            build(cli, source, &out_dir.join(relative), defaults)
+        });
+        let (mut built, mut failures) = (0, 0);
+        for ((source, _), result) in sources.iter().zip(results) {
+            match result {
+                Ok(destinations) => {
+                    for destination in destinations {
+                        println!("{} -> {}", source.display(), destination.display());
+                        built += 1;
+                    }
+                }
+                Err(err) => {
+                    eprintln!("{}: {:#}", source.display(), err);
+                    failures += 1;
+                }
+            }
+        }
+        println!(
+            "Built {} file(s) from {} source(s) in '{}'",
+            built,
+            sources.len() - failures,
+            out_dir.display()
+        );
+        if failures != 0 {
+            bail!("Failed to build {} source(s)", failures);
+        }
+        Ok(())
+    }
+
+    fn config(&self) -> Result<Config> {
+        let path = match &self.config {
+            Some(path) => path.as_path(),
+            None if Path::new(DEFAULT_CONFIG_FILE).is_file() => Path::new(DEFAULT_CONFIG_FILE),
+            None => return Ok(Config::default()),
+        };
+        fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| toml::from_str(&content).map_err(anyhow::Error::from))
+            .with_context(|| format!("Failed to read '{}'", path.display()))
+    }
+}
+
+/// Renders a source to each of its formats and returns the written paths.
+fn build(
+    cli: &mut Cli,
+    source: &Path,
+    destination: &Path,
+    defaults: Option<&Render>,
+) -> Result<Vec<PathBuf>> {
+    let buffer = compress::decode(fs::read(source)?)?;
+    cli.input_format = InputFormats::detect(source, &buffer);
+    let mut sequence = cli.parse(&buffer, Some(source))?;
+    if sequence.render().is_none() {
+        sequence.set_render(defaults.cloned());
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let destination = match Compressions::detect(destination) {
+        Some(_) => destination.with_extension(""),
+        None => destination.to_owned(),
+    };
+    // The render settings only apply to this source.
+    let (output_format, theme) = (cli.output_format, cli.asciicast.theme.clone());
+    let result = cli.render(&sequence).and_then(|formats| {
+        formats
+            .into_iter()
+            .map(|format| {
+                let path = destination.with_extension(format.extension());
+                cli.output_format = Some(format);
+                cli.write_file(&path, &sequence).map(|_| path)
+            })
+            .collect()
+    });
+    cli.output_format = output_format;
+    cli.asciicast.theme = theme;
+    result
+}
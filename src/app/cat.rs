@@ -0,0 +1,79 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{read_asciicast, read_input, Cli};
+use crate::screen::Terminal;
+use anyhow::Result;
+use atty::Stream;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// Resets the colors and styles at the end of each row of the screen.
+const RESET: &[u8] = b"\x1b[0m";
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Cat {
+    /// Prints the screen without colors and styles.
+    ///
+    /// The colors and styles are only printed if stdout is a terminal.
+    #[structopt(long = "plain")]
+    plain: bool,
+
+    /// The sequence or asciicast file.
+    ///
+    /// The format is detected from the extension and content. If not present,
+    /// then the -i,--input file or stdin is used. Sequences are rendered with
+    /// the options given before the subcommand.
+    #[structopt(parse(from_os_str))]
+    file: Option<PathBuf>,
+}
+
+impl Cat {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        let path = self.file.or_else(|| cli.input_files.first().cloned());
+        let buffer = read_input(path.as_deref())?;
+        let asciicast = read_asciicast(
+            cli,
+            path.as_deref().unwrap_or_else(|| Path::new("")),
+            &buffer,
+        )?;
+        let mut terminal = Terminal::new(&asciicast)?;
+        terminal.finish();
+        let screen = terminal.screen();
+        let (_, cols) = screen.size();
+        // Blank rows below the last line of text are not part of the demo.
+        let rows: Vec<String> = screen.rows(0, cols).collect();
+        let used = rows
+            .iter()
+            .rposition(|row| !row.trim_end().is_empty())
+            .map_or(0, |last| last + 1);
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        if !self.plain && atty::is(Stream::Stdout) {
+            for row in screen.rows_formatted(0, cols).take(used) {
+                stdout.write_all(&row)?;
+                stdout.write_all(RESET)?;
+                stdout.write_all(b"\n")?;
+            }
+        } else {
+            for row in &rows[..used] {
+                writeln!(stdout, "{}", row.trim_end())?;
+            }
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::Cli;
+use anyhow::Result;
+use std::io;
+use structopt::clap::Shell;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Completions {
+    /// The shell of the completion script.
+    #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+    shell: Shell,
+}
+
+impl Completions {
+    pub fn execute(self) -> Result<()> {
+        Cli::clap().gen_completions_to(env!("CARGO_PKG_NAME"), self.shell, &mut io::stdout());
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{read_asciicast, Cli};
+use crate::asciicast::Asciicast;
+use crate::compress;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Concat {
+    /// The asciicast file, stdout if not present.
+    #[structopt(short = "o", long = "output", value_name = "file", parse(from_os_str))]
+    output_file: Option<PathBuf>,
+
+    /// The sequence and/or asciicast files, in order.
+    ///
+    /// The format of each file is detected from its extension and content.
+    /// Sequences are rendered with the options given before the subcommand.
+    #[structopt(required = true, parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+impl Concat {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        let mut combined: Option<Asciicast> = None;
+        let mut sources = Vec::new();
+        for path in &self.paths {
+            let buffer =
+                fs::read(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+            let buffer = compress::decode(buffer)?;
+            let asciicast = read_asciicast(cli, path, &buffer)
+                .with_context(|| format!("Invalid input '{}'", path.display()))?;
+            sources.extend(buffer);
+            match combined.as_mut() {
+                Some(combined) => {
+                    combined.concat(asciicast);
+                }
+                None => combined = Some(asciicast),
+            }
+        }
+        let mut combined = combined.expect("At least one path");
+        match &self.output_file {
+            Some(output_file) => {
+                let mut file = cli.create(output_file)?;
+                combined.write_to(&mut file)?;
+                file.finish()?;
+                cli.record(output_file, combined.duration(), &sources);
+            }
+            None => combined.write_to(io::stdout())?,
+        }
+        Ok(())
+    }
+}
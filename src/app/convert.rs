@@ -0,0 +1,211 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{read_cast, run_jobs, Cli, InputFormats, OutputFormats};
+use crate::compress::{self, Compressions};
+use anyhow::{bail, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use strum::VariantNames;
+
+/// The file extensions considered when walking a directory.
+pub const SOURCE_EXTENSIONS: &[&str] = &["json", "germ", "cast"];
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Convert {
+    /// Descend into subdirectories of any directory path.
+    #[structopt(short, long)]
+    recursive: bool,
+
+    /// The format for the converted files.
+    ///
+    /// If not present, then the -O,--output-format format, or asciicast.
+    #[structopt(
+        short = "t",
+        long = "to",
+        possible_values = OutputFormats::VARIANTS,
+        case_insensitive = true,
+        value_name = "format"
+    )]
+    to: Option<OutputFormats>,
+
+    /// The directory for the converted files.
+    ///
+    /// The directory structure below each directory path is mirrored within
+    /// the output directory. If not present, then each converted file is
+    /// written next to its source file.
+    #[structopt(short = "d", long, value_name = "dir", parse(from_os_str))]
+    out_dir: Option<PathBuf>,
+
+    /// The files and/or directories to convert.
+    ///
+    /// The format of each file is detected from its extension and content.
+    /// Only files with a .json, .germ, or .cast extension, optionally
+    /// followed by a .gz or .zst extension, are converted when walking a
+    /// directory. A path that does not exist is a glob pattern, i.e.
+    /// "demos/*.germ", for shells that do not expand patterns.
+    #[structopt(required = true, parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+impl Convert {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        let to = self.to.or(cli.output_format).unwrap_or_default();
+        cli.output_format = Some(to);
+        let mut files = Vec::new();
+        for path in &self.paths {
+            for (source, relative) in self.sources(path)? {
+                let mut destination = self.destination(path, &source, &relative, to);
+                if let Some(compression) = cli.compress {
+                    let mut name = destination.into_os_string();
+                    name.push(".");
+                    name.push(compression.extension());
+                    destination = PathBuf::from(name);
+                }
+                files.push((source, destination));
+            }
+        }
+        let results = run_jobs(cli, &files, |cli, (source, destination)| {
+            convert(cli, source, destination)
+        });
+        let mut failures = 0;
+        for ((source, destination), result) in files.iter().zip(results) {
+            match result {
+                Ok(()) => println!("{} -> {}", source.display(), destination.display()),
+                Err(err) => {
+                    eprintln!("{}: {:#}", source.display(), err);
+                    failures += 1;
+                }
+            }
+        }
+        if failures != 0 {
+            bail!("Failed to convert {} file(s)", failures);
+        }
+        Ok(())
+    }
+
+    /// All of the files to convert for a path argument and their paths
+    /// relative to the argument.
+    fn sources(&self, path: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut sources = Vec::new();
+        if path.is_dir() {
+            walk(path, Path::new(""), self.recursive, &mut sources)?;
+        } else if !path.exists() && is_pattern(path) {
+            let pattern = path.to_string_lossy();
+            for matched in glob::glob(&pattern)? {
+                let matched = matched?;
+                if matched.is_file() {
+                    let relative = PathBuf::from(matched.file_name().unwrap_or_default());
+                    sources.push((matched, relative));
+                }
+            }
+            if sources.is_empty() {
+                bail!("No files match '{}'", pattern);
+            }
+        } else {
+            let relative = PathBuf::from(path.file_name().unwrap_or_default());
+            sources.push((path.to_owned(), relative));
+        }
+        Ok(sources)
+    }
+
+    fn destination(
+        &self,
+        root: &Path,
+        source: &Path,
+        relative: &Path,
+        to: OutputFormats,
+    ) -> PathBuf {
+        let destination = match &self.out_dir {
+            Some(out_dir) => out_dir.join(relative),
+            None if root.is_dir() => root.join(relative),
+            None => source.to_owned(),
+        };
+        match Compressions::detect(&destination) {
+            Some(_) => destination
+                .with_extension("")
+                .with_extension(to.extension()),
+            None => destination.with_extension(to.extension()),
+        }
+    }
+}
+
+/// The extension of a file, before the extension of its compression, i.e.
+/// "cast" for "demo.cast.gz".
+pub(super) fn source_extension(path: &Path) -> Option<&str> {
+    let path = match Compressions::detect(path) {
+        Some(_) => Path::new(path.file_stem()?),
+        None => path,
+    };
+    path.extension().and_then(|e| e.to_str())
+}
+
+/// Indicates the path contains any of the special characters of a glob
+/// pattern.
+fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// Collects the source files in a directory and their paths relative to the
+/// top-level directory.
+pub(super) fn walk(
+    dir: &Path,
+    relative: &Path,
+    recursive: bool,
+    sources: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let relative = relative.join(entry.file_name());
+        if path.is_dir() {
+            if recursive {
+                walk(&path, &relative, recursive, sources)?;
+            }
+        } else if source_extension(&path).is_some_and(|e| SOURCE_EXTENSIONS.contains(&e)) {
+            sources.push((path, relative));
+        }
+    }
+    Ok(())
+}
+
+fn convert(cli: &mut Cli, source: &Path, destination: &Path) -> Result<()> {
+    if source == destination {
+        bail!("The converted file would overwrite its source");
+    }
+    let buffer = compress::decode(fs::read(source)?)?;
+    cli.input_format = InputFormats::detect(source, &buffer);
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if let InputFormats::Asciicast = cli.input_format {
+        if let Some(OutputFormats::Asciicast) = cli.output_format {
+            let mut asciicast = read_cast(cli, &buffer)?;
+            let mut file = cli.create(destination)?;
+            asciicast.write_to(&mut file)?;
+            file.finish()?;
+            cli.record(destination, asciicast.duration(), &buffer);
+            return Ok(());
+        }
+        bail!(
+            "Converting an asciicast file to the {} format is not supported",
+            cli.output_format.unwrap_or_default()
+        );
+    }
+    let sequence = cli.parse(&buffer, Some(source))?;
+    cli.write_file(destination, &sequence)
+}
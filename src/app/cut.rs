@@ -0,0 +1,78 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{read_asciicast, read_input, Cli};
+use anyhow::{bail, Result};
+use std::io;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Cut {
+    /// The time, in seconds, of the first event to keep.
+    #[structopt(long = "from", value_name = "secs", default_value = "0")]
+    from: f64,
+
+    /// The time, in seconds, of the last event to keep.
+    ///
+    /// If not present, then all of the events after --from are kept.
+    #[structopt(long = "to", value_name = "secs")]
+    to: Option<f64>,
+
+    /// The asciicast file, stdout if not present.
+    #[structopt(short = "o", long = "output", value_name = "file", parse(from_os_str))]
+    output_file: Option<PathBuf>,
+
+    /// The sequence or asciicast file.
+    ///
+    /// The format is detected from the extension and content. If not present,
+    /// then the -i,--input file or stdin is used. Sequences are rendered with
+    /// the options given before the subcommand.
+    #[structopt(parse(from_os_str))]
+    file: Option<PathBuf>,
+}
+
+impl Cut {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        if self.from < 0.0 {
+            bail!("The --from time cannot be negative");
+        }
+        if let Some(to) = self.to.filter(|to| *to < self.from) {
+            bail!(
+                "The --to time ({}) cannot be before the --from time ({})",
+                to,
+                self.from
+            );
+        }
+        let path = self.file.or_else(|| cli.input_files.first().cloned());
+        let buffer = read_input(path.as_deref())?;
+        let mut asciicast = read_asciicast(
+            cli,
+            path.as_deref().unwrap_or_else(|| Path::new("")),
+            &buffer,
+        )?;
+        asciicast.cut(self.from, self.to);
+        match &self.output_file {
+            Some(output_file) => {
+                let mut file = cli.create(output_file)?;
+                asciicast.write_to(&mut file)?;
+                file.finish()?;
+                cli.record(output_file, asciicast.duration(), &buffer);
+            }
+            None => asciicast.write_to(io::stdout())?,
+        }
+        Ok(())
+    }
+}
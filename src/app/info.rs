@@ -0,0 +1,86 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{read_input, Cli, InputFormats};
+use crate::asciicast::{Asciicast, Event};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Info {
+    /// The sequence or asciicast file.
+    ///
+    /// The format is detected from the extension and content. If not present,
+    /// then the -i,--input file or stdin is used.
+    #[structopt(parse(from_os_str))]
+    file: Option<PathBuf>,
+}
+
+impl Info {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        let path = self
+            .file
+            .as_deref()
+            .or(cli.input_files.first().map(PathBuf::as_path));
+        let buffer = read_input(path)?;
+        cli.input_format = InputFormats::detect(path.unwrap_or_else(|| Path::new("")), &buffer);
+        let mut commands = Vec::new();
+        let asciicast = if let InputFormats::Asciicast = cli.input_format {
+            Asciicast::read_from(&buffer[..])?
+        } else {
+            let mut asciicast = std::mem::take(&mut cli.asciicast);
+            let sequence = cli.parse(&buffer, path)?.framed(&asciicast.ssh_banner);
+            let mut start = asciicast.add_intro(sequence.timings());
+            for command in sequence.iter() {
+                let prompt = sequence.prompt_of(command);
+                let end = asciicast.add_command(command, prompt, sequence.timings(), start);
+                commands.push((end - start, command.text()));
+                start = end;
+            }
+            asciicast.clear().append_from(&sequence);
+            asciicast
+        };
+        let events = asciicast.events();
+        println!("Duration: {:.3} s", asciicast.duration());
+        if !matches!(cli.input_format, InputFormats::Asciicast) {
+            println!("Commands: {}", commands.len());
+        }
+        println!("Events: {}", events.len());
+        match longest_gap(events) {
+            Some((gap, at)) => println!("Longest idle: {:.3} s (at {:.3} s)", gap, at),
+            None => println!("Longest idle: {:.3} s", 0.0),
+        }
+        if !commands.is_empty() {
+            println!("Command durations:");
+            for (i, (duration, input)) in commands.iter().enumerate() {
+                println!("  {}. {:.3} s  {}", i + 1, duration, input);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The longest time between two consecutive events and the time at which it
+/// starts.
+fn longest_gap(events: &[Event]) -> Option<(f64, f64)> {
+    events
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0, w[0].0))
+        .fold(None, |longest, (gap, at)| match longest {
+            Some((g, _)) if g >= gap => longest,
+            _ => Some((gap, at)),
+        })
+}
@@ -0,0 +1,48 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{read_input, Cli};
+use crate::asciicast::Asciicast;
+use crate::sequence::Sequence;
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Normalize {
+    /// The asciicast file.
+    ///
+    /// If not present, then the -i,--input file or stdin is used.
+    #[structopt(parse(from_os_str))]
+    file: Option<PathBuf>,
+}
+
+impl Normalize {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        let path = self.file.or_else(|| cli.input_files.first().cloned());
+        let buffer = read_input(path.as_deref())?;
+        let asciicast = Asciicast::read_from(&buffer[..]).context("Invalid asciicast input")?;
+        let mut sequence = Sequence::from(cli.timings);
+        for mut command in asciicast.commands(&cli.prompt) {
+            command.set_prompt(&cli.prompt);
+            sequence.add(command);
+        }
+        if sequence.iter().next().is_none() {
+            bail!("No commands found, use the -p,--prompt option to match the prompt of the recording");
+        }
+        let formats = cli.render(&sequence)?;
+        cli.write(sequence, &formats)
+    }
+}
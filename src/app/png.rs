@@ -0,0 +1,145 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{read_asciicast, read_input, Cli};
+use crate::delay::{self, Delay};
+use crate::image::{Image, Palette};
+use crate::screen::Terminal;
+use anyhow::{bail, Context, Result};
+use log::info;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// The name of the images of a file read from stdin.
+const STDIN_STEM: &str = "frame";
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Png {
+    /// The time, in seconds, of a frame to render.
+    ///
+    /// This can be used more than once. If neither --at nor --every is
+    /// present, then the last frame is rendered.
+    #[structopt(long = "at", value_name = "secs", number_of_values = 1)]
+    at: Vec<f64>,
+
+    /// Renders a frame every number of seconds, starting at zero.
+    #[structopt(long = "every", value_name = "secs", conflicts_with = "at")]
+    every: Option<f64>,
+
+    /// The time, in seconds, that the last frame is repeated after the end
+    /// with --every.
+    ///
+    /// Animations made from the frames hold the final output, instead of
+    /// restarting right after it.
+    #[structopt(
+        long = "hold-last",
+        value_name = "secs",
+        requires = "every",
+        parse(try_from_str = delay::parse_seconds)
+    )]
+    hold_last: Option<Delay>,
+
+    /// The size, in pixels, of a pixel of the font.
+    ///
+    /// A cell of the terminal is 8 pixels wide and 16 pixels tall at a scale
+    /// of 1.
+    #[structopt(long = "scale", value_name = "n", default_value = "2")]
+    scale: usize,
+
+    /// The directory of the images, the current directory if not present.
+    ///
+    /// Each image is named after the file and the time of the frame in
+    /// milliseconds, i.e. "demo-001500.png".
+    #[structopt(
+        short = "o",
+        long = "output-dir",
+        value_name = "dir",
+        parse(from_os_str)
+    )]
+    output_dir: Option<PathBuf>,
+
+    /// The sequence or asciicast file.
+    ///
+    /// The format is detected from the extension and content. If not present,
+    /// then the -i,--input file or stdin is used. Sequences are rendered with
+    /// the options given before the subcommand.
+    #[structopt(parse(from_os_str))]
+    file: Option<PathBuf>,
+}
+
+impl Png {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        if self.scale == 0 {
+            bail!("The --scale cannot be zero");
+        }
+        if self.at.iter().any(|at| !at.is_finite() || *at < 0.0) {
+            bail!("The --at times cannot be negative");
+        }
+        if let Some(every) = self
+            .every
+            .filter(|every| !every.is_finite() || *every <= 0.0)
+        {
+            bail!("The --every interval ({}) must be greater than zero", every);
+        }
+        let path = self.file.or_else(|| cli.input_files.first().cloned());
+        let buffer = read_input(path.as_deref())?;
+        let mut asciicast = read_asciicast(
+            cli,
+            path.as_deref().unwrap_or_else(|| Path::new("")),
+            &buffer,
+        )?;
+        let palette = Palette::new(asciicast.finalize_header()?.theme.as_ref());
+        let duration = asciicast.duration();
+        let end = duration + self.hold_last.map_or(0.0, Delay::as_secs_f64);
+        let mut times = match self.every {
+            Some(every) => (0..)
+                .map(|i| f64::from(i) * every)
+                .take_while(|time| *time <= end)
+                .collect(),
+            None if self.at.is_empty() => vec![duration],
+            None => self.at,
+        };
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+        let stem = path
+            .as_deref()
+            .and_then(Path::file_stem)
+            .map_or_else(|| STDIN_STEM.into(), |stem| stem.to_string_lossy());
+        let output_dir = self.output_dir.unwrap_or_default();
+        if !output_dir.as_os_str().is_empty() {
+            fs::create_dir_all(&output_dir)
+                .with_context(|| format!("Failed to create '{}'", output_dir.display()))?;
+        }
+        let mut terminal = Terminal::new(&asciicast)?;
+        for time in times {
+            terminal.advance_to(time);
+            let image = Image::render(terminal.screen(), &palette, self.scale);
+            let image_path = output_dir.join(format!(
+                "{}-{:06}.png",
+                stem,
+                (time * 1000.0).round() as u64
+            ));
+            info!("Writing '{}'", image_path.display());
+            let file = File::create(&image_path)
+                .with_context(|| format!("Failed to create '{}'", image_path.display()))?;
+            image
+                .write_png(BufWriter::new(file))
+                .with_context(|| format!("Failed to write '{}'", image_path.display()))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{exit_code, read_input, Cli, InputFormats};
+use crate::redact::{self, RedactPresets};
+use crate::sequence::{self, Command, Output, Response};
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Verify {
+    /// The sequence file.
+    ///
+    /// The format is detected from the extension and content. If not present,
+    /// then the -i,--input file or stdin is used.
+    #[structopt(parse(from_os_str))]
+    file: Option<PathBuf>,
+}
+
+impl Verify {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        let path = self.file.or_else(|| cli.input_files.first().cloned());
+        let buffer = read_input(path.as_deref())?;
+        cli.input_format = InputFormats::detect(path.as_deref().unwrap_or(Path::new("")), &buffer);
+        if let InputFormats::Asciicast = cli.input_format {
+            bail!("An asciicast file cannot be verified, use a sequence file");
+        }
+        let sequence = cli.parse(&buffer, path.as_deref())?;
+        let mut patterns = cli.redact.clone();
+        patterns.extend(cli.redact_preset.iter().map(RedactPresets::regex));
+        let ansi = sequence::ansi();
+        let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+        for command in sequence.iter() {
+            if let Some(reason) = skip_reason(command) {
+                println!("skipped: {} ({})", command.text(), reason);
+                skipped += 1;
+                continue;
+            }
+            let executed = cli.execute_cmd(
+                &command.text(),
+                command.cwd(),
+                command.responses(),
+                command.retries(),
+                false,
+            )?;
+            if executed.interrupted {
+                bail!("The input '{}' was interrupted", command.text());
+            }
+            let mut actual = redact::redact(&executed.output, &patterns);
+            if cli.strip_ansi {
+                actual = ansi.replace_all(&actual, "").into_owned();
+            }
+            if !command.responses().is_empty() {
+                // Split like the stored outputs, so a missing prompt fails.
+                let mut executed = Command::from(command.input());
+                executed.set_responses(
+                    command
+                        .responses()
+                        .iter()
+                        .map(|r| Response::new(&r.expect, &r.send))
+                        .collect(),
+                );
+                actual = match executed.append_responded(&mut vec![actual]) {
+                    Ok(executed) => expected_output(executed),
+                    Err(err) => format!("{}", err),
+                };
+            }
+            let expected = expected_output(command);
+            // A command killed by a signal has no exit code and always fails.
+            let status_matches = executed.code == Some(command.status());
+            if normalize(&actual) == normalize(&expected) && status_matches {
+                println!("ok: {}", command.text());
+                passed += 1;
+            } else {
+                println!("FAILED: {}", command.text());
+                if !status_matches {
+                    println!(
+                        "  expected status {}, found {}",
+                        command.status(),
+                        exit_code(executed.code, executed.signal)
+                    );
+                }
+                for line in diff(&normalize(&expected), &normalize(&actual)) {
+                    println!("  {}", line);
+                }
+                failed += 1;
+                if cli.fail_fast {
+                    break;
+                }
+            }
+        }
+        println!("{} passed, {} failed, {} skipped", passed, failed, skipped);
+        if failed != 0 {
+            bail!("{} command(s) did not match their outputs", failed);
+        }
+        Ok(())
+    }
+}
+
+/// The reason a command cannot be executed again, if any.
+fn skip_reason(command: &Command) -> Option<&'static str> {
+    if command.remote().is_some() {
+        Some("remote")
+    } else if command.interrupt() {
+        Some("interrupted")
+    } else if command.secret().is_some() {
+        Some("secret input")
+    } else if command.repl().is_some() {
+        Some("REPL")
+    } else {
+        None
+    }
+}
+
+/// The text of the stored outputs, one after the other on separate lines,
+/// followed by each response after its prompt and the outputs of the
+/// response.
+fn expected_output(command: &Command) -> String {
+    let mut text = command
+        .outputs()
+        .iter()
+        .filter(|o| matches!(o, Output::Text(_)))
+        .map(Output::text)
+        .collect::<Vec<String>>()
+        .join("\n");
+    for response in command.responses() {
+        let response = response.command();
+        text = normalize(&text);
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(response.prompt());
+        text.push_str(response.input());
+        text.push('\n');
+        text.push_str(&expected_output(&response));
+    }
+    text
+}
+
+/// The output with Unix line endings and without trailing newlines, as
+/// stored outputs usually end without one.
+fn normalize(output: &str) -> String {
+    output
+        .replace("\r\n", "\n")
+        .trim_end_matches('\n')
+        .to_owned()
+}
+
+/// The lines removed from the expected output, prefixed with "-", and added
+/// in the actual output, prefixed with "+", between the common lines.
+fn diff(expected: &str, actual: &str) -> Vec<String> {
+    let expected = expected.lines().collect::<Vec<&str>>();
+    let actual = actual.lines().collect::<Vec<&str>>();
+    // The length of the longest common subsequence of the remaining lines.
+    let mut lengths = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = if expected[i] == actual[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(format!(" {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len()
+            && (j == actual.len() || lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            lines.push(format!("-{}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+{}", actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{read_asciicast, read_input, Cli};
+use crate::delay::{self, Delay};
+use crate::image::{Image, Palette};
+use crate::screen::Terminal;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use structopt::StructOpt;
+use strum::{Display, EnumString, EnumVariantNames, VariantNames};
+
+/// The video formats and the arguments of ffmpeg to encode them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum VideoFormats {
+    Mp4,
+    Webm,
+}
+
+impl VideoFormats {
+    /// The video format for the extension of the path, if any.
+    pub fn detect(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("mp4") => Some(Self::Mp4),
+            Some("webm") => Some(Self::Webm),
+            _ => None,
+        }
+    }
+
+    /// The codec arguments of ffmpeg, with the pixel format most players
+    /// support.
+    fn codec(&self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 => &["-c:v", "libx264", "-pix_fmt", "yuv420p"],
+            Self::Webm => &["-c:v", "libvpx-vp9", "-pix_fmt", "yuv420p"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Video {
+    /// The video file.
+    ///
+    /// The format is detected from the extension, "mp4" or "webm", unless
+    /// --format is present.
+    #[structopt(short = "o", long = "output", value_name = "file", parse(from_os_str))]
+    output_file: PathBuf,
+
+    /// The format of the video.
+    #[structopt(
+        long = "format",
+        possible_values = VideoFormats::VARIANTS,
+        case_insensitive = true,
+        value_name = "format"
+    )]
+    format: Option<VideoFormats>,
+
+    /// The number of frames per second.
+    #[structopt(long = "fps", value_name = "n", default_value = "30")]
+    fps: u32,
+
+    /// The time, in seconds, that the last frame is repeated at the end of
+    /// the video.
+    ///
+    /// Videos played in a loop hold the final output, instead of restarting
+    /// right after it.
+    #[structopt(
+        long = "hold-last",
+        value_name = "secs",
+        parse(try_from_str = delay::parse_seconds)
+    )]
+    hold_last: Option<Delay>,
+
+    /// The size, in pixels, of a pixel of the font.
+    ///
+    /// A cell of the terminal is 8 pixels wide and 16 pixels tall at a scale
+    /// of 1.
+    #[structopt(long = "scale", value_name = "n", default_value = "2")]
+    scale: usize,
+
+    /// The ffmpeg program that encodes the frames.
+    ///
+    /// The frames are piped to the program as raw video, so it must be
+    /// installed, but it is not needed for any other output.
+    #[structopt(
+        long = "ffmpeg",
+        value_name = "program",
+        default_value = "ffmpeg",
+        env = "GERM_FFMPEG"
+    )]
+    ffmpeg: String,
+
+    /// The sequence or asciicast file.
+    ///
+    /// The format is detected from the extension and content. If not present,
+    /// then the -i,--input file or stdin is used. Sequences are rendered with
+    /// the options given before the subcommand.
+    #[structopt(parse(from_os_str))]
+    file: Option<PathBuf>,
+}
+
+impl Video {
+    pub fn execute(self, cli: &mut Cli) -> Result<()> {
+        let format = self
+            .format
+            .or_else(|| VideoFormats::detect(&self.output_file))
+            .ok_or_else(|| {
+                anyhow!(
+                    "The video format of '{}' is unknown, use the --format option",
+                    self.output_file.display()
+                )
+            })?;
+        if self.fps == 0 {
+            bail!("The --fps cannot be zero");
+        }
+        if self.scale == 0 {
+            bail!("The --scale cannot be zero");
+        }
+        let path = self
+            .file
+            .clone()
+            .or_else(|| cli.input_files.first().cloned());
+        let buffer = read_input(path.as_deref())?;
+        let mut asciicast = read_asciicast(
+            cli,
+            path.as_deref().unwrap_or_else(|| Path::new("")),
+            &buffer,
+        )?;
+        let palette = Palette::new(asciicast.finalize_header()?.theme.as_ref());
+        let duration = asciicast.duration();
+        let mut terminal = Terminal::new(&asciicast)?;
+        terminal.advance_to(0.0);
+        let mut image = Image::render(terminal.screen(), &palette, self.scale);
+        let (width, height) = (image.width(), image.height());
+        let mut command = Command::new(&self.ffmpeg);
+        command
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgb24",
+            ])
+            .arg("-s")
+            .arg(format!("{}x{}", width, height))
+            .arg("-r")
+            .arg(self.fps.to_string())
+            .args(["-i", "-"])
+            .args(format.codec())
+            .arg(&self.output_file)
+            .stdin(Stdio::piped());
+        debug!("Executing {:?}", command);
+        let mut child = command.spawn().map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => anyhow!(
+                "The '{}' program was not found, install ffmpeg or use the --ffmpeg option",
+                self.ffmpeg
+            ),
+            _ => anyhow!("Failed to execute '{}': {}", self.ffmpeg, err),
+        })?;
+        info!("Writing '{}'", self.output_file.display());
+        let mut stdin = child.stdin.take().expect("Piped stdin");
+        let end = duration + self.hold_last.map_or(0.0, Delay::as_secs_f64);
+        let frames = (end * f64::from(self.fps)).ceil() as u64 + 1;
+        let written = (0..frames).try_for_each(|frame| {
+            // Frames without events are the same as the frame before them.
+            if frame > 0 && terminal.advance_to(frame as f64 / f64::from(self.fps)) {
+                image = Image::render(terminal.screen(), &palette, self.scale);
+                if (image.width(), image.height()) != (width, height) {
+                    bail!("Resizing the terminal is not supported in videos");
+                }
+            }
+            stdin.write_all(image.pixels())?;
+            Ok(())
+        });
+        drop(stdin);
+        let status = child
+            .wait()
+            .with_context(|| format!("Failed to wait for '{}'", self.ffmpeg))?;
+        if !status.success() {
+            bail!("'{}' failed with {}", self.ffmpeg, status);
+        }
+        written
+    }
+}
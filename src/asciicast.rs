@@ -13,27 +13,60 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use anyhow::{Error, Result};
-use serde::Serialize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::sequence::{Command, Sequence, Timings, SECONDS_UNITS};
+use crate::delay::{self, Delay};
+use crate::error::{Error, Result};
+use crate::font;
+use crate::highlight::{self, Highlight};
+use crate::json;
+#[cfg(feature = "cli")]
+#[cfg(feature = "cli")]
+use crate::sequence::SECONDS_UNITS;
+use crate::sequence::{self, Command, InputPart, Output, Secret, Sequence, Timings};
+use crate::theme::ThemeOptions;
+use log::info;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
 use std::env;
 use std::fmt;
-use std::io::Write;
+use std::io::{self, BufRead, Write};
+use std::num::NonZeroUsize;
+#[cfg(feature = "cli")]
 use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "cli")]
 use structopt::StructOpt;
-use strum::{EnumString, EnumVariantNames, VariantNames};
+#[cfg(feature = "cli")]
+use strum::VariantNames;
+use strum::{Display, EnumString, EnumVariantNames};
+#[cfg(feature = "tty")]
+use terminal_size::{Height, Width};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 pub const VERSION: usize = 2;
+pub const CTRL_C: &str = "\u{3}";
+pub const ENTER: &str = "\r";
+pub const CTRL_C_ECHO: &str = "^C\r\n";
 pub const DEFAULT_HEIGHT: &str = "24";
+pub const DEFAULT_INTRO_DURATION: &str = "3";
 pub const DEFAULT_SHELL: &str = "/bin/sh";
+pub const DEFAULT_SSH_BANNER: &str = "Welcome to {host}";
+pub const DEFAULT_COMMENT_PREFIX: &str = "# ";
+pub const DEFAULT_CONTINUATION_PROMPT: &str = "> ";
 pub const DEFAULT_TERM: &str = "xterm-256color";
 pub const DEFAULT_WIDTH: &str = "80";
 pub const MILLISECONDS_IN_A_SECOND: f64 = 1000.0;
 pub const SHELL_VAR_NAME: &str = "SHELL";
 pub const TERM_VAR_NAME: &str = "TERM";
 
-#[derive(Debug, EnumString, EnumVariantNames)]
+/// Hides the cursor while the intro title is displayed.
+const HIDE_CURSOR: &str = "\x1b[?25l";
+
+/// Clears the screen of the intro title and shows the cursor again.
+const CLEAR_INTRO: &str = "\x1b[2J\x1b[H\x1b[?25h";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, EnumVariantNames)]
 #[strum(serialize_all = "lowercase")]
 pub enum ExecuteStringFlags {
     Unix,
@@ -41,6 +74,20 @@ pub enum ExecuteStringFlags {
     Cmd,
 }
 
+impl ExecuteStringFlags {
+    /// The flag for the shell from the name of its program, i.e. "-Command"
+    /// for "/usr/bin/pwsh" or "pwsh.exe".
+    pub fn detect(shell: &str) -> Self {
+        let program = shell.rsplit(['/', '\\']).next().unwrap_or(shell);
+        let program = program.to_lowercase();
+        match program.strip_suffix(".exe").unwrap_or(&program) {
+            "pwsh" | "powershell" => Self::Pwsh,
+            "cmd" => Self::Cmd,
+            _ => Self::Unix,
+        }
+    }
+}
+
 impl fmt::Display for ExecuteStringFlags {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -51,36 +98,195 @@ impl fmt::Display for ExecuteStringFlags {
     }
 }
 
-impl Default for ExecuteStringFlags {
-    fn default() -> Self {
-        Self::Unix
+#[derive(Debug, Clone, Copy, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum Sizes {
+    /// The size of the terminal running germ.
+    Auto,
+    /// The smallest size that displays the recording without wrapping or
+    /// scrolling.
+    Fit,
+}
+
+/// The ANSI style of comments, so they read as narration instead of output.
+#[derive(Debug, Clone, Copy, Default, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum CommentStyles {
+    #[default]
+    None,
+    Dim,
+    Italic,
+    Gray,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl CommentStyles {
+    /// The Select Graphic Rendition (SGR) parameter of the style, if any.
+    pub fn sgr(&self) -> Option<u8> {
+        match self {
+            Self::None => None,
+            Self::Dim => Some(2),
+            Self::Italic => Some(3),
+            Self::Gray => Some(90),
+            Self::Red => Some(31),
+            Self::Green => Some(32),
+            Self::Yellow => Some(33),
+            Self::Blue => Some(34),
+            Self::Magenta => Some(35),
+            Self::Cyan => Some(36),
+        }
+    }
+
+    /// Applies the style to the text and resets it afterwards.
+    pub fn paint(&self, text: &str) -> String {
+        match self.sgr() {
+            Some(sgr) => format!("\x1b[{}m{}\x1b[0m", sgr, text),
+            None => text.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum TypingUnits {
+    /// An event for each character.
+    #[default]
+    Char,
+    /// An event for each word and the whitespace after it.
+    Word,
+}
+
+impl TypingUnits {
+    /// Splits the input into the text typed by each event and the index of
+    /// its first grapheme cluster, i.e. a character as seen by the user.
+    pub fn split(&self, input: &str) -> Vec<(usize, String)> {
+        let mut units: Vec<(usize, String)> = Vec::new();
+        let mut previous_is_space = true;
+        for (i, g) in input.graphemes(true).enumerate() {
+            let is_space = g.chars().all(char::is_whitespace);
+            match (self, units.last_mut()) {
+                (Self::Word, Some((_, unit))) if is_space || !previous_is_space => unit.push_str(g),
+                _ => units.push((i, g.to_owned())),
+            }
+            previous_is_space = is_space;
+        }
+        units
     }
 }
 
-#[derive(Debug, Serialize, StructOpt)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "cli", derive(StructOpt))]
 pub struct Env {
     /// The SHELL environment variable for the recording.
-    #[structopt(short = "S", long, env = "SHELL", default_value = DEFAULT_SHELL)]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(short = "S", long, env = "SHELL", default_value = DEFAULT_SHELL)
+    )]
     #[serde(rename = "SHELL")]
     pub shell: String,
 
     /// The TERM environment variable for the recording.
-    #[structopt(short = "T", long, env = "TERM", default_value = DEFAULT_TERM)]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(short = "T", long, env = "TERM", default_value = DEFAULT_TERM)
+    )]
     #[serde(rename = "TERM")]
     pub term: String,
 
     /// The flag passed to the shell to execute the string as a command.
-    #[structopt(
-        short = "E",
-        long,
-        possible_values = ExecuteStringFlags::VARIANTS,
-        case_insensitive = true,
-        value_name = "shell",
-        env = "GERM_EXECUTE_STRING_FLAG",
-        default_value = "unix"
+    ///
+    /// By default, this is detected from the shell: "-Command" for pwsh and
+    /// powershell, "/c" for cmd, and "-c" for the others.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            short = "E",
+            long,
+            possible_values = ExecuteStringFlags::VARIANTS,
+            case_insensitive = true,
+            value_name = "shell",
+            env = "GERM_EXECUTE_STRING_FLAG"
+        )
+    )]
+    #[serde(skip)]
+    pub execute_string_flag: Option<ExecuteStringFlags>,
+
+    /// Adds an environment variable to the recording, i.e. "KEY=VALUE".
+    ///
+    /// This can be used multiple times.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "env",
+            value_name = "key=value",
+            parse(try_from_str = parse_var),
+            number_of_values = 1
+        )
     )]
     #[serde(skip)]
-    pub execute_string_flag: ExecuteStringFlags,
+    pub vars: Vec<(String, String)>,
+
+    /// Adds an environment variable with its current value to the recording.
+    ///
+    /// The variable is skipped if it is not set. This can be used multiple
+    /// times.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long = "env-capture", value_name = "key", number_of_values = 1)
+    )]
+    #[serde(skip)]
+    pub captures: Vec<String>,
+
+    /// The environment variables other than SHELL and TERM.
+    #[cfg_attr(feature = "cli", structopt(skip))]
+    #[serde(flatten)]
+    pub others: BTreeMap<String, String>,
+}
+
+impl Env {
+    /// The flag passed to the shell to execute the string as a command, from
+    /// the -E,--execute-string-flag option or detected from the shell.
+    pub fn execute_string_flag(&self) -> ExecuteStringFlags {
+        self.execute_string_flag
+            .unwrap_or_else(|| ExecuteStringFlags::detect(&self.shell))
+    }
+
+    /// Moves the variables from the --env and --env-capture options into the
+    /// variables of the recording.
+    pub fn collect(&mut self) -> &mut Self {
+        let captured = self
+            .captures
+            .drain(..)
+            .filter_map(|k| env::var(&k).ok().map(|v| (k, v)))
+            .collect::<Vec<(String, String)>>();
+        for (key, value) in captured.into_iter().chain(self.vars.drain(..)) {
+            match key.as_str() {
+                SHELL_VAR_NAME => self.shell = value,
+                TERM_VAR_NAME => self.term = value,
+                _ => {
+                    self.others.insert(key, value);
+                }
+            }
+        }
+        self
+    }
+
+    /// Replaces the SHELL and TERM inherited from the environment with the
+    /// defaults.
+    pub fn uninherit(&mut self) -> &mut Self {
+        if env::var(SHELL_VAR_NAME).is_ok_and(|s| s == self.shell) {
+            self.shell = String::from(DEFAULT_SHELL);
+        }
+        if env::var(TERM_VAR_NAME).is_ok_and(|t| t == self.term) {
+            self.term = String::from(DEFAULT_TERM);
+        }
+        self
+    }
 }
 
 impl Default for Env {
@@ -92,12 +298,15 @@ impl Default for Env {
             term: env::var_os(TERM_VAR_NAME)
                 .map(|s| String::from(s.to_string_lossy()))
                 .unwrap_or_else(|| String::from(DEFAULT_TERM)),
-            execute_string_flag: ExecuteStringFlags::default(),
+            execute_string_flag: None,
+            vars: Vec::new(),
+            captures: Vec::new(),
+            others: BTreeMap::new(),
         }
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Theme {
     #[serde(rename = "fg")]
     pub foreground: String,
@@ -108,21 +317,28 @@ pub struct Theme {
     pub palette: String,
 }
 
-#[derive(Debug, Serialize, StructOpt)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "cli", derive(StructOpt))]
 pub struct Header {
-    #[structopt(skip = VERSION)]
+    #[cfg_attr(feature = "cli", structopt(skip = VERSION))]
     version: usize,
 
     /// The number of columns for the terminal.
-    #[structopt(short = "W", long, default_value = DEFAULT_WIDTH, value_name = "cols")]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(short = "W", long, default_value = DEFAULT_WIDTH, value_name = "cols")
+    )]
     pub width: usize,
 
     /// The number of rows for the terminal.
-    #[structopt(
-        short = "H",
-        long = "height",
-        default_value = DEFAULT_HEIGHT,
-        value_name = "rows"
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            short = "H",
+            long = "height",
+            default_value = DEFAULT_HEIGHT,
+            value_name = "rows"
+        )
     )]
     pub height: usize,
 
@@ -131,42 +347,66 @@ pub struct Header {
     /// This is the number of seconds since the UNIX EPOCH. If the value is
     /// "now", then the current UNIX timestamp will be used.
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[structopt(long, value_name = SECONDS_UNITS, parse(try_from_str = parse_timestamp))]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long, value_name = SECONDS_UNITS, parse(try_from_str = parse_timestamp))
+    )]
     pub timestamp: Option<u64>,
 
     /// The length of the recording if known ahead of time.
     ///
     /// The value is in seconds.
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[structopt(long, value_name = SECONDS_UNITS)]
+    #[cfg_attr(feature = "cli", structopt(long, value_name = SECONDS_UNITS))]
     pub duration: Option<f64>,
 
     /// The idle time limit.
     ///
+    /// Players cap any pause between events to this limit during playback.
     /// The value is in seconds.
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[structopt(long, value_name = SECONDS_UNITS)]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            value_name = SECONDS_UNITS,
+            env = "GERM_IDLE_TIME_LIMIT",
+            parse(try_from_str = parse_limit)
+        )
+    )]
     pub idle_time_limit: Option<f64>,
 
     /// The command executed for the recording.
+    ///
+    /// Players and asciinema.org display this as metadata. If not present and
+    /// the sequence is a single input that is executed to obtain its output,
+    /// then the input is used.
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[structopt(long, value_name = "cmd")]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long = "header-command", alias = "command", value_name = "cmd")
+    )]
     pub command: Option<String>,
 
     /// The title for the asciicast file.
-    #[structopt(short, long)]
+    #[cfg_attr(feature = "cli", structopt(short, long))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
 
-    #[structopt(flatten)]
+    #[serde(default)]
+    #[cfg_attr(feature = "cli", structopt(flatten))]
     pub env: Env,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[structopt(skip)]
+    #[cfg_attr(feature = "cli", structopt(skip))]
     pub theme: Option<Theme>,
 }
 
 impl Header {
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
     pub fn write_to<W>(&self, mut writer: W) -> Result<()>
     where
         W: Write,
@@ -194,128 +434,1203 @@ impl Default for Header {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum EventKind {
-    #[serde(rename = "o")]
     Printed,
-
-    #[serde(rename = "i")]
     Keypress,
+
+    /// A marker, or chapter, used by players for navigation.
+    Marker,
+
+    /// A change of the terminal size, i.e. "{cols}x{rows}".
+    Resize,
+
+    /// An event code not known to germ.
+    ///
+    /// The code is kept verbatim so casts using newer channels can be read,
+    /// manipulated, and written again without losing any events.
+    Custom(String),
 }
 
-impl fmt::Display for EventKind {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl EventKind {
+    pub fn code(&self) -> &str {
         match self {
-            Self::Printed => write!(f, "o"),
-            Self::Keypress => write!(f, "i"),
+            Self::Printed => "o",
+            Self::Keypress => "i",
+            Self::Marker => "m",
+            Self::Resize => "r",
+            Self::Custom(c) => c,
+        }
+    }
+}
+
+impl From<&str> for EventKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "o" => Self::Printed,
+            "i" => Self::Keypress,
+            "m" => Self::Marker,
+            "r" => Self::Resize,
+            _ => Self::Custom(s.to_owned()),
         }
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct Event(pub f64, pub EventKind, pub String);
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl Serialize for EventKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for EventKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Ok(Self::from(code.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Event(pub f64, pub EventKind, pub Cow<'static, str>);
 
 impl Event {
     pub fn write_to<W>(&mut self, mut writer: W) -> Result<()>
     where
         W: Write,
     {
-        self.0 = (self.0 * MILLISECONDS_IN_A_SECOND).trunc() / MILLISECONDS_IN_A_SECOND;
+        self.0 = truncate_to_milliseconds(self.0);
         serde_json::to_writer(&mut writer, self)?;
         writeln!(&mut writer)?;
         Ok(())
     }
 }
 
-#[derive(Debug, Default, StructOpt)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "cli", derive(StructOpt))]
 pub struct Asciicast {
-    #[structopt(flatten)]
+    #[cfg_attr(feature = "cli", structopt(flatten))]
     pub header: Header,
 
-    #[structopt(skip)]
+    #[cfg_attr(feature = "cli", structopt(flatten))]
+    pub theme: ThemeOptions,
+
+    #[cfg_attr(feature = "cli", structopt(skip))]
     events: Vec<Event>,
 
+    /// Ends the lines of a pasted input with CRLF in the keypress events,
+    /// like pasting on Windows.
+    #[cfg_attr(feature = "cli", structopt(skip))]
+    pub crlf: bool,
+
     /// Mimic keypress logging functionality of the asciinema record functionality.
-    #[structopt(long)]
+    ///
+    /// Each typed character is logged, as well as the Enter that submits the
+    /// input.
+    #[cfg_attr(feature = "cli", structopt(long))]
     pub stdin: bool,
+
+    /// Adds a marker event for each command with a comment.
+    ///
+    /// The comment is used as the label of the marker, which players display
+    /// as a chapter for navigation.
+    #[cfg_attr(feature = "cli", structopt(long))]
+    pub markers: bool,
+
+    /// The output of the `ssh host` command typed before remote commands.
+    ///
+    /// Any "{host}" is replaced with the name of the remote host. Use an empty
+    /// value for no banner.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long, default_value = DEFAULT_SSH_BANNER, value_name = "text")
+    )]
+    pub ssh_banner: String,
+
+    /// The amount of input typed by each event.
+    ///
+    /// Typing a word at a time greatly reduces the number of events for long
+    /// inputs. Each word appears when its first character would have been
+    /// typed, so the pacing is unchanged.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            possible_values = TypingUnits::VARIANTS,
+            case_insensitive = true,
+            default_value,
+            value_name = "unit",
+            env = "GERM_TYPING_UNIT"
+        )
+    )]
+    pub typing_unit: TypingUnits,
+
+    /// The prompt for each line after the first of a multi-line input.
+    ///
+    /// The lines of an input, like the body of a heredoc, are typed under
+    /// this prompt and the whole input is executed as one command.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            default_value = DEFAULT_CONTINUATION_PROMPT,
+            value_name = "prompt",
+            env = "GERM_CONTINUATION_PROMPT"
+        )
+    )]
+    pub continuation_prompt: String,
+
+    /// The text printed before each line of a comment.
+    ///
+    /// Use an empty value to print comments as they are.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            default_value = DEFAULT_COMMENT_PREFIX,
+            value_name = "text",
+            env = "GERM_COMMENT_PREFIX"
+        )
+    )]
+    pub comment_prefix: String,
+
+    /// The color or style of comments.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            possible_values = CommentStyles::VARIANTS,
+            case_insensitive = true,
+            default_value,
+            value_name = "style",
+            env = "GERM_COMMENT_STYLE"
+        )
+    )]
+    pub comment_style: CommentStyles,
+
+    /// Paints the matches of a regular expression in the outputs, i.e.
+    /// "error:.*=red".
+    ///
+    /// The rule is the regular expression and a style of --comment-style
+    /// separated by the last "=". The outputs are only painted in the
+    /// events, so sequence files are unchanged. This can be used multiple
+    /// times, and the first rule wins where matches overlap.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            value_name = "rule",
+            number_of_values = 1,
+            parse(try_from_str = highlight::parse_rule)
+        )
+    )]
+    pub highlight: Vec<Highlight>,
+
+    /// A title displayed in the middle of the screen before the first
+    /// prompt.
+    ///
+    /// The title card opens the recording with context, i.e. the name of the
+    /// project, and is cleared after the --intro-duration. Each line of the
+    /// title is centered.
+    #[cfg_attr(feature = "cli", structopt(long, value_name = "text"))]
+    pub intro_title: Option<String>,
+
+    /// The time, in seconds, that the --intro-title is displayed.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            default_value = DEFAULT_INTRO_DURATION,
+            value_name = "secs",
+            parse(try_from_str = delay::parse_seconds)
+        )
+    )]
+    pub intro_duration: Delay,
+
+    /// Displays the --intro-title in large letters drawn with block
+    /// characters, like figlet.
+    ///
+    /// A title that is wider than the terminal is displayed as is.
+    #[cfg_attr(feature = "cli", structopt(long, requires = "intro-title"))]
+    pub intro_banner: bool,
+
+    /// The time, in seconds, that the last frame is held after the end
+    /// delay.
+    ///
+    /// Players that loop restart right after the last event, so this gives
+    /// viewers time to read the final output.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            value_name = "secs",
+            parse(try_from_str = delay::parse_seconds)
+        )
+    )]
+    pub hold_last: Option<Delay>,
+
+    /// Wraps output lines longer than the width of the terminal.
+    ///
+    /// The lines are wrapped at the last column, like a terminal does, so
+    /// players that clip or wrap long lines display the same output.
+    #[cfg_attr(feature = "cli", structopt(long))]
+    pub wrap: bool,
+
+    /// Combines consecutive output events with the same time into one event.
+    ///
+    /// The lines of an output are printed at once, so this shrinks the file
+    /// and players draw each output in a single update.
+    #[cfg_attr(feature = "cli", structopt(long))]
+    pub merge_events: bool,
+
+    /// Writes the same file for the same input, wherever germ is run.
+    ///
+    /// The header has no timestamp, and the SHELL and TERM inherited from the
+    /// environment are replaced with the defaults, "/bin/sh" and
+    /// "xterm-256color", so generated files can be committed and compared in
+    /// CI. The shell used to execute commands is unchanged.
+    #[cfg_attr(feature = "cli", structopt(long, conflicts_with_all = &["timestamp", "captures"]))]
+    pub deterministic: bool,
+
+    /// The most bytes printed by each event of a text output.
+    ///
+    /// The lines of an output are packed into events of at most this size,
+    /// and longer lines are split, instead of an event for each line. This
+    /// keeps the number and size of the events in check for very large
+    /// outputs.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long, value_name = "bytes", env = "GERM_CHUNK_SIZE")
+    )]
+    pub chunk_size: Option<NonZeroUsize>,
+
+    /// The most lines displayed for each output of a command.
+    ///
+    /// The first and last lines of a longer output are kept, with a line like
+    /// "… (1234 lines omitted)" between them, so printing a large file does
+    /// not make the recording unwatchable.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long, value_name = "lines", env = "GERM_MAX_OUTPUT_LINES")
+    )]
+    pub max_output_lines: Option<usize>,
+
+    /// The longest time, in seconds, between two events of an asciicast input.
+    ///
+    /// Longer pauses in a converted recording are shortened to this time in
+    /// the output file, so every player skips them, not only those honoring
+    /// the idle time limit of the header.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            value_name = "secs",
+            env = "GERM_MAX_IDLE",
+            parse(try_from_str = parse_limit)
+        )
+    )]
+    pub max_idle: Option<f64>,
+
+    /// Sets the number of columns and rows for the terminal.
+    ///
+    /// Use "auto" for the size of the terminal running germ, or "fit" for the
+    /// longest line and the most lines displayed at once, instead of the
+    /// -W,--width and -H,--height values.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            possible_values = Sizes::VARIANTS,
+            case_insensitive = true,
+            conflicts_with_all = &["width", "height"],
+            value_name = "size",
+            env = "GERM_SIZE"
+        )
+    )]
+    pub size: Option<Sizes>,
+}
+
+impl Default for Asciicast {
+    fn default() -> Self {
+        Self {
+            header: Header::default(),
+            theme: ThemeOptions::default(),
+            events: Vec::new(),
+            crlf: false,
+            stdin: false,
+            markers: false,
+            ssh_banner: String::from(DEFAULT_SSH_BANNER),
+            typing_unit: TypingUnits::default(),
+            continuation_prompt: String::from(DEFAULT_CONTINUATION_PROMPT),
+            comment_prefix: String::from(DEFAULT_COMMENT_PREFIX),
+            comment_style: CommentStyles::default(),
+            highlight: Vec::new(),
+            intro_title: None,
+            intro_duration: delay::parse_seconds(DEFAULT_INTRO_DURATION).expect("Valid delay"),
+            intro_banner: false,
+            hold_last: None,
+            wrap: false,
+            merge_events: false,
+            deterministic: false,
+            chunk_size: None,
+            max_output_lines: None,
+            max_idle: None,
+            size: None,
+        }
+    }
 }
 
 impl Asciicast {
+    /// The asciicast with the events of the sequence, displayed with the
+    /// settings of the other asciicast, i.e. its header and typing unit.
+    ///
+    /// The events of the other asciicast are not copied.
+    pub fn from_sequence(sequence: &Sequence, settings: &Asciicast) -> Self {
+        let mut asciicast = settings.settings();
+        asciicast.append_from(sequence);
+        if let Some(Sizes::Fit) = asciicast.size {
+            asciicast.fit();
+        }
+        if asciicast.merge_events {
+            asciicast.merge();
+        }
+        asciicast
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// The time of the last event in seconds.
+    pub fn duration(&self) -> f64 {
+        self.events.iter().map(|e| e.0).fold(0.0, f64::max)
+    }
+
+    /// Adds the events of the other asciicast after the last event, and
+    /// enlarges the terminal to fit both.
+    pub fn concat(&mut self, other: Asciicast) -> &mut Self {
+        let offset = self.duration();
+        self.header.width = self.header.width.max(other.header.width);
+        self.header.height = self.header.height.max(other.header.height);
+        self.events.extend(
+            other
+                .events
+                .into_iter()
+                .map(|Event(time, kind, data)| Event(offset + time, kind, data)),
+        );
+        self
+    }
+
+    /// Drops the events before `from` and after `to` and rebases the remaining
+    /// events to start at zero.
+    pub fn cut(&mut self, from: f64, to: Option<f64>) -> &mut Self {
+        let to = to.unwrap_or(f64::INFINITY);
+        self.events
+            .retain(|Event(time, ..)| *time >= from && *time <= to);
+        for Event(time, ..) in &mut self.events {
+            *time -= from;
+        }
+        self
+    }
+
+    /// Shortens every gap between consecutive events to at most `max` seconds.
+    pub fn clamp_idle(&mut self, max: f64) -> &mut Self {
+        let mut previous = 0.0;
+        let mut shift = 0.0;
+        for Event(time, ..) in &mut self.events {
+            let gap = *time - previous;
+            previous = *time;
+            if gap > max {
+                shift += gap - max;
+            }
+            *time -= shift;
+        }
+        self
+    }
+
+    /// Splits the printed output into commands, using the prompt to find the
+    /// lines with an input.
+    ///
+    /// A line containing the prompt starts a command with the rest of the
+    /// line as its input, and the lines up to the next prompt are its output.
+    /// ANSI escape sequences are ignored when looking for the prompt and
+    /// anything printed before the first prompt is dropped.
+    pub fn commands(&self, prompt: &str) -> Vec<Command> {
+        let ansi = sequence::ansi();
+        let prompt = ansi.replace_all(prompt, "");
+        let printed: String = self
+            .events
+            .iter()
+            .filter(|Event(_, kind, _)| matches!(kind, EventKind::Printed))
+            .map(|Event(_, _, data)| data.as_ref())
+            .collect();
+        let mut commands = Vec::new();
+        let mut current: Option<(Command, Vec<&str>)> = None;
+        for line in printed.split('\n') {
+            let line = displayed(line);
+            let plain = erase_backspaces(&ansi.replace_all(line, ""));
+            match plain.find(prompt.as_ref()) {
+                Some(i) => {
+                    commands.extend(current.take().map(with_output));
+                    let input = plain[i + prompt.len()..].trim();
+                    if !input.is_empty() {
+                        current = Some((Command::from(input), Vec::new()));
+                    }
+                }
+                None => {
+                    if let Some((_, lines)) = current.as_mut() {
+                        lines.push(line);
+                    }
+                }
+            }
+        }
+        commands.extend(current.map(with_output));
+        commands
+    }
+
+    /// Combines consecutive printed events with the same time, to the
+    /// millisecond, into one event.
+    pub fn merge(&mut self) -> &mut Self {
+        let mut merged: Vec<Event> = Vec::with_capacity(self.events.len());
+        for event in self.events.drain(..) {
+            match (merged.last_mut(), &event) {
+                (
+                    Some(Event(time, EventKind::Printed, data)),
+                    Event(next_time, EventKind::Printed, next_data),
+                ) if truncate_to_milliseconds(*time) == truncate_to_milliseconds(*next_time) => {
+                    data.to_mut().push_str(next_data);
+                }
+                _ => merged.push(event),
+            }
+        }
+        self.events = merged;
+        self
+    }
+
+    /// Removes the events, in order, leaving the header untouched.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, Event> {
+        self.events.drain(..)
+    }
+
+    /// A copy without the events.
+    fn settings(&self) -> Self {
+        Self {
+            events: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.events.clear();
+        self
+    }
+
+    /// Sets the width and height of the header from the --size option.
+    ///
+    /// The "fit" size depends on the events, so it is applied when writing.
+    pub fn resize(&mut self) -> Result<&mut Self> {
+        match self.size {
+            Some(Sizes::Auto) => {
+                let (width, height) = detect_terminal_size().ok_or_else(|| {
+                    Error::Io(io::Error::other(
+                        "Failed to detect the size of the terminal",
+                    ))
+                })?;
+                self.header.width = width;
+                self.header.height = height;
+            }
+            Some(Sizes::Fit) | None => {}
+        }
+        Ok(self)
+    }
+
+    /// Sets the width and height of the header to the longest line and the
+    /// most lines displayed at once by the printed events.
+    ///
+    /// Clearing the screen, i.e. "\x1b[2J", starts counting lines again.
+    pub fn fit(&mut self) -> &mut Self {
+        let mut width = 1;
+        let mut height = 1;
+        let mut column = 0;
+        let mut lines = 1;
+        for event in self.events.iter().filter(|e| e.1 == EventKind::Printed) {
+            let mut graphemes = event.2.graphemes(true);
+            while let Some(g) = graphemes.next() {
+                match g {
+                    "\n" | "\r\n" => {
+                        lines += 1;
+                        column = 0;
+                    }
+                    "\r" => column = 0,
+                    "\x1b" if graphemes.next() == Some("[") => {
+                        let mut params = String::new();
+                        for g in graphemes.by_ref() {
+                            if g.len() == 1 && ("@"..="~").contains(&g) {
+                                if g == "J" && (params == "2" || params == "3") {
+                                    lines = 1;
+                                }
+                                break;
+                            }
+                            params.push_str(g);
+                        }
+                    }
+                    g if g.chars().all(char::is_control) => {}
+                    g => {
+                        column += g.width();
+                        width = width.max(column);
+                    }
+                }
+            }
+            height = height.max(lines);
+        }
+        // Resizes back to the header size follow the fitted size, while
+        // commands with their own size keep it.
+        let previous = format!("{}x{}", self.header.width, self.header.height);
+        self.header.width = width;
+        self.header.height = height;
+        for event in self.events.iter_mut().filter(|e| e.1 == EventKind::Resize) {
+            if event.2 == previous {
+                event.2 = format!("{}x{}", width, height).into();
+            }
+        }
+        self
+    }
+
     pub fn add(&mut self, event: Event) -> &mut Self {
         self.events.push(event);
         self
     }
 
     pub fn append_from(&mut self, sequence: &Sequence) -> &mut Self {
-        let start_delay = sequence
-            .iter()
-            .fold(sequence.timings().begin, |start_delay, command| {
-                self.add_command(command, sequence.timings(), start_delay)
-            });
-        if sequence.timings().end.into_milliseconds() as usize != 0 {
+        let start_delay = self.add_intro(sequence.timings());
+        self.append_at(sequence, start_delay)
+    }
+
+    /// Adds the title card of the intro title, if any, and returns the delay
+    /// of the first command, after the title card and the begin delay.
+    pub fn add_intro(&mut self, timings: &Timings) -> f64 {
+        let begin = timings.begin.as_secs_f64();
+        let title = match &self.intro_title {
+            Some(title) => title,
+            None => return begin,
+        };
+        let mut lines: Vec<String> = Vec::new();
+        for line in title.lines() {
+            match font::banner(line) {
+                banner if self.intro_banner && banner_fits(&banner, self.header.width) => {
+                    lines.extend(banner)
+                }
+                _ => lines.push(line.to_owned()),
+            }
+        }
+        let top = self.header.height.saturating_sub(lines.len()) / 2;
+        let mut card = String::from(HIDE_CURSOR);
+        for (i, line) in lines.iter().enumerate() {
+            let left = self.header.width.saturating_sub(line.width()) / 2;
+            card.push_str(&format!("\x1b[{};{}H{}", top + i + 1, left + 1, line));
+        }
+        let duration = self.intro_duration.as_secs_f64();
+        self.add(Event(0.0, EventKind::Printed, card.into()));
+        self.add(Event(
+            duration,
+            EventKind::Printed,
+            Cow::Borrowed(CLEAR_INTRO),
+        ));
+        duration + begin
+    }
+
+    /// Adds the events for the sequence starting at the delay, instead of
+    /// the begin delay of the sequence, i.e. after the events of a recording.
+    pub fn append_at(&mut self, sequence: &Sequence, start_delay: f64) -> &mut Self {
+        let sequence = &sequence.framed(&self.ssh_banner);
+        let start_delay = sequence.iter().fold(start_delay, |start_delay, command| {
+            self.add_command(
+                command,
+                sequence.prompt_of(command),
+                sequence.timings(),
+                start_delay,
+            )
+        });
+        self.add_end(sequence.timings(), start_delay)
+    }
+
+    /// Adds the empty event that holds the last frame for the end delay, and
+    /// the hold of the last frame.
+    pub fn add_end(&mut self, timings: &Timings, start_delay: f64) -> &mut Self {
+        let hold = timings.end.as_secs_f64() + self.hold_last.map_or(0.0, Delay::as_secs_f64);
+        if hold != 0.0 {
             self.add(Event(
-                start_delay + sequence.timings().end,
+                start_delay + hold,
                 EventKind::Printed,
-                String::new(),
+                Cow::Borrowed(""),
             ));
         }
         self
     }
 
-    fn add_command(&mut self, command: &Command, timings: &Timings, start_delay: f64) -> f64 {
+    /// Adds the events for a command, displayed after the prompt, starting at
+    /// the delay and returns the delay at which the command ends.
+    pub fn add_command(
+        &mut self,
+        command: &Command,
+        prompt: &str,
+        timings: &Timings,
+        start_delay: f64,
+    ) -> f64 {
+        let width = command.width().unwrap_or(self.header.width);
+        let height = command.height().unwrap_or(self.header.height);
+        let resized = width != self.header.width || height != self.header.height;
+        if resized {
+            self.add_resize(start_delay, width, height);
+        }
         if let Some(c) = command.comment() {
-            let mut comment = c.to_owned();
-            comment.push_str("\r\n");
-            self.add(Event(start_delay, EventKind::Printed, comment));
+            if self.markers {
+                self.add(Event(start_delay, EventKind::Marker, c.to_owned().into()));
+            }
+            let mut comment = String::new();
+            for line in c.lines() {
+                let mut styled = self.comment_prefix.clone();
+                styled.push_str(line);
+                comment.push_str(&self.comment_style.paint(&styled));
+                comment.push_str("\r\n");
+            }
+            self.add(Event(start_delay, EventKind::Printed, comment.into()));
         }
+        let start_delay = match command.comment() {
+            Some(_) => {
+                start_delay
+                    + (command
+                        .comment_delay()
+                        .unwrap_or(timings.comment_delay)
+                        .as_millis() as f64)
+                        .speed(timings.speed)
+                        .into_seconds()
+            }
+            None => start_delay,
+        };
         self.add(Event(
             start_delay,
             EventKind::Printed,
-            command.prompt().to_owned(),
+            prompt.to_owned().into(),
         ));
-        let input_time = ((timings.type_start
-            + timings.type_char * command.input().len()
-            + timings.type_submit) as f64)
-            .speed(timings.speed)
-            .into_seconds();
-        for (i, c) in command.input().chars().map(|c| c.to_string()).enumerate() {
+        // The delay in milliseconds after the type start delay, the printed
+        // text, and the pressed keys, if different from the text, of each
+        // typing event.
+        let mut typed: Vec<(usize, Cow<str>, Option<Cow<str>>)> = Vec::new();
+        let mut elapsed = 0;
+        let mut continuation = String::from("\r\n");
+        continuation.push_str(&self.continuation_prompt);
+        if command.paste() {
+            let text = command.text();
+            let keys = if self.crlf {
+                text.replace('\n', "\r\n")
+            } else {
+                text.clone()
+            };
+            typed.push((
+                0,
+                Cow::Owned(text.replace('\n', &continuation)),
+                Some(keys.into()),
+            ));
+        } else {
+            for part in command.parts() {
+                match part {
+                    InputPart::Typed(text) => {
+                        // Each line after the first is typed after pressing
+                        // enter at the continuation prompt, like a heredoc.
+                        for (n, line) in text.split('\n').enumerate() {
+                            if n != 0 {
+                                elapsed += timings.type_submit.as_millis();
+                                typed.push((
+                                    elapsed,
+                                    Cow::Owned(continuation.clone()),
+                                    Some(Cow::Borrowed("\r")),
+                                ));
+                                elapsed += timings.type_char.as_millis();
+                            }
+                            let line = line.trim_end_matches('\r');
+                            let offsets = timings.type_offsets(line);
+                            for (i, unit) in self.typing_unit.split(line) {
+                                typed.push((elapsed + offsets[i], Cow::Owned(unit), None));
+                            }
+                            elapsed += offsets[offsets.len() - 1];
+                        }
+                    }
+                    InputPart::Completed(text) => {
+                        elapsed += timings.type_submit.as_millis();
+                        typed.push((
+                            elapsed,
+                            Cow::Owned(text.to_owned()),
+                            Some(Cow::Borrowed("\t")),
+                        ));
+                        elapsed += timings.type_char.as_millis();
+                    }
+                }
+            }
+        }
+        let mut input_time =
+            ((timings.type_start.as_millis() + elapsed + timings.type_submit.as_millis()) as f64)
+                .speed(timings.speed)
+                .into_seconds();
+        // The echo of each typed character trails its keypress, so the events
+        // are ordered by time once all of them are known.
+        let echo_latency = if self.stdin {
+            (timings.echo_latency.as_millis() as f64)
+                .speed(timings.speed)
+                .into_seconds()
+        } else {
+            0.0
+        };
+        let mut typed_events = Vec::new();
+        for (offset, text, keys) in typed {
             let char_delay = start_delay
-                + ((timings.type_start + timings.type_char * i) as f64)
+                + ((timings.type_start.as_millis() + offset) as f64)
                     .speed(timings.speed)
                     .into_seconds();
             if self.stdin {
-                self.add(Event(char_delay, EventKind::Keypress, c.clone()));
+                let keys = keys.unwrap_or_else(|| text.clone());
+                typed_events.push(Event(char_delay, EventKind::Keypress, keys));
             }
-            self.add(Event(char_delay, EventKind::Printed, c));
+            typed_events.push(Event(char_delay + echo_latency, EventKind::Printed, text));
+        }
+        // The input is submitted with Enter, unless it is interrupted before
+        // any outputs, which cancels it instead.
+        if self.stdin && !(command.interrupt() && command.outputs().is_empty()) {
+            typed_events.push(Event(
+                start_delay + input_time,
+                EventKind::Keypress,
+                Cow::Borrowed(ENTER),
+            ));
+        }
+        typed_events.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.events.extend(typed_events);
+        if let Some(secret) = command.secret() {
+            input_time += self.add_secret(secret, timings, start_delay + input_time);
         }
+        // Animated outputs delay the outputs after them.
+        let mut animation_time = 0.0;
         for (i, output) in command.outputs().iter().enumerate() {
             let show_delay = start_delay
                 + input_time
-                + ((timings.output_line * (i + 1)) as f64)
+                + animation_time
+                + ((timings.output_line.as_millis() * (i + 1)) as f64)
+                    .speed(timings.speed)
+                    .into_seconds();
+            // The secret input already ends the line of the input.
+            if i == 0 && command.secret().is_none() {
+                self.add(Event(show_delay, EventKind::Printed, Cow::Borrowed("\r\n")));
+            }
+            match output {
+                Output::Text(text) => {
+                    let max_lines = command.max_output_lines().or(self.max_output_lines);
+                    let mut lines = Vec::new();
+                    for mut line in truncate_lines(text, max_lines) {
+                        if !self.highlight.is_empty() {
+                            line = Cow::Owned(highlight::highlight(&line, &self.highlight));
+                        }
+                        if self.wrap {
+                            lines.extend(wrap_line(&line, width).into_iter().map(Cow::Owned));
+                        } else {
+                            lines.push(line);
+                        }
+                    }
+                    self.add_lines(lines, show_delay);
+                }
+                Output::Progress(progress) => {
+                    self.add_frames(progress.frames(), timings, show_delay)
+                }
+                Output::Spinner(spinner) => self.add_frames(spinner.frames(), timings, show_delay),
+            }
+            animation_time += output.duration().speed(timings.speed);
+        }
+        let outputs_time = ((timings.output_line.as_millis() * command.outputs().len()) as f64)
+            .speed(timings.speed)
+            .into_seconds()
+            + animation_time;
+        let mut end_delay = start_delay + input_time + outputs_time;
+        // A response is typed on the line of its prompt, which starts on a
+        // new line if there were no outputs to end the previous line.
+        let mut line_ended = !command.outputs().is_empty() || command.secret().is_some();
+        for response in command.responses() {
+            let response = response.command();
+            if !line_ended {
+                self.add(Event(end_delay, EventKind::Printed, Cow::Borrowed("\r\n")));
+            }
+            line_ended = !response.outputs().is_empty();
+            end_delay = self.add_command(&response, response.prompt(), timings, end_delay);
+        }
+        if let Some(repl) = command.repl() {
+            end_delay = repl.iter().fold(end_delay, |start_delay, step| {
+                self.add_command(&step, &repl.prompt, timings, start_delay)
+            });
+        }
+        if command.interrupt() {
+            // Without outputs, the typed input is canceled instead of submitted.
+            if !command.outputs().is_empty() {
+                end_delay += (timings.output_line.as_millis() as f64)
                     .speed(timings.speed)
                     .into_seconds();
-            if i == 0 {
-                self.add(Event(show_delay, EventKind::Printed, String::from("\r\n")));
             }
-            for line in output.lines() {
-                let mut output_data = String::from(line);
-                output_data.push_str("\r\n");
-                self.add(Event(show_delay, EventKind::Printed, output_data));
+            if self.stdin {
+                self.add(Event(end_delay, EventKind::Keypress, Cow::Borrowed(CTRL_C)));
+            }
+            self.add(Event(
+                end_delay,
+                EventKind::Printed,
+                Cow::Borrowed(CTRL_C_ECHO),
+            ));
+        }
+        if resized {
+            self.add_resize(end_delay, self.header.width, self.header.height);
+        }
+        end_delay
+    }
+
+    /// Adds the events for the lines of a text output at the delay.
+    ///
+    /// Each line is an event, unless there is a chunk size, then the lines
+    /// are packed into events of at most the chunk size in bytes.
+    fn add_lines(&mut self, lines: Vec<Cow<str>>, delay: f64) {
+        let size = match self.chunk_size {
+            Some(size) => size.get(),
+            None => {
+                for line in lines {
+                    let mut output_data = line.into_owned();
+                    output_data.push_str("\r\n");
+                    self.add(Event(delay, EventKind::Printed, output_data.into()));
+                }
+                return;
+            }
+        };
+        let mut chunk = String::new();
+        for line in &lines {
+            for mut rest in [line.as_ref(), "\r\n"] {
+                while !rest.is_empty() {
+                    let mut end = rest.len().min(size - chunk.len());
+                    while !rest.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    if end == 0 && chunk.is_empty() {
+                        // A character larger than the chunk size.
+                        end = rest.chars().next().map_or(0, char::len_utf8);
+                    }
+                    chunk.push_str(&rest[..end]);
+                    rest = &rest[end..];
+                    if chunk.len() >= size || !rest.is_empty() {
+                        let data = std::mem::take(&mut chunk);
+                        self.add(Event(delay, EventKind::Printed, data.into()));
+                    }
+                }
+            }
+        }
+        if !chunk.is_empty() {
+            self.add(Event(delay, EventKind::Printed, chunk.into()));
+        }
+    }
+
+    /// Adds the events for the frames of an animated output starting at the
+    /// delay, each overwriting the previous frame.
+    fn add_frames(&mut self, frames: Vec<(f64, String)>, timings: &Timings, start_delay: f64) {
+        for (offset, frame) in frames {
+            let mut output_data = String::from("\r");
+            output_data.push_str(&frame);
+            self.add(Event(
+                start_delay + offset.speed(timings.speed),
+                EventKind::Printed,
+                output_data.into(),
+            ));
+        }
+    }
+
+    /// Adds the events for a secret typed at a password prompt starting at
+    /// the delay and returns the time taken in seconds.
+    ///
+    /// Nothing is printed for the typed characters, unless there is a mask,
+    /// and no keypress events are added, except for the Enter that submits
+    /// it, so the secret cannot leak.
+    fn add_secret(&mut self, secret: &Secret, timings: &Timings, start_delay: f64) -> f64 {
+        let mut prompt = String::from("\r\n");
+        prompt.push_str(&secret.prompt);
+        self.add(Event(start_delay, EventKind::Printed, prompt.into()));
+        let offsets = timings.type_offsets(&"*".repeat(secret.length));
+        if let Some(mask) = secret.mask {
+            for offset in &offsets[..secret.length] {
+                let char_delay = start_delay
+                    + ((timings.type_start.as_millis() + offset) as f64)
+                        .speed(timings.speed)
+                        .into_seconds();
+                self.add(Event(
+                    char_delay,
+                    EventKind::Printed,
+                    mask.to_string().into(),
+                ));
             }
         }
-        let outputs_time = ((timings.output_line * command.outputs().len()) as f64)
+        let secret_time = ((timings.type_start.as_millis()
+            + offsets[secret.length]
+            + timings.type_submit.as_millis()) as f64)
             .speed(timings.speed)
             .into_seconds();
-        start_delay + input_time + outputs_time
+        if self.stdin {
+            self.add(Event(
+                start_delay + secret_time,
+                EventKind::Keypress,
+                Cow::Borrowed(ENTER),
+            ));
+        }
+        self.add(Event(
+            start_delay + secret_time,
+            EventKind::Printed,
+            Cow::Borrowed("\r\n"),
+        ));
+        secret_time
+    }
+
+    /// Adds a resize event, i.e. "COLSxROWS", which is part of the version 2
+    /// format and supported by asciinema-player.
+    fn add_resize(&mut self, delay: f64, width: usize, height: usize) {
+        let size = format!("{}x{}", width, height);
+        self.add(Event(delay, EventKind::Resize, size.into()));
+    }
+
+    /// Reads an asciicast file, i.e. a header line followed by one event per
+    /// line.
+    pub fn read_from<R: BufRead>(reader: R) -> Result<Self> {
+        let mut reader = CastReader::new(reader)?;
+        let events = reader.by_ref().collect::<Result<Vec<Event>>>()?;
+        Ok(Self {
+            header: reader.header,
+            events,
+            ..Self::default()
+        })
+    }
+
+    /// Completes the header with the theme and environment variables.
+    ///
+    /// With the --deterministic flag, the header is a copy without the values
+    /// that vary between invocations, so the shell used to execute commands is
+    /// kept.
+    pub fn finalize_header(&mut self) -> Result<Cow<'_, Header>> {
+        if let Some(theme) = self.theme.to_theme()? {
+            self.header.theme = Some(theme);
+        }
+        self.header.env.collect();
+        if self.deterministic {
+            let mut header = self.header.clone();
+            header.timestamp = None;
+            header.env.uninherit();
+            return Ok(Cow::Owned(header));
+        }
+        Ok(Cow::Borrowed(&self.header))
     }
 
     pub fn write_to<W: Write>(&mut self, mut writer: W) -> Result<()> {
-        self.header.write_to(&mut writer)?;
+        if let Some(Sizes::Fit) = self.size {
+            self.fit();
+        }
+        if self.merge_events {
+            self.merge();
+        }
+        self.finalize_header()?.write_to(&mut writer)?;
         for event in self.events.iter_mut() {
             event.write_to(&mut writer)?;
         }
+        info!("Wrote {} events", self.events.len());
         Ok(())
     }
+
+    /// Writes the header, and the events of the sequence as each command is
+    /// added, instead of holding every event until the end.
+    ///
+    /// Only the events of one command are held at a time. The "fit" size
+    /// depends on all of the events, which are needed before the header can
+    /// be written, so the sequence is buffered for it.
+    pub fn stream_to<W: Write>(&mut self, sequence: &Sequence, mut writer: W) -> Result<()> {
+        if let Some(Sizes::Fit) = self.size {
+            return self.append_from(sequence).write_to(writer);
+        }
+        self.finalize_header()?.write_to(&mut writer)?;
+        let sequence = &sequence.framed(&self.ssh_banner);
+        let mut start_delay = self.add_intro(sequence.timings());
+        let mut count = 0;
+        for command in sequence.iter() {
+            start_delay = self.add_command(
+                command,
+                sequence.prompt_of(command),
+                sequence.timings(),
+                start_delay,
+            );
+            count += self.flush_to(&mut writer, false)?;
+        }
+        self.add_end(sequence.timings(), start_delay);
+        count += self.flush_to(&mut writer, true)?;
+        info!("Wrote {} events", count);
+        Ok(())
+    }
+
+    /// Writes and removes the events added so far, and returns the number of
+    /// events written.
+    ///
+    /// When merging events, the last event is held back, unless this is the
+    /// last flush, as the next command may start at the same time.
+    fn flush_to<W: Write>(&mut self, mut writer: W, last: bool) -> Result<usize> {
+        let held = if self.merge_events {
+            self.merge();
+            usize::from(!last)
+        } else {
+            0
+        };
+        let count = self.events.len().saturating_sub(held);
+        for mut event in self.events.drain(..count) {
+            event.write_to(&mut writer)?;
+        }
+        Ok(count)
+    }
+}
+
+/// The events of a sequence, generated lazily one command at a time, so they
+/// can be sent to a player or socket as they are produced.
+///
+/// The "fit" size is ignored, since it depends on all of the events.
+#[derive(Debug)]
+pub struct Events {
+    asciicast: Asciicast,
+    sequence: Sequence,
+    next: usize,
+    start_delay: f64,
+    pending: VecDeque<Event>,
+    ended: bool,
+}
+
+impl Events {
+    /// The events of the sequence with the timings, instead of the timings
+    /// of the sequence, displayed with the settings of the asciicast.
+    pub fn new(sequence: &Sequence, timings: Timings, settings: &Asciicast) -> Self {
+        let mut asciicast = settings.settings();
+        let mut sequence = sequence.framed(&asciicast.ssh_banner);
+        sequence.set_timings(timings);
+        let start_delay = asciicast.add_intro(&timings);
+        Self {
+            asciicast,
+            sequence,
+            next: 0,
+            start_delay,
+            pending: VecDeque::new(),
+            ended: false,
+        }
+    }
+
+    /// The header of the asciicast, which precedes the events.
+    pub fn header(&self) -> &Header {
+        &self.asciicast.header
+    }
+
+    /// Generates the events of the next command, or the end delay after the
+    /// last command.
+    ///
+    /// When merging events, the last event is held back until the next
+    /// command, which may start at the same time.
+    fn generate(&mut self) {
+        match self.sequence.get(self.next) {
+            Some(command) => {
+                self.start_delay = self.asciicast.add_command(
+                    command,
+                    self.sequence.prompt_of(command),
+                    self.sequence.timings(),
+                    self.start_delay,
+                );
+                self.next += 1;
+            }
+            None => {
+                self.asciicast
+                    .add_end(self.sequence.timings(), self.start_delay);
+                self.ended = true;
+            }
+        }
+        let held = if self.asciicast.merge_events {
+            self.asciicast.merge();
+            usize::from(!self.ended)
+        } else {
+            0
+        };
+        let count = self.asciicast.events.len().saturating_sub(held);
+        self.pending.extend(self.asciicast.events.drain(..count));
+    }
+}
+
+impl Iterator for Events {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.ended {
+            self.generate();
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// Reads an asciicast file one line at a time, yielding each event as it is
+/// read, so a large recording does not need to be held in memory.
+///
+/// The header is read, and its version checked, when the reader is created.
+#[derive(Debug)]
+pub struct CastReader<R> {
+    header: Header,
+    lines: std::iter::Enumerate<io::Lines<R>>,
+}
+
+impl<R: BufRead> CastReader<R> {
+    pub fn new(reader: R) -> Result<Self> {
+        let mut lines = reader.lines().enumerate();
+        let header: Header = match lines.next() {
+            Some((_, line)) => json::from_slice_at_line(line?.as_bytes(), 1)?,
+            None => {
+                return Err(Error::Invalid(String::from(
+                    "Missing the asciicast header (line 1)",
+                )))
+            }
+        };
+        if header.version() != VERSION {
+            return Err(Error::UnsupportedVersion {
+                format: "asciicast",
+                found: header.version(),
+                expected: VERSION,
+            });
+        }
+        if let Some(limit) = header.idle_time_limit {
+            check_limit(limit)
+                .map_err(|err| Error::Invalid(format!("idle_time_limit: {}", err)))?;
+        }
+        Ok(Self { header, lines })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+}
+
+impl<R: BufRead> Iterator for CastReader<R> {
+    type Item = Result<Event>;
+
+    /// The next event, skipping blank lines.
+    fn next(&mut self) -> Option<Self::Item> {
+        for (i, line) in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(Error::from(err))),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(json::from_slice_at_line(line.as_bytes(), i + 1));
+        }
+        None
+    }
 }
 
 trait ApplySpeed {
@@ -332,12 +1647,111 @@ impl ApplySpeed for f64 {
     }
 }
 
+/// The time in seconds without any fraction of a millisecond.
+fn truncate_to_milliseconds(seconds: f64) -> f64 {
+    (seconds * MILLISECONDS_IN_A_SECOND).trunc() / MILLISECONDS_IN_A_SECOND
+}
+
+/// Adds the lines, without any trailing empty lines, as the output.
+fn with_output((mut command, lines): (Command, Vec<&str>)) -> Command {
+    let output = lines.join("\n");
+    let output = output.trim_end_matches('\n');
+    if !output.is_empty() {
+        command.add(output);
+    }
+    command
+}
+
+/// The part of a printed line left on the screen after any carriage return
+/// moved the cursor back to the start of the line.
+pub(crate) fn displayed(line: &str) -> &str {
+    let line = line.trim_end_matches('\r');
+    line.rsplit('\r').next().unwrap_or(line)
+}
+
+/// Applies the backspaces of typed text and drops any other control
+/// characters.
+fn erase_backspaces(text: &str) -> String {
+    let mut erased = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\u{8}' | '\u{7f}' => {
+                erased.pop();
+            }
+            c if c.is_control() => {}
+            c => erased.push(c),
+        }
+    }
+    erased
+}
+
+/// The lines of the text, without the middle lines if there are more than
+/// the maximum, replaced with a line for the number of omitted lines.
+fn truncate_lines(text: &str, max_lines: Option<usize>) -> Vec<Cow<'_, str>> {
+    let lines = text.lines().map(Cow::Borrowed).collect::<Vec<Cow<str>>>();
+    match max_lines {
+        Some(max) if lines.len() > max => {
+            let tail = max / 2;
+            let head = max - tail;
+            let omitted = lines.len() - max;
+            let mut truncated = lines[..head].to_vec();
+            truncated.push(Cow::Owned(format!(
+                "… ({} line{} omitted)",
+                omitted,
+                if omitted == 1 { "" } else { "s" }
+            )));
+            truncated.extend_from_slice(&lines[lines.len() - tail..]);
+            truncated
+        }
+        _ => lines,
+    }
+}
+
+/// If the lines of a banner fit within the width of the terminal.
+fn banner_fits(banner: &[String], width: usize) -> bool {
+    banner.iter().all(|line| line.width() <= width)
+}
+
+/// Splits the line into lines of at most the width in columns.
+///
+/// ANSI escape sequences and control characters do not take any columns,
+/// and a wide character that does not fit is moved to the next line.
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut column = 0;
+    let mut graphemes = line.graphemes(true).peekable();
+    while let Some(g) = graphemes.next() {
+        let current = lines.last_mut().expect("At least one line");
+        if g == "\x1b" && graphemes.peek() == Some(&"[") {
+            current.push_str(g);
+            for g in graphemes.by_ref() {
+                current.push_str(g);
+                if g != "[" && g.len() == 1 && ("@"..="~").contains(&g) {
+                    break;
+                }
+            }
+            continue;
+        }
+        let w = if g.chars().all(char::is_control) {
+            0
+        } else {
+            g.width()
+        };
+        if column + w > width && column != 0 {
+            lines.push(String::from(g));
+            column = w;
+        } else {
+            current.push_str(g);
+            column += w;
+        }
+    }
+    lines
+}
+
 trait SecondsConversions {
     type Output;
 
     fn into_seconds(self) -> Self::Output;
-
-    fn into_milliseconds(self) -> Self::Output;
 }
 
 impl SecondsConversions for f64 {
@@ -346,18 +1760,68 @@ impl SecondsConversions for f64 {
     fn into_seconds(self) -> Self::Output {
         self / MILLISECONDS_IN_A_SECOND
     }
+}
 
-    fn into_milliseconds(self) -> Self::Output {
-        self * MILLISECONDS_IN_A_SECOND
+/// The columns and rows of the first terminal found for stdout, stderr, or
+/// stdin, since stdout is often redirected to a file.
+#[cfg(all(feature = "tty", unix))]
+fn detect_terminal_size() -> Option<(usize, usize)> {
+    [1, 2, 0]
+        .iter()
+        .find_map(|fd| terminal_size::terminal_size_using_fd(*fd))
+        .map(|(Width(w), Height(h))| (w as usize, h as usize))
+}
+
+#[cfg(all(feature = "tty", not(unix)))]
+fn detect_terminal_size() -> Option<(usize, usize)> {
+    terminal_size::terminal_size().map(|(Width(w), Height(h))| (w as usize, h as usize))
+}
+
+/// There is no terminal to detect without the "tty" feature, i.e. in a
+/// browser.
+#[cfg(not(feature = "tty"))]
+fn detect_terminal_size() -> Option<(usize, usize)> {
+    None
+}
+
+#[cfg(feature = "cli")]
+fn parse_var(src: &str) -> anyhow::Result<(String, String)> {
+    match src.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => anyhow::bail!("'{}' is not a variable in the KEY=VALUE format", src),
     }
 }
 
-fn parse_timestamp(src: &str) -> Result<u64> {
+/// Checks a time limit is a finite number of seconds greater than zero, so
+/// the clamped timestamps stay in order.
+fn check_limit(secs: f64) -> std::result::Result<f64, String> {
+    if secs.is_finite() && secs > 0.0 {
+        Ok(secs)
+    } else {
+        Err(format!(
+            "The limit must be greater than zero seconds, found '{}'",
+            secs
+        ))
+    }
+}
+
+/// Parses a command line time limit with the same rules as the idle time
+/// limit of an asciicast file.
+#[cfg(feature = "cli")]
+fn parse_limit(value: &str) -> std::result::Result<f64, String> {
+    value
+        .parse()
+        .map_err(|err| format!("{}", err))
+        .and_then(check_limit)
+}
+
+#[cfg(feature = "cli")]
+fn parse_timestamp(src: &str) -> anyhow::Result<u64> {
     match src.to_lowercase().as_ref() {
         "now" => SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_secs())
-            .map_err(Error::from),
-        _ => src.parse().map_err(Error::from),
+            .map_err(anyhow::Error::from),
+        _ => src.parse().map_err(anyhow::Error::from),
     }
 }
@@ -0,0 +1,287 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::Env;
+use anyhow::{bail, Result};
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use structopt::StructOpt;
+use strum::{Display, EnumString, EnumVariantNames, VariantNames};
+
+/// The program that runs containers for the docker backend.
+pub const DOCKER: &str = "docker";
+
+/// The exit code of `docker run` when the container could not be run.
+const DOCKER_ERROR_STATUS: i32 = 125;
+
+/// The program that connects to remote hosts for the ssh backend.
+pub const SSH: &str = "ssh";
+
+/// The exit code of `ssh` when the connection failed.
+const SSH_ERROR_STATUS: i32 = 255;
+
+/// The shell syntax that runs or changes things beyond the programs of an
+/// input, which is forbidden with an allowlist.
+const UNSAFE_SYNTAX: &[&str] = &["$(", "`", "<(", ">(", ">", "<"];
+
+/// The shell that executes the inputs within a container, which is available
+/// in nearly every image unlike the shell of the host.
+pub const CONTAINER_SHELL: &str = "sh";
+
+/// Where the inputs are executed to obtain their outputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum ExecBackends {
+    /// The shell of the host, from the -S,--shell option.
+    #[default]
+    Shell,
+    /// A new container from the --image option for each input.
+    Docker,
+    /// The login shell of the --ssh-host remote host.
+    Ssh,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Backend {
+    /// Where the inputs are executed to obtain their outputs.
+    ///
+    /// With "docker", each input is executed with `sh -c` in a new container
+    /// from the --image, so the outputs are the same regardless of the
+    /// machine recording the demo. Files and directories changed by an input
+    /// are not seen by the next input.
+    ///
+    /// With "ssh", each input is executed on the --ssh-host over SSH, for
+    /// tools that only run on a server. The recording is still generated
+    /// locally.
+    #[structopt(
+        long,
+        possible_values = ExecBackends::VARIANTS,
+        case_insensitive = true,
+        value_name = "backend",
+        env = "GERM_EXEC_BACKEND",
+        default_value = "shell"
+    )]
+    pub exec_backend: ExecBackends,
+
+    /// The container image for the docker backend, i.e. "rust:1.75".
+    #[structopt(
+        long,
+        value_name = "image",
+        env = "GERM_IMAGE",
+        required_if("exec-backend", "docker")
+    )]
+    pub image: Option<String>,
+
+    /// The remote host for the ssh backend.
+    #[structopt(
+        long,
+        value_name = "host",
+        env = "GERM_SSH_HOST",
+        required_if("exec-backend", "ssh")
+    )]
+    pub ssh_host: Option<String>,
+
+    /// The user logged in to the remote host for the ssh backend.
+    ///
+    /// By default, this is the user from the SSH configuration.
+    #[structopt(long, value_name = "user", env = "GERM_SSH_USER")]
+    pub ssh_user: Option<String>,
+
+    /// The private key file for the ssh backend.
+    ///
+    /// By default, the keys from the SSH configuration and agent are used.
+    #[structopt(long, value_name = "file", parse(from_os_str), env = "GERM_SSH_KEY")]
+    pub ssh_key: Option<PathBuf>,
+
+    /// Fails instead of executing an input without outputs.
+    ///
+    /// This is useful for rendering sequence files from others, i.e. in CI,
+    /// without running arbitrary commands.
+    #[structopt(long, conflicts_with = "allow")]
+    pub no_execute: bool,
+
+    /// Only executes inputs where every command is this program, i.e. "ls".
+    ///
+    /// This can be used multiple times to allow more programs. With the shell
+    /// backend, the programs are compared by their paths, so another program
+    /// with the same name, i.e. "./ls", is not allowed. An input with command
+    /// substitution, redirection, or variables set before a program is never
+    /// executed, since it could run or change anything.
+    #[structopt(long, value_name = "program", number_of_values = 1)]
+    pub allow: Vec<String>,
+}
+
+impl Backend {
+    /// Fails if the input must not be executed in the working directory
+    /// because of the --no-execute flag or the --allow programs.
+    pub fn permit(&self, input: &str, cwd: Option<&str>) -> Result<()> {
+        if self.no_execute {
+            bail!(
+                "The input '{}' has no outputs and --no-execute forbids executing it",
+                input
+            );
+        }
+        if self.allow.is_empty() {
+            return Ok(());
+        }
+        if UNSAFE_SYNTAX.iter().any(|s| input.contains(s)) {
+            bail!(
+                "The input '{}' uses command substitution or redirection, which \
+                 --allow forbids",
+                input
+            );
+        }
+        for program in programs(input) {
+            // A variable like PATH or LD_PRELOAD changes which program runs.
+            if program.contains('=') && !program.starts_with('=') {
+                bail!(
+                    "The input '{}' sets '{}' before a program, which --allow forbids",
+                    input,
+                    program
+                );
+            }
+            if !self.is_allowed(program, cwd) {
+                bail!("The program '{}' is not allowed by --allow", program);
+            }
+        }
+        Ok(())
+    }
+
+    /// Indicates the program is one of the --allow programs.
+    fn is_allowed(&self, program: &str, cwd: Option<&str>) -> bool {
+        match self.exec_backend {
+            ExecBackends::Shell => {
+                let resolved = resolve(program, cwd);
+                self.allow
+                    .iter()
+                    .any(|allowed| match (&resolved, resolve(allowed, None)) {
+                        (Some(resolved), Some(allowed)) => *resolved == allowed,
+                        // Builtins of the shell, i.e. "cd", have no path.
+                        (None, None) => program == allowed,
+                        _ => false,
+                    })
+            }
+            // The paths of a container or remote host are unknown here.
+            ExecBackends::Docker | ExecBackends::Ssh => self.allow.iter().any(|a| a == program),
+        }
+    }
+
+    /// The process that executes the input, in the working directory if any.
+    pub fn command(&self, env: &Env, input: &str, cwd: Option<&str>) -> process::Command {
+        match self.exec_backend {
+            ExecBackends::Shell => {
+                let mut command = process::Command::new(&env.shell);
+                if let Some(cwd) = cwd {
+                    command.current_dir(working_dir(cwd));
+                }
+                command.args([&format!("{}", env.execute_string_flag()), input]);
+                command
+            }
+            ExecBackends::Docker => {
+                let mut command = process::Command::new(DOCKER);
+                command.args(["run", "--rm", "--env"]);
+                command.arg(format!("TERM={}", env.term));
+                if let Some(cwd) = cwd {
+                    command.arg("--workdir").arg(cwd);
+                }
+                command
+                    .arg(self.image.as_deref().unwrap_or_default())
+                    .args([CONTAINER_SHELL, "-c", input]);
+                command
+            }
+            ExecBackends::Ssh => {
+                let mut command = process::Command::new(SSH);
+                // Fails instead of asking for a password, which would hang
+                // without a terminal.
+                command.args(["-o", "BatchMode=yes"]);
+                if let Some(key) = &self.ssh_key {
+                    command.arg("-i").arg(key);
+                }
+                if let Some(user) = &self.ssh_user {
+                    command.arg("-l").arg(user);
+                }
+                command
+                    .arg(self.ssh_host.as_deref().unwrap_or_default())
+                    .arg("--");
+                match cwd {
+                    Some(cwd) => command.arg(format!("cd {} && {}", quote_dir(cwd), input)),
+                    None => command.arg(input),
+                };
+                command
+            }
+        }
+    }
+
+    /// Fails if the backend, rather than the input, failed with the exit code.
+    pub fn check(&self, status: Option<i32>) -> Result<()> {
+        match (self.exec_backend, status) {
+            (ExecBackends::Docker, Some(DOCKER_ERROR_STATUS)) => bail!(
+                "Failed to run a container from the '{}' image, is docker running?",
+                self.image.as_deref().unwrap_or_default()
+            ),
+            (ExecBackends::Ssh, Some(SSH_ERROR_STATUS)) => bail!(
+                "Failed to connect to '{}' over SSH",
+                self.ssh_host.as_deref().unwrap_or_default()
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The first words of the commands in an input, which are separated by new
+/// lines, `;`, `&`, or `|`. This is the program, unless variables are set
+/// before it, i.e. "LANG=C".
+fn programs(input: &str) -> Vec<&str> {
+    input
+        .split(['\n', ';', '&', '|'])
+        .filter_map(|part| part.split_whitespace().next())
+        .collect()
+}
+
+/// The canonical path of a program, relative to the working directory if it
+/// has a "/", or from the PATH otherwise, if it exists.
+fn resolve(program: &str, cwd: Option<&str>) -> Option<PathBuf> {
+    let path = if program.contains('/') {
+        cwd.map_or_else(PathBuf::new, working_dir).join(program)
+    } else {
+        env::split_paths(&env::var_os("PATH")?)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())?
+    };
+    path.canonicalize().ok()
+}
+
+/// The path of a working directory on the host, with a leading "~" replaced
+/// by the home directory.
+fn working_dir(cwd: &str) -> PathBuf {
+    match (cwd.strip_prefix('~'), env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(cwd),
+    }
+}
+
+/// Quotes a directory for a remote shell, leaving a leading "~" unquoted so
+/// it is still expanded to the home directory.
+fn quote_dir(cwd: &str) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+    match cwd.strip_prefix('~') {
+        Some("") => String::from("~"),
+        Some(rest) if rest.starts_with('/') => format!("~/{}", quote(&rest[1..])),
+        _ => quote(cwd),
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::Asciicast;
+use crate::sequence::{Command, Sequence};
+use anyhow::Result;
+use std::io::Write;
+
+pub const SECONDS_IN_A_MINUTE: u64 = 60;
+pub const SECONDS_IN_AN_HOUR: u64 = 60 * SECONDS_IN_A_MINUTE;
+
+/// A caption displayed from the start to the end, in seconds.
+#[derive(Debug, Clone)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// A command of a sequence with its prompt and the times, in seconds, at
+/// which it starts and ends in the asciicast.
+#[derive(Debug, Clone)]
+pub struct Timed {
+    pub start: f64,
+    pub end: f64,
+    pub prompt: String,
+    pub command: Command,
+}
+
+/// The commands of the sequence, with the commands of the SSH banner, timed
+/// like the events of the asciicast.
+pub fn timed(asciicast: &mut Asciicast, sequence: &Sequence) -> Vec<Timed> {
+    let sequence = sequence.framed(&asciicast.ssh_banner);
+    let mut timed = Vec::new();
+    asciicast.clear();
+    let mut start = asciicast.add_intro(sequence.timings());
+    for command in sequence.iter() {
+        let prompt = sequence.prompt_of(command);
+        let end = asciicast.add_command(command, prompt, sequence.timings(), start);
+        timed.push(Timed {
+            start,
+            end,
+            prompt: prompt.to_owned(),
+            command: command.clone(),
+        });
+        start = end;
+    }
+    asciicast.clear();
+    timed
+}
+
+/// The captions of the commands with a comment, displayed from the comment
+/// to the end of the command, timed like the events of the asciicast.
+///
+/// With inputs, every command has a caption with its input after its
+/// comment, if any.
+pub fn cues(asciicast: &mut Asciicast, sequence: &Sequence, inputs: bool) -> Vec<Cue> {
+    timed(asciicast, sequence)
+        .into_iter()
+        .filter_map(|t| {
+            let mut lines: Vec<String> = t
+                .command
+                .comment()
+                .map(|c| c.lines().map(String::from).collect())
+                .unwrap_or_default();
+            if inputs {
+                let input = t.command.text();
+                lines.extend(input.lines().map(|l| format!("{}{}", t.prompt, l)));
+            }
+            // A blank line ends a cue in both formats.
+            lines.retain(|l| !l.trim().is_empty());
+            if lines.is_empty() || t.end <= t.start {
+                return None;
+            }
+            Some(Cue {
+                start: t.start,
+                end: t.end,
+                text: lines.join("\n"),
+            })
+        })
+        .collect()
+}
+
+/// Writes the cues as a WebVTT file, i.e. for the `<track>` of a video.
+pub fn write_webvtt<W: Write>(cues: &[Cue], mut writer: W) -> Result<()> {
+    writeln!(writer, "WEBVTT")?;
+    for cue in cues {
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "{} --> {}",
+            timestamp(cue.start, '.'),
+            timestamp(cue.end, '.')
+        )?;
+        let text = cue
+            .text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        writeln!(writer, "{}", text)?;
+    }
+    Ok(())
+}
+
+/// Writes the cues as a SubRip (SRT) file.
+pub fn write_srt<W: Write>(cues: &[Cue], mut writer: W) -> Result<()> {
+    for (i, cue) in cues.iter().enumerate() {
+        if i != 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "{}", i + 1)?;
+        writeln!(
+            writer,
+            "{} --> {}",
+            timestamp(cue.start, ','),
+            timestamp(cue.end, ',')
+        )?;
+        writeln!(writer, "{}", cue.text)?;
+    }
+    Ok(())
+}
+
+/// The time as hours, minutes, seconds, and milliseconds, i.e.
+/// "00:01:02.500", with the separator before the milliseconds.
+fn timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    let (secs, millis) = (millis / 1000, millis % 1000);
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        secs / SECONDS_IN_AN_HOUR,
+        secs % SECONDS_IN_AN_HOUR / SECONDS_IN_A_MINUTE,
+        secs % SECONDS_IN_A_MINUTE,
+        separator,
+        millis
+    )
+}
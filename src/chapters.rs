@@ -0,0 +1,90 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::Asciicast;
+use crate::captions;
+use crate::sequence::Sequence;
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+
+/// A chapter of a video rendered from the asciicast, with times in seconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+/// A chapter for each command, titled with the first line of its comment,
+/// or its input without a comment.
+pub fn chapters(asciicast: &mut Asciicast, sequence: &Sequence) -> Vec<Chapter> {
+    captions::timed(asciicast, sequence)
+        .into_iter()
+        .map(|t| {
+            let title = t
+                .command
+                .comment()
+                .and_then(|c| c.lines().find(|l| !l.trim().is_empty()))
+                .map(String::from)
+                .unwrap_or_else(|| {
+                    let input = t.command.text();
+                    input.lines().next().unwrap_or_default().to_owned()
+                });
+            Chapter {
+                start: round_to_milliseconds(t.start),
+                end: round_to_milliseconds(t.end),
+                title: title.trim().to_owned(),
+            }
+        })
+        .collect()
+}
+
+fn round_to_milliseconds(seconds: f64) -> f64 {
+    (seconds * 1000.0).round() / 1000.0
+}
+
+/// Writes the chapters as a JSON array.
+pub fn write_json<W: Write>(chapters: &[Chapter], mut writer: W) -> Result<()> {
+    serde_json::to_writer(&mut writer, chapters)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Writes the chapters as an FFmpeg metadata file, which is added to a video
+/// with `ffmpeg -i demo.mp4 -i demo.ffmetadata -map_metadata 1 -codec copy`.
+pub fn write_ffmetadata<W: Write>(chapters: &[Chapter], mut writer: W) -> Result<()> {
+    writeln!(writer, ";FFMETADATA1")?;
+    for chapter in chapters {
+        writeln!(writer, "[CHAPTER]")?;
+        writeln!(writer, "TIMEBASE=1/1000")?;
+        writeln!(writer, "START={}", (chapter.start * 1000.0).round() as u64)?;
+        writeln!(writer, "END={}", (chapter.end * 1000.0).round() as u64)?;
+        writeln!(writer, "title={}", escape(&chapter.title))?;
+    }
+    Ok(())
+}
+
+/// Escapes the special characters of a metadata value with a backslash.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
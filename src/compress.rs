@@ -0,0 +1,126 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use strum::{Display, EnumString, EnumVariantNames};
+
+/// The first bytes of gzip compressed content.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// The first bytes of zstd compressed content.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// The compression formats for input and output files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum Compressions {
+    Gzip,
+    Zstd,
+}
+
+impl Compressions {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+
+    /// The compression format for the extension of the path, if any.
+    pub fn detect(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Some(Self::Gzip),
+            Some("zst") => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// The compression format of the content, from its first bytes, if any.
+    pub fn sniff(buffer: &[u8]) -> Option<Self> {
+        if buffer.starts_with(GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else if buffer.starts_with(ZSTD_MAGIC) {
+            Some(Self::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+/// Decompresses the content, if it is compressed, or returns it unchanged.
+pub fn decode(buffer: Vec<u8>) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    match Compressions::sniff(&buffer) {
+        Some(Compressions::Gzip) => {
+            MultiGzDecoder::new(&buffer[..]).read_to_end(&mut decoded)?;
+        }
+        Some(Compressions::Zstd) => zstd::stream::copy_decode(&buffer[..], &mut decoded)?,
+        None => return Ok(buffer),
+    }
+    Ok(decoded)
+}
+
+/// A writer compressing everything written to it, if a compression format
+/// is used.
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W, compression: Option<Compressions>) -> Result<Self> {
+        Ok(match compression {
+            Some(Compressions::Gzip) => Self::Gzip(GzEncoder::new(writer, Compression::default())),
+            Some(Compressions::Zstd) => Self::Zstd(zstd::Encoder::new(writer, 0)?),
+            None => Self::Plain(writer),
+        })
+    }
+
+    /// Writes the end of the compressed stream, which is needed for the
+    /// output to be read, and returns the inner writer.
+    pub fn finish(self) -> Result<W> {
+        let mut writer = match self {
+            Self::Plain(writer) => writer,
+            Self::Gzip(encoder) => encoder.finish()?,
+            Self::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::de::{self, Deserializer, Unexpected, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::Duration;
+
+const MILLISECONDS_IN_A_SECOND: f64 = 1000.0;
+
+/// A delay of the timings, which is written to a sequence file in
+/// milliseconds.
+///
+/// The begin and end delays, and the durations of progress bars and
+/// spinners, of version 1 sequence files are in seconds, i.e. `"end": 1.0`,
+/// and they are converted when the sequence is read.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Delay(Duration);
+
+impl Delay {
+    pub const ZERO: Self = Self(Duration::ZERO);
+
+    pub fn from_millis(ms: u64) -> Self {
+        Self(Duration::from_millis(ms))
+    }
+
+    /// The delay, unless the seconds are negative or not finite.
+    pub fn try_from_secs_f64(secs: f64) -> Option<Self> {
+        Duration::try_from_secs_f64(secs).ok().map(Self)
+    }
+
+    /// The delay in whole milliseconds, rounded to the nearest one.
+    pub fn as_millis(self) -> usize {
+        (self.as_secs_f64() * MILLISECONDS_IN_A_SECOND).round() as usize
+    }
+
+    pub fn as_secs_f64(self) -> f64 {
+        self.0.as_secs_f64()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl From<Duration> for Delay {
+    fn from(d: Duration) -> Self {
+        Self(d)
+    }
+}
+
+impl From<Delay> for Duration {
+    fn from(d: Delay) -> Self {
+        d.0
+    }
+}
+
+impl Serialize for Delay {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.as_millis() as u64)
+    }
+}
+
+impl<'de> Deserialize<'de> for Delay {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DelayVisitor)
+    }
+}
+
+struct DelayVisitor;
+
+impl<'de> Visitor<'de> for DelayVisitor {
+    type Value = Delay;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a delay of zero or more milliseconds")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Delay::from_millis(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        u64::try_from(v)
+            .map(Delay::from_millis)
+            .map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Delay::try_from_secs_f64(v / MILLISECONDS_IN_A_SECOND)
+            .ok_or_else(|| E::invalid_value(Unexpected::Float(v), &self))
+    }
+}
+
+/// Parses a delay in seconds, i.e. "1.5", for the command line.
+pub(crate) fn parse_seconds(value: &str) -> Result<Delay, String> {
+    let secs = value.parse::<f64>().map_err(|err| format!("{}", err))?;
+    Delay::try_from_secs_f64(secs)
+        .ok_or_else(|| format!("The delay must be zero or more seconds, found '{}'", secs))
+}
+
+/// Parses a delay in milliseconds, i.e. "750", for the command line.
+pub(crate) fn parse_milliseconds(value: &str) -> Result<Delay, String> {
+    let ms = value.parse::<f64>().map_err(|err| format!("{}", err))?;
+    Delay::try_from_secs_f64(ms / MILLISECONDS_IN_A_SECOND).ok_or_else(|| {
+        format!(
+            "The delay must be zero or more milliseconds, found '{}'",
+            ms
+        )
+    })
+}
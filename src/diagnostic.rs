@@ -0,0 +1,100 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::Error;
+use atty::Stream;
+use std::env;
+use std::fmt::Write;
+use std::path::Path;
+
+/// The name of a file read from stdin in a report.
+const STDIN_NAME: &str = "<stdin>";
+
+/// The environment variable that disables colors, see https://no-color.org.
+const NO_COLOR_VAR_NAME: &str = "NO_COLOR";
+
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+/// A report of a JSON error that points at the line and column with the
+/// error, since the files are often edited by hand, i.e.
+///
+/// ```text
+/// commands[0].input: invalid type: integer `1`, expected a string
+///   --> demo.germ:3:14
+///    |
+///  3 |     "input": 1,
+///    |              ^ invalid type: integer `1`, expected a string
+/// ```
+///
+/// The report is colored if stderr is a terminal, unless NO_COLOR is set.
+/// Errors without a location are not reported.
+pub fn report(err: &Error, buffer: &[u8], path: Option<&Path>) -> Option<String> {
+    let (field, start, source) = match err {
+        Error::Serde { path, line, source } if source.line() != 0 => (path, *line, source),
+        _ => return None,
+    };
+    let content = String::from_utf8_lossy(buffer);
+    let number = start + source.line() - 1;
+    let text = content.lines().nth(source.line() - 1).unwrap_or_default();
+    let column = source.column().max(1);
+    let message = source.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map(|(m, _)| m)
+        .unwrap_or(&message);
+    let (red, blue, reset) = if colored() {
+        (BOLD_RED, BOLD_BLUE, RESET)
+    } else {
+        ("", "", "")
+    };
+    let gutter = " ".repeat(number.to_string().len());
+    // Tabs are kept so the caret lines up with the text.
+    let indent: String = text
+        .chars()
+        .take(column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let mut report = String::new();
+    if !field.is_empty() && field != "." {
+        write!(report, "{}: ", field).ok();
+    }
+    writeln!(report, "{}", message).ok();
+    writeln!(
+        report,
+        "{}{}-->{} {}:{}:{}",
+        gutter,
+        blue,
+        reset,
+        path.map_or_else(|| String::from(STDIN_NAME), |p| p.display().to_string()),
+        number,
+        column
+    )
+    .ok();
+    writeln!(report, "{} {}|{}", gutter, blue, reset).ok();
+    writeln!(report, "{}{} |{} {}", blue, number, reset, text).ok();
+    write!(
+        report,
+        "{} {}|{} {}{}^ {}{}",
+        gutter, blue, reset, indent, red, message, reset
+    )
+    .ok();
+    Some(report)
+}
+
+fn colored() -> bool {
+    atty::is(Stream::Stderr) && env::var_os(NO_COLOR_VAR_NAME).is_none()
+}
@@ -0,0 +1,149 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+use structopt::StructOpt;
+use strum::{Display, EnumString, EnumVariantNames, VariantNames};
+
+/// The release of asciinema-player loaded by the snippet.
+const PLAYER_URL: &str = "https://cdn.jsdelivr.net/npm/asciinema-player@3/dist/bundle";
+
+/// The built-in color themes of asciinema-player.
+#[derive(Debug, Clone, Copy, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "kebab-case")]
+pub enum PlayerThemes {
+    Asciinema,
+    Dracula,
+    Monokai,
+    Nord,
+    SolarizedDark,
+    SolarizedLight,
+    Tango,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct Embed {
+    /// The URL of the cast file played by the embed output format.
+    ///
+    /// If not present, then the cast file next to the -o,--output file is
+    /// played, i.e. "demo.cast" for "demo.html", which is written with
+    /// `-O asciicast` or the "asciicast" render format.
+    #[structopt(long, value_name = "url")]
+    pub embed_src: Option<String>,
+
+    /// The playback speed of the embedded player.
+    ///
+    /// This is applied on top of the -s,--speed of the events.
+    #[structopt(long, value_name = "float")]
+    pub embed_speed: Option<f64>,
+
+    /// The color theme of the embedded player.
+    ///
+    /// If not present, then the theme of the cast file is used, if any.
+    #[structopt(
+        long,
+        possible_values = PlayerThemes::VARIANTS,
+        case_insensitive = true,
+        value_name = "theme"
+    )]
+    pub embed_theme: Option<PlayerThemes>,
+
+    /// The frame displayed by the embedded player before playback.
+    ///
+    /// For example, "npt:0:3" displays the frame at 3 seconds.
+    #[structopt(long, value_name = "poster")]
+    pub embed_poster: Option<String>,
+
+    /// Starts playback as soon as the embedded player is loaded.
+    #[structopt(long)]
+    pub embed_autoplay: bool,
+
+    /// Restarts playback of the embedded player when it ends.
+    #[structopt(long)]
+    pub embed_loop: bool,
+}
+
+impl Embed {
+    /// Writes the HTML snippet that plays the cast file at the URL with
+    /// asciinema-player, ready to paste into a page.
+    ///
+    /// The player is created in an element with an id from the name of the
+    /// cast file, i.e. "germ-demo" for "demo.cast", so several snippets can
+    /// be pasted into the same page.
+    pub fn write_to<W: Write>(&self, mut writer: W, src: &str) -> Result<()> {
+        let mut options = Map::new();
+        if let Some(speed) = self.embed_speed {
+            options.insert("speed".into(), speed.into());
+        }
+        if let Some(theme) = self.embed_theme {
+            options.insert("theme".into(), theme.to_string().into());
+        }
+        if let Some(poster) = &self.embed_poster {
+            options.insert("poster".into(), poster.as_str().into());
+        }
+        if self.embed_autoplay {
+            options.insert("autoPlay".into(), true.into());
+        }
+        if self.embed_loop {
+            options.insert("loop".into(), true.into());
+        }
+        let id = element_id(src);
+        writeln!(writer, r#"<div id="{}"></div>"#, id)?;
+        writeln!(
+            writer,
+            r#"<link rel="stylesheet" type="text/css" href="{}/asciinema-player.css" />"#,
+            PLAYER_URL
+        )?;
+        writeln!(
+            writer,
+            r#"<script src="{}/asciinema-player.min.js"></script>"#,
+            PLAYER_URL
+        )?;
+        writeln!(writer, "<script>")?;
+        writeln!(
+            writer,
+            "  AsciinemaPlayer.create({}, document.getElementById({}), {});",
+            script(&src)?,
+            script(&id)?,
+            script(&Value::Object(options))?
+        )?;
+        writeln!(writer, "</script>")?;
+        Ok(())
+    }
+}
+
+/// The id of the player element from the name of the cast file, without
+/// any characters that need escaping in HTML.
+fn element_id(src: &str) -> String {
+    let name = src.rsplit('/').next().unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    let stem: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    if stem.is_empty() {
+        String::from("germ")
+    } else {
+        format!("germ-{}", stem)
+    }
+}
+
+/// The value as a JavaScript literal, which cannot end the script element.
+fn script<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(value)?.replace("</", r"<\/"))
+}
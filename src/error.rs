@@ -0,0 +1,121 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The errors of the library, which can be matched on by other crates.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing failed.
+    Io(io::Error),
+
+    /// Reading or writing JSON failed.
+    ///
+    /// The path is the field with the error, i.e. "commands[0].input", and
+    /// the line is where the document starts within a larger file, such as an
+    /// event of an asciicast file.
+    Serde {
+        path: String,
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    /// A delay or speed that cannot be used to generate events.
+    InvalidTiming(String),
+
+    /// The version of a germ or asciicast file is not supported.
+    UnsupportedVersion {
+        format: &'static str,
+        found: usize,
+        expected: usize,
+    },
+
+    /// Executing an input failed, or its outputs are not as expected.
+    Execution(String),
+
+    /// A file is valid JSON but not valid for germ, i.e. a command is
+    /// assigned to an unknown persona.
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Serde { path, line, source } => {
+                let message = source.to_string();
+                let message = message
+                    .rsplit_once(" at line ")
+                    .map(|(m, _)| m)
+                    .unwrap_or(&message);
+                if !path.is_empty() && path != "." {
+                    write!(f, "{}: ", path)?;
+                }
+                write!(f, "{}", message)?;
+                if source.line() != 0 {
+                    write!(
+                        f,
+                        " (line {}, column {})",
+                        line + source.line().saturating_sub(1),
+                        source.column()
+                    )?;
+                }
+                Ok(())
+            }
+            Self::InvalidTiming(message) | Self::Execution(message) | Self::Invalid(message) => {
+                write!(f, "{}", message)
+            }
+            Self::UnsupportedVersion {
+                format,
+                found,
+                expected,
+            } => write!(
+                f,
+                "Unsupported {} version {}, expected {}",
+                format, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Serde { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(source: serde_json::Error) -> Self {
+        Self::Serde {
+            path: String::new(),
+            line: 1,
+            source,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::CommentStyles;
+use crate::sequence::ansi;
+#[cfg(feature = "cli")]
+use anyhow::{anyhow, Result};
+use regex::Regex;
+#[cfg(feature = "exec")]
+use std::fs;
+#[cfg(feature = "exec")]
+use std::path::Path;
+#[cfg(feature = "cli")]
+use strum::VariantNames;
+
+/// Paints the matches of a regular expression in the outputs with a style,
+/// i.e. "error:.*=red".
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    pattern: Regex,
+    style: CommentStyles,
+}
+
+impl Highlight {
+    pub fn new(pattern: Regex, style: CommentStyles) -> Self {
+        Self { pattern, style }
+    }
+}
+
+/// Parses a rule as the regular expression and the style separated by the
+/// last "=", so the expression can contain "=".
+#[cfg(feature = "cli")]
+pub fn parse_rule(src: &str) -> Result<Highlight> {
+    let (pattern, style) = src
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow!("'{}' is not a highlight in the regex=style format", src))?;
+    let style = style.trim().to_lowercase().parse().map_err(|_| {
+        anyhow!(
+            "'{}' is not a highlight style, expected one of: {}",
+            style,
+            CommentStyles::VARIANTS.join(", ")
+        )
+    })?;
+    Ok(Highlight::new(Regex::new(pattern)?, style))
+}
+
+/// Reads the rules of a file, one per line. Empty lines and lines starting
+/// with "#" are skipped.
+#[cfg(feature = "exec")]
+pub fn read_rules(path: &Path) -> Result<Vec<Highlight>> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| parse_rule(line).map_err(|err| anyhow!("line {}: {}", i + 1, err)))
+        .collect()
+}
+
+/// Paints each match of the rules in the text.
+///
+/// A match that overlaps the match of an earlier rule, or an escape sequence
+/// already in the text, is left as is, so the colors of the outputs are
+/// never broken.
+pub fn highlight(text: &str, rules: &[Highlight]) -> String {
+    let mut matches: Vec<(usize, usize, CommentStyles)> = ansi()
+        .find_iter(text)
+        .map(|m| (m.start(), m.end(), CommentStyles::None))
+        .collect();
+    for rule in rules {
+        for m in rule.pattern.find_iter(text).filter(|m| !m.is_empty()) {
+            if matches
+                .iter()
+                .all(|(start, end, _)| m.end() <= *start || m.start() >= *end)
+            {
+                matches.push((m.start(), m.end(), rule.style));
+            }
+        }
+    }
+    matches.sort_by_key(|(start, ..)| *start);
+    let mut highlighted = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, style) in matches {
+        highlighted.push_str(&text[last..start]);
+        highlighted.push_str(&style.paint(&text[start..end]));
+        last = end;
+    }
+    highlighted.push_str(&text[last..]);
+    highlighted
+}
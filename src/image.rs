@@ -0,0 +1,255 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::Theme;
+use crate::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+/// The foreground color of the asciinema player without a theme.
+const DEFAULT_FOREGROUND: Rgb = [0xcc, 0xcc, 0xcc];
+
+/// The background color of the asciinema player without a theme.
+const DEFAULT_BACKGROUND: Rgb = [0x12, 0x13, 0x14];
+
+/// The 16 colors of the asciinema player without a theme.
+const DEFAULT_COLORS: [Rgb; 16] = [
+    [0x00, 0x00, 0x00],
+    [0xdd, 0x3c, 0x69],
+    [0x4e, 0xbf, 0x22],
+    [0xdd, 0xaf, 0x3c],
+    [0x26, 0xb0, 0xd7],
+    [0xb9, 0x54, 0xe1],
+    [0x54, 0xe1, 0xb9],
+    [0xd9, 0xd9, 0xd9],
+    [0x4d, 0x4d, 0x4d],
+    [0xdd, 0x3c, 0x69],
+    [0x4e, 0xbf, 0x22],
+    [0xdd, 0xaf, 0x3c],
+    [0x26, 0xb0, 0xd7],
+    [0xb9, 0x54, 0xe1],
+    [0x54, 0xe1, 0xb9],
+    [0xff, 0xff, 0xff],
+];
+
+/// The levels of the red, green, and blue of the 6x6x6 color cube of the 256
+/// colors.
+const CUBE_LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// The color type of an image with a red, green, and blue byte per pixel.
+const PNG_TRUECOLOR: u8 = 2;
+
+/// The filter type of a row of pixels that is stored as is.
+const PNG_NO_FILTER: u8 = 0;
+
+pub type Rgb = [u8; 3];
+
+/// The colors used to render a screen, from the theme of an asciicast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    foreground: Rgb,
+    background: Rgb,
+    colors: [Rgb; 16],
+}
+
+impl Palette {
+    /// The palette of the theme, or of the asciinema player without a theme.
+    ///
+    /// A theme with 8 colors uses them for the bright colors, too. Colors
+    /// that are not in the #rrggbb format are replaced by the default.
+    pub fn new(theme: Option<&Theme>) -> Self {
+        let mut palette = Self::default();
+        if let Some(theme) = theme {
+            palette.foreground = parse_rgb(&theme.foreground).unwrap_or(DEFAULT_FOREGROUND);
+            palette.background = parse_rgb(&theme.background).unwrap_or(DEFAULT_BACKGROUND);
+            let colors: Vec<&str> = theme.palette.split(':').collect();
+            for (i, color) in palette.colors.iter_mut().enumerate() {
+                if let Some(rgb) = colors
+                    .get(i)
+                    .or_else(|| colors.get(i % 8).filter(|_| colors.len() == 8))
+                    .and_then(|c| parse_rgb(c))
+                {
+                    *color = rgb;
+                }
+            }
+        }
+        palette
+    }
+
+    fn rgb(&self, color: vt100::Color, default: Rgb, bold: bool) -> Rgb {
+        match color {
+            vt100::Color::Default => default,
+            // Bold text is drawn in the bright colors, like most terminals.
+            vt100::Color::Idx(i) if i < 8 && bold => self.colors[usize::from(i) + 8],
+            vt100::Color::Idx(i) if i < 16 => self.colors[usize::from(i)],
+            vt100::Color::Idx(i) if i < 232 => {
+                let i = usize::from(i - 16);
+                [
+                    CUBE_LEVELS[i / 36],
+                    CUBE_LEVELS[i / 6 % 6],
+                    CUBE_LEVELS[i % 6],
+                ]
+            }
+            vt100::Color::Idx(i) => {
+                let level = 8 + (i - 232) * 10;
+                [level, level, level]
+            }
+            vt100::Color::Rgb(r, g, b) => [r, g, b],
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            foreground: DEFAULT_FOREGROUND,
+            background: DEFAULT_BACKGROUND,
+            colors: DEFAULT_COLORS,
+        }
+    }
+}
+
+/// An image of a screen with three bytes, the red, green, and blue, per
+/// pixel.
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Renders the screen with the bitmap font.
+    ///
+    /// Each pixel of the font is a square of `scale` pixels, and the rows of
+    /// the font are doubled so the cells are twice as tall as they are wide,
+    /// like a terminal. The cursor is drawn as a block unless it is hidden.
+    pub fn render(screen: &vt100::Screen, palette: &Palette, scale: usize) -> Self {
+        let scale = scale.max(1);
+        let cell_width = GLYPH_WIDTH * scale;
+        let cell_height = GLYPH_HEIGHT * 2 * scale;
+        let (rows, cols) = screen.size();
+        let mut image = Self {
+            width: usize::from(cols) * cell_width,
+            height: usize::from(rows) * cell_height,
+            pixels: Vec::new(),
+        };
+        image.pixels = palette
+            .background
+            .iter()
+            .copied()
+            .cycle()
+            .take(image.width * image.height * 3)
+            .collect();
+        let cursor = screen.cursor_position();
+        for row in 0..rows {
+            for col in 0..cols {
+                let cell = match screen.cell(row, col) {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                let mut fg = palette.rgb(cell.fgcolor(), palette.foreground, cell.bold());
+                let mut bg = palette.rgb(cell.bgcolor(), palette.background, false);
+                if cell.inverse() != (!screen.hide_cursor() && cursor == (row, col)) {
+                    std::mem::swap(&mut fg, &mut bg);
+                }
+                let glyph = cell.contents().chars().next().map(font::glyph);
+                let x0 = usize::from(col) * cell_width;
+                let y0 = usize::from(row) * cell_height;
+                for y in 0..cell_height {
+                    let gy = y / (2 * scale);
+                    for x in 0..cell_width {
+                        let gx = x / scale;
+                        let set = glyph.is_some_and(|glyph| {
+                            font::is_set(glyph, gx, gy)
+                                // Bold is drawn by smearing the glyph one pixel
+                                // to the right.
+                                || (cell.bold() && gx > 0 && font::is_set(glyph, gx - 1, gy))
+                        }) || (cell.underline() && gy == GLYPH_HEIGHT - 1);
+                        image.put(x0 + x, y0 + y, if set { fg } else { bg });
+                    }
+                }
+            }
+        }
+        image
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixels from the top left to the bottom right, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Writes the image in the PNG format.
+    pub fn write_png<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&PNG_SIGNATURE)?;
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&png_dimension(self.width)?.to_be_bytes());
+        header.extend_from_slice(&png_dimension(self.height)?.to_be_bytes());
+        // The bit depth, color type, compression, filter, and interlace.
+        header.extend_from_slice(&[8, PNG_TRUECOLOR, 0, 0, 0]);
+        write_chunk(&mut writer, b"IHDR", &header)?;
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        for row in self.pixels.chunks(self.width * 3) {
+            encoder.write_all(&[PNG_NO_FILTER])?;
+            encoder.write_all(row)?;
+        }
+        write_chunk(&mut writer, b"IDAT", &encoder.finish()?)?;
+        write_chunk(&mut writer, b"IEND", &[])?;
+        writer.flush()
+    }
+
+    fn put(&mut self, x: usize, y: usize, rgb: Rgb) {
+        let offset = (y * self.width + x) * 3;
+        self.pixels[offset..offset + 3].copy_from_slice(&rgb);
+    }
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&png_dimension(data.len())?.to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    writer.write_all(&crc.sum().to_be_bytes())
+}
+
+fn png_dimension(value: usize) -> io::Result<u32> {
+    u32::try_from(value)
+        .ok()
+        .filter(|value| *value <= i32::MAX as u32)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The image is too large"))
+}
+
+fn parse_rgb(color: &str) -> Option<Rgb> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
@@ -0,0 +1,90 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::sequence::INTERRUPTED_STATUS;
+use std::io;
+use std::process::{self, Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, Once};
+use std::thread;
+use std::time::Duration;
+
+/// The child executing an input, if any, which is killed by Ctrl+C instead of
+/// germ.
+static RUNNING: Mutex<Option<Child>> = Mutex::new(None);
+
+/// Set when the running child is killed by Ctrl+C.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// How often the watched child is checked for an exit.
+const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+static INSTALL: Once = Once::new();
+
+/// Handles Ctrl+C by killing the child executing an input, so the sequence
+/// being generated is not lost. Without a child, germ exits like it does
+/// without a handler.
+pub fn install() {
+    INSTALL.call_once(|| {
+        // Failing to install the handler leaves the default behavior, which
+        // is only less forgiving.
+        let _ = ctrlc::set_handler(|| match running().as_mut() {
+            Some(child) => {
+                INTERRUPTED.store(true, Ordering::SeqCst);
+                let _ = child.kill();
+            }
+            None => process::exit(INTERRUPTED_STATUS),
+        });
+    });
+}
+
+/// Tracks the child until it is waited on, so Ctrl+C kills it.
+pub fn watch(child: Child) {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    *running() = Some(child);
+}
+
+/// Waits for the watched child to exit and returns its status, and if it was
+/// killed by Ctrl+C.
+///
+/// The child is polled, instead of waited on with the lock held, so Ctrl+C
+/// can still kill it.
+pub fn wait() -> io::Result<(ExitStatus, bool)> {
+    loop {
+        let mut running = running();
+        if let Some(status) = running.as_mut().expect("Watched child").try_wait()? {
+            *running = None;
+            return Ok((status, INTERRUPTED.swap(false, Ordering::SeqCst)));
+        }
+        drop(running);
+        thread::sleep(WAIT_INTERVAL);
+    }
+}
+
+/// Kills the watched child, i.e. when it stops responding, and waits for it.
+pub fn kill() -> io::Result<()> {
+    if let Some(mut child) = running().take() {
+        // The child may have exited already.
+        let _ = child.kill();
+        child.wait()?;
+    }
+    Ok(())
+}
+
+fn running() -> MutexGuard<'static, Option<Child>> {
+    RUNNING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,34 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::{Error, Result};
+use serde::de::DeserializeOwned;
+
+/// Deserializes a JSON document, reporting the line, column, and field of any
+/// error.
+pub fn from_slice<T: DeserializeOwned>(buffer: &[u8]) -> Result<T> {
+    from_slice_at_line(buffer, 1)
+}
+
+/// Deserializes a JSON document that starts at the given line of a larger
+/// file, such as a single line of an asciicast file.
+pub fn from_slice_at_line<T: DeserializeOwned>(buffer: &[u8], line: usize) -> Result<T> {
+    let de = &mut serde_json::Deserializer::from_slice(buffer);
+    serde_path_to_error::deserialize(de).map_err(|err| Error::Serde {
+        path: err.path().to_string(),
+        line,
+        source: err.into_inner(),
+    })
+}
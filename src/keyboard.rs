@@ -0,0 +1,106 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString, EnumVariantNames};
+
+/// The fraction of the type char delay between presses of the same key.
+const SAME_KEY_FACTOR: f64 = 0.6;
+
+/// The added fraction of the type char delay for each key of distance.
+const PER_KEY_FACTOR: f64 = 0.15;
+
+/// The most the type char delay is multiplied by a long reach.
+const MAX_FACTOR: f64 = 2.0;
+
+/// The horizontal offset of each row of keys, in keys, for the stagger of a
+/// standard keyboard.
+const ROW_OFFSETS: [f64; 4] = [0.0, 1.5, 1.75, 2.25];
+
+/// The position of the space bar, in keys.
+const SPACE_POSITION: (f64, f64) = (6.0, 4.0);
+
+/// The physical arrangement of the keys used to type characters.
+pub trait Layout {
+    /// The column and row of the key for the character, in keys.
+    fn position(&self, c: char) -> Option<(f64, f64)>;
+
+    /// The multiplier of the type char delay for moving from one key to the
+    /// next, which grows with the distance between the keys.
+    fn factor(&self, from: char, to: char) -> f64 {
+        match (self.position(from), self.position(to)) {
+            (Some((x1, y1)), Some((x2, y2))) => {
+                let distance = (x2 - x1).hypot(y2 - y1);
+                (SAME_KEY_FACTOR + PER_KEY_FACTOR * distance).min(MAX_FACTOR)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Display, EnumString, EnumVariantNames, Deserialize, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardLayouts {
+    Qwerty,
+    Dvorak,
+    Colemak,
+}
+
+impl KeyboardLayouts {
+    /// The unshifted and shifted characters of each row, from the number row
+    /// to the bottom row.
+    fn rows(&self) -> [(&'static str, &'static str); 4] {
+        match self {
+            Self::Qwerty => [
+                ("`1234567890-=", "~!@#$%^&*()_+"),
+                ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+                ("asdfghjkl;'", "ASDFGHJKL:\""),
+                ("zxcvbnm,./", "ZXCVBNM<>?"),
+            ],
+            Self::Dvorak => [
+                ("`1234567890[]", "~!@#$%^&*(){}"),
+                ("',.pyfgcrl/=\\", "\"<>PYFGCRL?+|"),
+                ("aoeuidhtns-", "AOEUIDHTNS_"),
+                (";qjkxbmwvz", ":QJKXBMWVZ"),
+            ],
+            Self::Colemak => [
+                ("`1234567890-=", "~!@#$%^&*()_+"),
+                ("qwfpgjluy;[]\\", "QWFPGJLUY:{}|"),
+                ("arstdhneio'", "ARSTDHNEIO\""),
+                ("zxcvbkm,./", "ZXCVBKM<>?"),
+            ],
+        }
+    }
+}
+
+impl Layout for KeyboardLayouts {
+    fn position(&self, c: char) -> Option<(f64, f64)> {
+        if c == ' ' {
+            return Some(SPACE_POSITION);
+        }
+        self.rows()
+            .iter()
+            .zip(ROW_OFFSETS.iter())
+            .enumerate()
+            .find_map(|(row, ((unshifted, shifted), offset))| {
+                unshifted
+                    .chars()
+                    .position(|k| k == c)
+                    .or_else(|| shifted.chars().position(|k| k == c))
+                    .map(|column| (offset + column as f64, row as f64))
+            })
+    }
+}
@@ -13,9 +13,58 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+#[cfg(feature = "exec")]
 pub use crate::app::Cli;
+pub use crate::asciicast::{
+    Asciicast, CastReader, CommentStyles, Event, EventKind, Events, Header, Sizes, TypingUnits,
+};
+pub use crate::delay::Delay;
+pub use crate::error::{Error, Result};
+pub use crate::keyboard::{KeyboardLayouts, Layout};
+pub use crate::recorder::{Recorder, Sink, WriteSink};
+pub use crate::sequence::{
+    Command, CommandBuilder, Output, Progress, Repl, Response, Secret, Sequence, Spinner, Timings,
+};
+#[cfg(feature = "exec")]
+pub use crate::websocket::WebSocket;
 
+#[cfg(feature = "exec")]
 mod app;
 mod asciicast;
+#[cfg(feature = "exec")]
+mod backend;
+#[cfg(feature = "exec")]
+mod captions;
+#[cfg(feature = "exec")]
+mod chapters;
+#[cfg(feature = "exec")]
+mod compress;
+mod delay;
+#[cfg(feature = "exec")]
+mod diagnostic;
+#[cfg(feature = "exec")]
+mod embed;
+mod error;
+mod font;
+mod highlight;
+#[cfg(feature = "exec")]
+mod image;
+#[cfg(feature = "exec")]
+mod interrupt;
+mod json;
+mod keyboard;
+#[cfg(feature = "exec")]
+mod manifest;
+#[cfg(feature = "exec")]
+mod prompt;
+mod recorder;
+mod redact;
+#[cfg(feature = "exec")]
+mod screen;
 mod sequence;
 mod termsheets;
+mod theme;
+#[cfg(feature = "exec")]
+mod transcript;
+#[cfg(feature = "exec")]
+mod websocket;
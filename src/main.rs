@@ -62,7 +62,7 @@
 //!
 //! ```sh
 //! ~$ germ "echo 'Hello, World!'"
-//! {"version":2,"width":80,"height":24,"env":{"SHELL":"/bin/sh","TERM":"xterm-256color"}}
+//! {"version":2,"width":80,"height":24,"command":"echo 'Hello, World!'","env":{"SHELL":"/bin/sh","TERM":"xterm-256color"}}
 //! [0.0,"o","$ "]
 //! [0.75,"o","e"]
 //! [0.785,"o","c"]
@@ -181,7 +181,7 @@
 //!
 //! ```sh
 //! ~$ germ --output-format germ "echo 'Hello, World!'"
-//! {"version":1,"timings":{"begin":0.0,"end":1.0,"type_start":750,"type_char":35,"type_submit":350,"output_line":500,"speed":1.0},"commands":[{"prompt":"$ ","input":"echo 'Hello, World!'","outputs":["Hello, World!\n"]}]}
+//! {"version":2,"timings":{"begin":0,"end":1000,"type_start":750,"type_char":35,"type_submit":350,"output_line":500,"speed":1.0},"commands":[{"prompt":"$ ","input":"echo 'Hello, World!'","outputs":["Hello, World!\n"]}]}
 //! ```
 //!
 //! which is not very readable by a human, so let's pipe stdout from the germ
@@ -191,10 +191,10 @@
 //! ```sh
 //! ~$ germ --output-format germ "echo 'Hello, World!'" | jq
 //! {
-//!   "version": 1,
+//!   "version": 2,
 //!   "timings": {
 //!     "begin": 0,
-//!     "end": 1,
+//!     "end": 1000,
 //!     "type_start": 750,
 //!     "type_char": 35,
 //!     "type_submit": 350,
@@ -215,11 +215,12 @@
 //!
 //! The JSON-based germ sequence format contains timing information for
 //! generating a cast file as well as a list or array of all inputs and their
-//! respective outputs. Germ is capable of reading, modifying, and writing
-//! JSON-based sequence format. Furthermore, while asciinema does not read from
-//! stdin, the germ application is capable of reading from stdin. The
-//! combination of all of these features can be used to create multi-input cast
-//! files.
+//! respective outputs. All of the delays are in milliseconds, even the begin
+//! and end delays, which are in seconds on the command line and in version 1
+//! files. Germ is capable of reading, modifying, and writing JSON-based
+//! sequence format. Furthermore, while asciinema does not read from stdin, the
+//! germ application is capable of reading from stdin. The combination of all of
+//! these features can be used to create multi-input cast files.
 //!
 //! [asciinema]: https://asciinema.org
 //! [TermSheets]: https://neatsoftware.github.io/term-sheets/
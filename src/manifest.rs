@@ -0,0 +1,61 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A record of every file written during an invocation.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Manifest {
+    artifacts: Vec<Artifact>,
+}
+
+impl Manifest {
+    pub fn add(&mut self, artifact: Artifact) -> &mut Self {
+        self.artifacts.push(artifact);
+        self
+    }
+
+    /// Adds the artifacts of the other manifest after those of this one.
+    pub fn append(&mut self, mut other: Manifest) -> &mut Self {
+        self.artifacts.append(&mut other.artifacts);
+        self
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer(&mut writer, self)?;
+        writeln!(&mut writer)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub format: String,
+
+    /// The playback duration in seconds.
+    pub duration: f64,
+
+    /// The SHA-256 hash of the source, as a lowercase hex string.
+    pub source_hash: String,
+}
+
+pub fn hash(source: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(source))
+}
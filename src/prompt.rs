@@ -0,0 +1,105 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use strum::{Display, EnumString, EnumVariantNames};
+
+/// The ways to write the escape character in a prompt on the command line,
+/// where it cannot be typed directly.
+pub const ESCAPES: [&str; 5] = ["\\e", "\\E", "\\033", "\\x1b", "\\x1B"];
+
+#[derive(Debug, Clone, Copy, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum PromptPresets {
+    /// A green "user@host" and a blue working directory.
+    Color,
+    /// A green arrow and a cyan directory.
+    Arrow,
+    /// A magenta chevron.
+    Minimal,
+    /// A blue working directory and a yellow git branch.
+    Git,
+    /// The default prompt of bash, "user@host:~$ ".
+    Bash,
+    /// The default prompt of zsh, "user@host ~ % ".
+    Zsh,
+    /// The default prompt of fish, with a green user and working directory.
+    Fish,
+    /// The default prompt of PowerShell on Windows.
+    PowerShell,
+    /// The prompt of bash for the root user, which ends with "#".
+    Root,
+}
+
+impl PromptPresets {
+    /// The prompt template, with ANSI escape sequences for the colors.
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            Self::Color => "\x1b[1;32muser@host\x1b[0m:\x1b[1;34m{cwd}\x1b[0m$ ",
+            Self::Arrow => "\x1b[1;32m➜\x1b[0m  \x1b[1;36m{dir}\x1b[0m ",
+            Self::Minimal => "\x1b[1;35m❯\x1b[0m ",
+            Self::Git => "\x1b[1;34m{cwd}\x1b[0m \x1b[33m({git_branch})\x1b[0m $ ",
+            Self::Bash => "user@host:{cwd}$ ",
+            Self::Zsh => "user@host {dir} % ",
+            Self::Fish => "\x1b[32muser\x1b[0m@host \x1b[32m{cwd}\x1b[0m> ",
+            Self::PowerShell => "PS C:\\Users\\user> ",
+            Self::Root => "root@host:{cwd}# ",
+        }
+    }
+}
+
+/// The conventions of the shell displayed in a recording.
+#[derive(Debug, Clone, Copy, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum Flavors {
+    /// A PowerShell session in Windows Terminal.
+    PowerShell,
+}
+
+impl Flavors {
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            Self::PowerShell => "PS C:\\> ",
+        }
+    }
+
+    /// The prompt for each line after the first of a multi-line input.
+    pub fn continuation_prompt(&self) -> &'static str {
+        match self {
+            Self::PowerShell => ">> ",
+        }
+    }
+
+    /// The TERM of the terminal running the shell.
+    pub fn term(&self) -> &'static str {
+        match self {
+            Self::PowerShell => "xterm-256color",
+        }
+    }
+
+    /// Whether the lines of a pasted input end with CRLF.
+    pub fn crlf(&self) -> bool {
+        match self {
+            Self::PowerShell => true,
+        }
+    }
+}
+
+/// Replaces the escape characters written as text, i.e. "\e[32m", with the
+/// escape character, so ANSI colors can be used in prompts.
+pub fn unescape(prompt: &str) -> String {
+    ESCAPES.iter().fold(prompt.to_owned(), |prompt, escape| {
+        prompt.replace(escape, "\x1b")
+    })
+}
@@ -0,0 +1,145 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::{Asciicast, Event, Header, Sizes};
+use crate::sequence::{Command, Timings};
+use anyhow::{bail, Result};
+use std::io::Write;
+use std::sync::mpsc::Sender;
+
+/// A destination for the events of a [`Recorder`].
+pub trait Sink {
+    /// Receives the header before any event.
+    fn header(&mut self, header: &Header) -> Result<()>;
+
+    /// Receives an event as soon as it is generated.
+    fn event(&mut self, event: Event) -> Result<()>;
+
+    /// Called after the last event.
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the header and events in the asciicast format, e.g. to a file.
+#[derive(Debug)]
+pub struct WriteSink<W: Write>(pub W);
+
+impl<W: Write> Sink for WriteSink<W> {
+    fn header(&mut self, header: &Header) -> Result<()> {
+        header.write_to(&mut self.0)?;
+        self.0.flush()?;
+        Ok(())
+    }
+
+    fn event(&mut self, mut event: Event) -> Result<()> {
+        event.write_to(&mut self.0)?;
+        self.0.flush()?;
+        Ok(())
+    }
+}
+
+/// Sends the events to a channel. The header is not sent.
+impl Sink for Sender<Event> {
+    fn header(&mut self, _header: &Header) -> Result<()> {
+        Ok(())
+    }
+
+    fn event(&mut self, event: Event) -> Result<()> {
+        self.send(event)?;
+        Ok(())
+    }
+}
+
+/// Generates the events for commands one at a time and forwards them to a
+/// sink immediately.
+///
+/// Since the header must come first, it is finalized and sent when the
+/// recorder is created. The event for the end delay is sent by
+/// [`Recorder::finish`], or when the recorder is dropped. Personas and remote
+/// framing are not applied; each command is displayed after its own prompt.
+#[derive(Debug)]
+pub struct Recorder<S: Sink> {
+    asciicast: Asciicast,
+    timings: Timings,
+    sink: Option<S>,
+    delay: f64,
+}
+
+impl<S: Sink> Recorder<S> {
+    pub fn new(mut asciicast: Asciicast, timings: Timings, mut sink: S) -> Result<Self> {
+        if let Some(Sizes::Fit) = asciicast.size {
+            bail!("The 'fit' size is not supported when streaming events");
+        }
+        asciicast.clear().resize()?;
+        sink.header(asciicast.finalize_header()?.as_ref())?;
+        Ok(Self {
+            asciicast,
+            timings,
+            sink: Some(sink),
+            delay: timings.begin.as_secs_f64(),
+        })
+    }
+
+    /// Generates the events for the command and sends them to the sink.
+    pub fn record(&mut self, command: &Command) -> Result<&mut Self> {
+        self.delay =
+            self.asciicast
+                .add_command(command, command.prompt(), &self.timings, self.delay);
+        self.flush()?;
+        Ok(self)
+    }
+
+    /// The time in seconds at which the next command starts.
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+
+    /// Sends the event for the end delay and returns the sink.
+    pub fn finish(mut self) -> Result<S> {
+        self.end()
+            .map(|sink| sink.expect("The sink is only taken when finished"))
+    }
+
+    fn end(&mut self) -> Result<Option<S>> {
+        let delay = self.delay;
+        self.asciicast.add_end(&self.timings, delay);
+        self.flush()?;
+        match self.sink.take() {
+            Some(mut sink) => {
+                sink.finish()?;
+                Ok(Some(sink))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some(sink) = self.sink.as_mut() {
+            for event in self.asciicast.drain() {
+                sink.event(event)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Sink> Drop for Recorder<S> {
+    fn drop(&mut self) {
+        if self.sink.is_some() {
+            let _ = self.end();
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::{Asciicast, Event, EventKind};
+use crate::error::{Error, Result};
+use std::convert::TryFrom;
+
+/// Plays the events of an asciicast through a terminal emulator, so the
+/// screen can be inspected at any time without waiting.
+pub struct Terminal<'a> {
+    events: &'a [Event],
+    next: usize,
+    parser: vt100::Parser,
+}
+
+impl<'a> Terminal<'a> {
+    pub fn new(asciicast: &'a Asciicast) -> Result<Self> {
+        let header = &asciicast.header;
+        let (rows, cols) = match (u16::try_from(header.height), u16::try_from(header.width)) {
+            (Ok(rows), Ok(cols)) => (rows, cols),
+            _ => {
+                return Err(Error::Invalid(format!(
+                    "The terminal size ({}x{}) is too large",
+                    header.width, header.height
+                )))
+            }
+        };
+        Ok(Self {
+            events: asciicast.events(),
+            next: 0,
+            parser: vt100::Parser::new(rows, cols, 0),
+        })
+    }
+
+    /// Plays the events up to, and including, the time in seconds, and
+    /// returns if any were played.
+    ///
+    /// The time cannot go backwards, so earlier times do nothing.
+    pub fn advance_to(&mut self, time: f64) -> bool {
+        let first = self.next;
+        while let Some(Event(at, kind, data)) = self.events.get(self.next) {
+            if *at > time {
+                break;
+            }
+            match kind {
+                EventKind::Printed => self.parser.process(data.as_bytes()),
+                EventKind::Resize => {
+                    if let Some((cols, rows)) = data
+                        .split_once('x')
+                        .and_then(|(c, r)| Some((c.parse().ok()?, r.parse().ok()?)))
+                    {
+                        self.parser.set_size(rows, cols);
+                    }
+                }
+                _ => {}
+            }
+            self.next += 1;
+        }
+        self.next != first
+    }
+
+    /// Plays all of the remaining events.
+    pub fn finish(&mut self) {
+        self.advance_to(f64::INFINITY);
+    }
+
+    pub fn screen(&self) -> &vt100::Screen {
+        self.parser.screen()
+    }
+}
@@ -13,10 +13,29 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use serde::{Deserialize, Serialize};
+use crate::asciicast::{Asciicast, Events};
+use crate::delay::{parse_milliseconds, parse_seconds, Delay};
+use crate::error::{Error, Result};
+use crate::json;
+use crate::keyboard::{KeyboardLayouts, Layout};
+use crate::redact;
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+#[cfg(feature = "cli")]
 use structopt::StructOpt;
+#[cfg(feature = "cli")]
+use strum::VariantNames;
+use strum::{Display, EnumString, EnumVariantNames};
+use unicode_segmentation::UnicodeSegmentation;
 
-pub const VERSION: usize = 1;
+pub const VERSION: usize = 2;
+/// The version of sequence files with the begin and end delays in seconds,
+/// instead of milliseconds like the other delays.
+pub const LEGACY_VERSION: usize = 1;
 pub const DEFAULT_PROMPT: &str = "$ ";
 pub const DEFAULT_SPEED: &str = "1.0";
 pub const DEFAULT_BEGIN_DELAY: &str = "0.0";
@@ -25,109 +44,458 @@ pub const DEFAULT_DELAY_TYPE_START: &str = "750";
 pub const DEFAULT_DELAY_TYPE_CHAR: &str = "35";
 pub const DEFAULT_DELAY_TYPE_SUBMIT: &str = "350";
 pub const DEFAULT_DELAY_OUTPUT_LINE: &str = "500";
+pub const DEFAULT_DELAY_TYPE_JITTER: &str = "0";
+pub const DEFAULT_COMMENT_DELAY: &str = "0";
+pub const DEFAULT_ECHO_LATENCY: &str = "0";
+pub const DEFAULT_CWD: &str = "~";
+pub const DEFAULT_GIT_BRANCH: &str = "main";
+pub const INTERRUPTED_STATUS: i32 = 130;
+pub const ANSI_ESCAPE: &str =
+    r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[ -/]*[0-~])";
+pub const STATUS_MARK: &str = "\x1b[31m✗\x1b[0m ";
+#[cfg(feature = "exec")]
+pub const DEFAULT_SECRET_LENGTH: &str = "8";
+pub const DEFAULT_PROGRESS_WIDTH: usize = 30;
+pub const DEFAULT_PROGRESS_STEPS: usize = 20;
+pub const DEFAULT_SPINNER_FRAMES: &str = "⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏";
+pub const DEFAULT_SPINNER_INTERVAL: &str = "80";
+#[cfg(feature = "cli")]
 pub const MILLISECONDS_UNITS: &str = "ms";
+#[cfg(feature = "cli")]
 pub const SECONDS_UNITS: &str = "secs";
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, StructOpt)]
+/// Sets of typing delays for the feel of a demo.
+#[derive(Debug, Clone, Copy, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum TypingProfiles {
+    Slow,
+    Normal,
+    Fast,
+    Instant,
+    /// Like normal, but with an uneven delay between characters.
+    Human,
+}
+
+/// Where the environment variables referenced in an input, i.e. "$HOME", are
+/// expanded by germ.
+#[cfg(feature = "exec")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum EnvExpansions {
+    /// Nowhere, the input is typed and executed as written.
+    #[default]
+    None,
+    /// In the executed input, while the typed input is written literally.
+    Execute,
+    /// In both the typed and the executed input.
+    All,
+}
+
+impl TypingProfiles {
+    /// The type start, type char, type submit, and type jitter delays.
+    pub fn delays(&self) -> (Delay, Delay, Delay, Delay) {
+        let (start, char, submit, jitter) = match self {
+            Self::Slow => (1000, 100, 500, 0),
+            Self::Normal => (750, 35, 350, 0),
+            Self::Fast => (250, 15, 150, 0),
+            Self::Instant => (0, 0, 0, 0),
+            Self::Human => (750, 60, 400, 40),
+        };
+        (
+            Delay::from_millis(start),
+            Delay::from_millis(char),
+            Delay::from_millis(submit),
+            Delay::from_millis(jitter),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[cfg_attr(feature = "cli", derive(StructOpt))]
 pub struct Timings {
     /// The delay before starting the animation.
     ///
     /// The units are in seconds (s).
-    #[structopt(
-        short = "b",
-        long = "begin-delay",
-        default_value = DEFAULT_BEGIN_DELAY,
-        value_name = SECONDS_UNITS,
-        env = "GERM_BEGIN_DELAY"
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            short = "b",
+            long = "begin-delay",
+            default_value = DEFAULT_BEGIN_DELAY,
+            value_name = SECONDS_UNITS,
+            env = "GERM_BEGIN_DELAY",
+            parse(try_from_str = parse_seconds)
+        )
     )]
-    pub begin: f64, // seconds
+    pub begin: Delay,
 
     /// The delay at the end of the animation.
     ///
     /// This is useful when looping/repeat is enabled and some time between
     /// iterations is needed and/or desired. Set the value to 0.0 if no hold is
     /// desired. The units are in seconds (s).
-    #[structopt(
-        short = "e",
-        long = "end-delay",
-        default_value = DEFAULT_END_DELAY,
-        value_name = SECONDS_UNITS,
-        env = "GERM_END_DELAY"
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            short = "e",
+            long = "end-delay",
+            default_value = DEFAULT_END_DELAY,
+            value_name = SECONDS_UNITS,
+            env = "GERM_END_DELAY",
+            parse(try_from_str = parse_seconds)
+        )
     )]
-    pub end: f64, // seconds
+    pub end: Delay,
 
     /// The delay before starting the simulated typing for the command.
     ///
     /// The units are in milliseconds (ms).
-    #[structopt(
-        long = "delay-type-start",
-        default_value = DEFAULT_DELAY_TYPE_START,
-        value_name = MILLISECONDS_UNITS,
-        env = "GERM_DELAY_TYPE_START"
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "delay-type-start",
+            default_value = DEFAULT_DELAY_TYPE_START,
+            value_name = MILLISECONDS_UNITS,
+            env = "GERM_DELAY_TYPE_START",
+            parse(try_from_str = parse_milliseconds)
+        )
     )]
-    pub type_start: usize, // milliseconds
+    pub type_start: Delay,
 
     /// The delay between simulating typing of characters for the command.
     ///
     /// The units are in milliseconds (ms).
-    #[structopt(
-        long = "delay-type-char",
-        default_value = DEFAULT_DELAY_TYPE_CHAR,
-        value_name = MILLISECONDS_UNITS,
-        env = "GERM_DELAY_TYPE_CHAR"
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "delay-type-char",
+            default_value = DEFAULT_DELAY_TYPE_CHAR,
+            value_name = MILLISECONDS_UNITS,
+            env = "GERM_DELAY_TYPE_CHAR",
+            parse(try_from_str = parse_milliseconds)
+        )
     )]
-    pub type_char: usize, // milliseconds
+    pub type_char: Delay,
+
+    /// The most the delay between characters varies from --delay-type-char.
+    ///
+    /// Each delay is varied by a pseudo-random amount, the same for the same
+    /// input, so the typing looks less mechanical. The units are in
+    /// milliseconds (ms).
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "delay-type-jitter",
+            default_value = DEFAULT_DELAY_TYPE_JITTER,
+            value_name = MILLISECONDS_UNITS,
+            env = "GERM_DELAY_TYPE_JITTER",
+            parse(try_from_str = parse_milliseconds)
+        )
+    )]
+    #[serde(default, skip_serializing_if = "Delay::is_zero")]
+    pub type_jitter: Delay,
 
     /// The delay between the simulated typing and output printing.
     ///
     /// The units are in milliseconds (ms).
-    #[structopt(
-        long = "delay-type-submit",
-        default_value = DEFAULT_DELAY_TYPE_SUBMIT,
-        value_name = MILLISECONDS_UNITS,
-        env = "GERM_DELAY_TYPE_SUBMIT"
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "delay-type-submit",
+            default_value = DEFAULT_DELAY_TYPE_SUBMIT,
+            value_name = MILLISECONDS_UNITS,
+            env = "GERM_DELAY_TYPE_SUBMIT",
+            parse(try_from_str = parse_milliseconds)
+        )
+    )]
+    pub type_submit: Delay,
+
+    /// The delay after a comment is printed before the prompt.
+    ///
+    /// This holds the comment on screen for reading time before the command
+    /// is typed. The units are in milliseconds (ms).
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "comment-delay",
+            default_value = DEFAULT_COMMENT_DELAY,
+            value_name = MILLISECONDS_UNITS,
+            env = "GERM_COMMENT_DELAY",
+            parse(try_from_str = parse_milliseconds)
+        )
     )]
-    pub type_submit: usize, // milliseconds
+    #[serde(default, skip_serializing_if = "Delay::is_zero")]
+    pub comment_delay: Delay,
 
     /// The delay between outputs for the command.
     ///
     /// The units are in milliseconds (ms).
-    #[structopt(
-        long = "delay-output-line",
-        default_value = DEFAULT_DELAY_OUTPUT_LINE,
-        value_name = MILLISECONDS_UNITS,
-        env = "GERM_DELAY_OUTPUT_LINE",
-        hide_env_values = true
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "delay-output-line",
+            default_value = DEFAULT_DELAY_OUTPUT_LINE,
+            value_name = MILLISECONDS_UNITS,
+            env = "GERM_DELAY_OUTPUT_LINE",
+            hide_env_values = true,
+            parse(try_from_str = parse_milliseconds)
+        )
     )]
-    pub output_line: usize, // milliseconds
+    pub output_line: Delay,
+
+    /// The delay between a keypress and the echo of the typed character.
+    ///
+    /// With --stdin, each character is printed this long after its keypress
+    /// event, like the echo of a real terminal trails the keystroke. It
+    /// should be less than the --delay-type-char value. The units are in
+    /// milliseconds (ms).
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long = "echo-latency",
+            default_value = DEFAULT_ECHO_LATENCY,
+            value_name = MILLISECONDS_UNITS,
+            env = "GERM_ECHO_LATENCY",
+            parse(try_from_str = parse_milliseconds)
+        )
+    )]
+    #[serde(default, skip_serializing_if = "Delay::is_zero")]
+    pub echo_latency: Delay,
 
     /// Speed up or slow down the animation by this factor.
-    #[structopt(short = "s", long, default_value = "1.0", value_name = "float")]
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            short = "s",
+            long,
+            default_value = DEFAULT_SPEED,
+            value_name = "float",
+            validator = validate_speed
+        )
+    )]
     pub speed: f64, // Factor
+
+    /// A preset of the typing delays.
+    ///
+    /// This replaces the --delay-type-start, --delay-type-char,
+    /// --delay-type-submit, and --delay-type-jitter values.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            possible_values = TypingProfiles::VARIANTS,
+            case_insensitive = true,
+            value_name = "profile",
+            env = "GERM_TYPING_PROFILE"
+        )
+    )]
+    #[serde(skip)]
+    pub typing_profile: Option<TypingProfiles>,
+
+    /// Varies the delay between characters by the distance between their
+    /// keys on the keyboard layout.
+    ///
+    /// Nearby keys are typed faster than the --delay-type-char value and
+    /// distant keys slower, for a natural typing rhythm.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            possible_values = KeyboardLayouts::VARIANTS,
+            case_insensitive = true,
+            value_name = "layout",
+            env = "GERM_TYPING_LAYOUT"
+        )
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typing_layout: Option<KeyboardLayouts>,
+}
+
+impl Timings {
+    /// Checks the speed is greater than zero, so the timestamps of the
+    /// generated events are finite and in order. The delays cannot be
+    /// negative.
+    pub fn validate(&self) -> Result<()> {
+        check_speed(self.speed)
+            .map(|_| ())
+            .map_err(|err| Error::InvalidTiming(format!("speed: {}", err)))
+    }
+
+    /// Replaces the typing delays with those of the typing profile, if any.
+    pub fn profiled(mut self) -> Self {
+        if let Some(profile) = self.typing_profile {
+            let (start, char, submit, jitter) = profile.delays();
+            self.type_start = start;
+            self.type_char = char;
+            self.type_submit = submit;
+            self.type_jitter = jitter;
+        }
+        self
+    }
+
+    /// The delay in milliseconds after the type start delay of each grapheme
+    /// cluster of the input, followed by the delay after the last one.
+    pub fn type_offsets(&self, input: &str) -> Vec<usize> {
+        self.type_offsets_with(input, self.typing_layout.as_ref().map(|l| l as &dyn Layout))
+    }
+
+    /// Like [`Timings::type_offsets`], but the delay between characters
+    /// depends on the distance between their keys on the layout, if any.
+    pub fn type_offsets_with(&self, input: &str, layout: Option<&dyn Layout>) -> Vec<usize> {
+        let seed = input.bytes().fold(FNV_OFFSET_BASIS, |h, b| {
+            (h ^ b as u64).wrapping_mul(FNV_PRIME)
+        });
+        let keys = input
+            .graphemes(true)
+            .map(|g| g.chars().next().unwrap_or_default())
+            .collect::<Vec<char>>();
+        let mut offset = 0;
+        let mut offsets = vec![offset];
+        let type_jitter = self.type_jitter.as_millis();
+        for (i, key) in keys.iter().enumerate() {
+            let type_char = match (layout, keys.get(i + 1)) {
+                (Some(layout), Some(next)) => (self.type_char.as_millis() as f64
+                    * layout.factor(*key, *next))
+                .round() as usize,
+                _ => self.type_char.as_millis(),
+            };
+            let jitter = (2 * type_jitter + 1) as u64;
+            let variation = (splitmix64(seed.wrapping_add(i as u64)) % jitter) as usize;
+            // Only the step saturates, so the characters are typed in order.
+            offset += (type_char + variation).saturating_sub(type_jitter);
+            offsets.push(offset);
+        }
+        offsets
+    }
+}
+
+/// The speed if it is a finite factor greater than zero.
+fn check_speed(speed: f64) -> std::result::Result<f64, String> {
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(format!(
+            "The speed must be a factor greater than zero, found '{}'",
+            speed
+        ))
+    }
+}
+
+/// Validates a command line speed with the same rules as the speed of a
+/// sequence file.
+#[cfg(feature = "cli")]
+fn validate_speed(value: String) -> std::result::Result<(), String> {
+    value
+        .parse()
+        .map_err(|err| format!("{}", err))
+        .and_then(check_speed)
+        .map(|_| ())
 }
 
 impl Default for Timings {
     fn default() -> Self {
         Self {
-            begin: DEFAULT_BEGIN_DELAY.parse().expect("Default float"),
-            end: DEFAULT_END_DELAY.parse().expect("Default float"),
-            type_start: DEFAULT_DELAY_TYPE_START.parse().expect("Default usize"),
-            type_char: DEFAULT_DELAY_TYPE_CHAR.parse().expect("Default usize"),
-            type_submit: DEFAULT_DELAY_TYPE_SUBMIT.parse().expect("Default usize"),
-            output_line: DEFAULT_DELAY_OUTPUT_LINE.parse().expect("Default usize"),
+            begin: parse_seconds(DEFAULT_BEGIN_DELAY).expect("Default delay"),
+            end: parse_seconds(DEFAULT_END_DELAY).expect("Default delay"),
+            type_start: parse_milliseconds(DEFAULT_DELAY_TYPE_START).expect("Default delay"),
+            type_char: parse_milliseconds(DEFAULT_DELAY_TYPE_CHAR).expect("Default delay"),
+            type_submit: parse_milliseconds(DEFAULT_DELAY_TYPE_SUBMIT).expect("Default delay"),
+            output_line: parse_milliseconds(DEFAULT_DELAY_OUTPUT_LINE).expect("Default delay"),
+            type_jitter: parse_milliseconds(DEFAULT_DELAY_TYPE_JITTER).expect("Default delay"),
+            comment_delay: parse_milliseconds(DEFAULT_COMMENT_DELAY).expect("Default delay"),
+            echo_latency: parse_milliseconds(DEFAULT_ECHO_LATENCY).expect("Default delay"),
             speed: DEFAULT_SPEED.parse().expect("Default speed"),
+            typing_profile: None,
+            typing_layout: None,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// The preferred output settings declared by a sequence file.
+///
+/// The command line options take precedence over these settings.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Render {
+    /// The output formats, i.e. "asciicast" or "germ".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formats: Vec<String>,
+    /// The name of a built-in color theme.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Sequence {
     version: usize,
     timings: Timings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    render: Option<Render>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    personas: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<PathBuf>,
     commands: Vec<Command>,
 }
 
 impl Sequence {
+    /// Reads a sequence from a germ JSON document.
+    pub fn from_slice(buffer: &[u8]) -> Result<Self> {
+        let mut sequence: Self = json::from_slice(buffer)?;
+        match sequence.version() {
+            VERSION => {}
+            LEGACY_VERSION => {
+                // The seconds were read as milliseconds.
+                sequence.timings.begin = (Duration::from(sequence.timings.begin) * 1000).into();
+                sequence.timings.end = (Duration::from(sequence.timings.end) * 1000).into();
+                for command in &mut sequence.commands {
+                    for output in command.outputs_mut() {
+                        match output {
+                            Output::Text(_) => {}
+                            Output::Progress(p) => {
+                                p.progress = (Duration::from(p.progress) * 1000).into()
+                            }
+                            Output::Spinner(s) => {
+                                s.spinner = (Duration::from(s.spinner) * 1000).into()
+                            }
+                        }
+                    }
+                }
+                sequence.version = VERSION;
+            }
+            found => {
+                return Err(Error::UnsupportedVersion {
+                    format: "germ",
+                    found,
+                    expected: VERSION,
+                })
+            }
+        }
+        sequence
+            .timings
+            .validate()
+            .map_err(|err| Error::InvalidTiming(format!("timings.{}", err)))?;
+        Ok(sequence)
+    }
+
+    /// The asciicast with the events of the commands, displayed with the
+    /// settings of the other asciicast, i.e. `Asciicast::default()` with a
+    /// different header.
+    pub fn to_asciicast(&self, settings: &Asciicast) -> Asciicast {
+        Asciicast::from_sequence(self, settings)
+    }
+
+    /// The events of the commands, generated one command at a time as they
+    /// are needed.
+    pub fn events(&self, settings: &Asciicast) -> Events {
+        Events::new(self, *self.timings(), settings)
+    }
+
+    pub fn get(&self, i: usize) -> Option<&Command> {
+        self.commands.get(i)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &Command> {
         self.commands.iter()
     }
@@ -137,23 +505,172 @@ impl Sequence {
         self
     }
 
+    /// Replaces each match of the patterns in the comments, inputs, and
+    /// outputs of the commands with "[REDACTED]".
+    pub fn redact(&mut self, patterns: &[Regex]) -> &mut Self {
+        for command in &mut self.commands {
+            command.redact(patterns);
+        }
+        self
+    }
+
+    /// Removes the ANSI escape sequences, like colors, from the outputs of
+    /// the commands.
+    pub fn strip_ansi(&mut self) -> &mut Self {
+        for command in &mut self.commands {
+            command.strip_ansi();
+        }
+        self
+    }
+
     pub fn append(&mut self, commands: &mut Vec<Command>) -> &mut Self {
         self.commands.append(commands);
         self
     }
 
     pub fn append_from(&mut self, s: Sequence) -> &mut Self {
-        let Sequence { commands, .. } = s;
+        let Sequence {
+            personas, commands, ..
+        } = s;
+        self.personas.extend(personas);
         for command in commands {
             self.add(command);
         }
         self
     }
 
+    /// Inserts the commands of an included sequence before the commands of
+    /// this sequence. The personas of this sequence take precedence over the
+    /// personas of the included sequence.
+    pub fn prepend_from(&mut self, s: Sequence) -> &mut Self {
+        let Sequence {
+            mut personas,
+            mut commands,
+            ..
+        } = s;
+        personas.append(&mut self.personas);
+        self.personas = personas;
+        commands.append(&mut self.commands);
+        self.commands = commands;
+        self
+    }
+
+    /// Removes the paths of the sequence files to include, which are relative
+    /// to the file of this sequence.
+    pub fn take_includes(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.include)
+    }
+
+    /// Adds, or replaces, a named prompt that commands can be assigned.
+    pub fn add_persona(&mut self, name: &str, prompt: &str) -> &mut Self {
+        self.personas.insert(name.to_owned(), prompt.to_owned());
+        self
+    }
+
+    pub fn persona(&self, name: &str) -> Option<&str> {
+        self.personas.get(name).map(String::as_str)
+    }
+
+    /// The prompt displayed for the command, which is the prompt of its
+    /// persona if it is assigned one.
+    pub fn prompt_of<'a>(&'a self, command: &'a Command) -> &'a str {
+        command
+            .persona()
+            .and_then(|p| self.persona(p))
+            .unwrap_or_else(|| command.prompt())
+    }
+
+    /// A copy of the sequence with the commands of each remote session framed
+    /// by typing `ssh host` with the banner as its output before the session
+    /// and `exit` after the session.
+    ///
+    /// The commands within a session use the remote prompt, i.e.
+    /// "user@host:~$ ". Any "{host}" in the banner is replaced with the host.
+    ///
+    /// The prompts of the other commands, including those of personas, are
+    /// resolved as templates, i.e. "{cwd} $ " is displayed as "~/src $ "
+    /// after `cd src`.
+    pub fn framed(&self, banner: &str) -> Sequence {
+        let mut commands = Vec::new();
+        let mut session: Option<&str> = None;
+        let mut shell = Shell::default();
+        for command in &self.commands {
+            if let Some(cwd) = command.cwd() {
+                shell.enter(cwd);
+            }
+            let prompt = shell.resolve(self.prompt_of(command));
+            if session != command.remote() {
+                if let Some(remote) = session {
+                    commands.push(exit_command(remote));
+                }
+                if let Some(remote) = command.remote() {
+                    let host = remote.rsplit('@').next().unwrap_or(remote);
+                    let mut ssh = Command::from(format!("ssh {}", remote));
+                    ssh.set_prompt(&prompt);
+                    if !banner.is_empty() {
+                        ssh.add(&banner.replace("{host}", host));
+                    }
+                    commands.push(ssh);
+                }
+                session = command.remote();
+            }
+            let mut command = command.clone();
+            if let Some(remote) = command.remote.take() {
+                command.set_prompt(&remote_prompt(&remote));
+            } else {
+                command.set_prompt(&prompt);
+                shell.run(&command.text());
+                shell.status = command.status();
+            }
+            command.set_persona(None);
+            commands.push(command);
+        }
+        if let Some(remote) = session {
+            commands.push(exit_command(remote));
+        }
+        Sequence {
+            version: self.version,
+            timings: self.timings,
+            render: self.render.clone(),
+            personas: self.personas.clone(),
+            include: self.include.clone(),
+            commands,
+        }
+    }
+
+    /// Checks that every persona assigned to a command is defined.
+    pub fn check_personas(&self) -> Result<()> {
+        for (i, command) in self.commands.iter().enumerate() {
+            if let Some(persona) = command.persona() {
+                if self.persona(persona).is_none() {
+                    return Err(Error::Invalid(format!(
+                        "commands[{}]: unknown persona '{}'",
+                        i, persona
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn render(&self) -> Option<&Render> {
+        self.render.as_ref()
+    }
+
+    pub fn set_render(&mut self, r: Option<Render>) -> &mut Self {
+        self.render = r;
+        self
+    }
+
     pub fn timings(&self) -> &Timings {
         &self.timings
     }
 
+    pub fn set_timings(&mut self, t: Timings) -> &mut Self {
+        self.timings = t;
+        self
+    }
+
     pub fn into_timings(self) -> Timings {
         self.timings
     }
@@ -186,6 +703,9 @@ impl Default for Sequence {
         Self {
             version: VERSION,
             timings: Timings::default(),
+            render: None,
+            personas: BTreeMap::new(),
+            include: Vec::new(),
             commands: Vec::new(),
         }
     }
@@ -200,16 +720,395 @@ impl IntoIterator for Sequence {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// The markers in an input for pressing the tab key to complete a word.
+pub const TAB_MARKERS: &[&str] = &["{tab}", "\u{21e5}"];
+
+/// A part of the input of a command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputPart<'a> {
+    /// Typed one character at a time.
+    Typed(&'a str),
+    /// Printed at once after a pause, like a shell completing a word after
+    /// the tab key is pressed. The completion ends at the next whitespace.
+    Completed(&'a str),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Command {
     #[serde(skip_serializing_if = "Option::is_none")]
     comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment_delay: Option<Delay>,
     prompt: String,
     input: String,
-    outputs: Vec<String>,
+    outputs: Vec<Output>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    width: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persona: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retries: Option<usize>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    paste: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    interrupt: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<Secret>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repl: Option<Repl>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    responses: Vec<Response>,
+}
+
+/// A response typed when a command prompts for it, like "y" for a "[y/n]"
+/// confirmation, so the command can be executed unattended.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Response {
+    /// The text of the prompt waited for in the output, i.e. "[y/n]".
+    pub expect: String,
+    /// The text typed and sent, followed by a new line, after the prompt.
+    pub send: String,
+    /// The line of the output with the prompt, which is displayed before the
+    /// response is typed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt: Option<String>,
+    /// The outputs after the response, up to the next prompt.
+    #[serde(default)]
+    outputs: Vec<Output>,
+}
+
+impl Response {
+    pub fn new(expect: &str, send: &str) -> Self {
+        Self {
+            expect: expect.to_owned(),
+            send: send.to_owned(),
+            prompt: None,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// The response as a command typed at its prompt.
+    pub fn command(&self) -> Command {
+        let mut command = Command::from(self.send.as_str());
+        command.set_prompt(self.prompt.as_deref().unwrap_or(&self.expect));
+        for output in &self.outputs {
+            command.add_output(output.clone());
+        }
+        command
+    }
+}
+
+/// The inputs and outputs of an interactive program, like python, psql, or
+/// gdb, started by a command.
+///
+/// Each step is typed at the prompt of the program after the outputs of the
+/// command, so a REPL session is one command in the sequence.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Repl {
+    /// The prompt of the program, i.e. ">>> ".
+    pub prompt: String,
+    steps: Vec<Step>,
+}
+
+impl Repl {
+    pub fn new(prompt: &str) -> Self {
+        Self {
+            prompt: prompt.to_owned(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, input: &str, outputs: Vec<Output>) -> &mut Self {
+        self.steps.push(Step {
+            input: input.to_owned(),
+            outputs,
+        });
+        self
+    }
+
+    /// The steps as commands at the prompt of the program.
+    pub fn iter(&self) -> impl Iterator<Item = Command> + '_ {
+        self.steps.iter().map(move |step| {
+            let mut command = Command::from(step.input.as_str());
+            command.set_prompt(&self.prompt);
+            for output in &step.outputs {
+                command.add_output(output.clone());
+            }
+            command
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Step {
+    input: String,
+    #[serde(default)]
+    outputs: Vec<Output>,
+}
+
+/// An output of a command.
+///
+/// Most outputs are text, but an output can also be an animation that is
+/// redrawn in place, like a progress bar.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Output {
+    /// Printed line by line.
+    Text(String),
+    /// A progress bar filled from 0% to 100%.
+    Progress(Progress),
+    /// A spinner that is erased when done.
+    Spinner(Spinner),
+}
+
+impl<'de> Deserialize<'de> for Output {
+    /// Reads a text, or the animation named by its key, so an invalid
+    /// duration is reported instead of an output matching no variant.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let output = if value.get("progress").is_some() {
+            Progress::deserialize(value).map(Self::Progress)
+        } else if value.get("spinner").is_some() {
+            Spinner::deserialize(value).map(Self::Spinner)
+        } else {
+            String::deserialize(value).map(Self::Text)
+        };
+        output.map_err(de::Error::custom)
+    }
+}
+
+impl Output {
+    /// The time taken by the output in seconds, in addition to the delay
+    /// between output lines.
+    pub fn duration(&self) -> f64 {
+        match self {
+            Self::Text(_) => 0.0,
+            Self::Progress(p) => p.progress.as_secs_f64(),
+            Self::Spinner(s) => s.spinner.as_secs_f64(),
+        }
+    }
+
+    /// Replaces the text, if any, with the result of the function.
+    pub fn map_text(&mut self, f: &dyn Fn(&str) -> String) -> &mut Self {
+        if let Self::Text(text) = self {
+            *text = f(text);
+        }
+        self
+    }
+
+    /// The output as it appears after it is done, without any animation.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(t) => t.clone(),
+            Self::Progress(p) => p.frame(p.steps()),
+            Self::Spinner(_) => String::new(),
+        }
+    }
+}
+
+/// An animated spinner, i.e. "⠋ Working".
+///
+/// Each frame overwrites the previous one with a carriage return, and the
+/// line is cleared when the spinner is done.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Spinner {
+    /// The time the spinner is displayed.
+    pub spinner: Delay,
+    /// The text printed after the spinner.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    /// The frames of the spinner in order, which repeat.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<String>,
+    /// The time between frames.
+    #[serde(default, skip_serializing_if = "Delay::is_zero")]
+    pub interval: Delay,
+}
+
+impl Spinner {
+    pub fn new(duration: Delay, label: &str) -> Self {
+        Self {
+            spinner: duration,
+            label: label.to_owned(),
+            frames: Vec::new(),
+            interval: Delay::ZERO,
+        }
+    }
+
+    /// Sets the frames from the characters of the text, i.e. "|/-\\".
+    pub fn set_frames(&mut self, f: &str) -> &mut Self {
+        self.frames = f.graphemes(true).map(String::from).collect();
+        self
+    }
+
+    fn interval(&self) -> Delay {
+        if self.interval.is_zero() {
+            parse_milliseconds(DEFAULT_SPINNER_INTERVAL).expect("Default delay")
+        } else {
+            self.interval
+        }
+    }
+
+    /// The frames of the spinner with their offsets in seconds from the start
+    /// of the animation, ending with clearing the line.
+    pub fn frames(&self) -> Vec<(f64, String)> {
+        let frames = if self.frames.is_empty() {
+            DEFAULT_SPINNER_FRAMES
+                .graphemes(true)
+                .map(String::from)
+                .collect()
+        } else {
+            self.frames.clone()
+        };
+        let interval = self.interval().as_secs_f64();
+        let duration = self.spinner.as_secs_f64();
+        let mut timed = frames
+            .iter()
+            .cycle()
+            .enumerate()
+            .map(|(i, frame)| (i as f64 * interval, frame))
+            .take_while(|(offset, _)| *offset < duration)
+            .map(|(offset, frame)| {
+                let mut text = frame.clone();
+                if !self.label.is_empty() {
+                    text.push(' ');
+                    text.push_str(&self.label);
+                }
+                (offset, text)
+            })
+            .collect::<Vec<(f64, String)>>();
+        timed.push((duration, String::from("\x1b[K")));
+        timed
+    }
+}
+
+impl From<String> for Output {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<&str> for Output {
+    fn from(s: &str) -> Self {
+        Self::Text(s.to_owned())
+    }
+}
+
+/// An animated progress bar, i.e. "Downloading [#######-------]  50%".
+///
+/// Each frame overwrites the previous one with a carriage return, like
+/// the progress bars of package managers and downloaders.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Progress {
+    /// The time to go from 0% to 100%.
+    pub progress: Delay,
+    /// The text printed before the bar.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    /// The number of columns of the bar, without the label and percentage.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub width: usize,
+    /// The number of frames after the empty bar.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub steps: usize,
+}
+
+impl Progress {
+    pub fn new(duration: Delay, label: &str) -> Self {
+        Self {
+            progress: duration,
+            label: label.to_owned(),
+            width: 0,
+            steps: 0,
+        }
+    }
+
+    fn width(&self) -> usize {
+        if self.width == 0 {
+            DEFAULT_PROGRESS_WIDTH
+        } else {
+            self.width
+        }
+    }
+
+    fn steps(&self) -> usize {
+        if self.steps == 0 {
+            DEFAULT_PROGRESS_STEPS
+        } else {
+            self.steps
+        }
+    }
+
+    /// The text of the bar at the step.
+    fn frame(&self, step: usize) -> String {
+        let width = self.width();
+        let filled = width * step / self.steps();
+        let mut frame = self.label.clone();
+        if !frame.is_empty() {
+            frame.push(' ');
+        }
+        frame.push('[');
+        frame.push_str(&"#".repeat(filled));
+        frame.push_str(&"-".repeat(width - filled));
+        frame.push_str(&format!("] {:>3}%", 100 * step / self.steps()));
+        frame
+    }
+
+    /// The frames of the bar with their offsets in seconds from the start of
+    /// the animation, ending the line after the full bar.
+    pub fn frames(&self) -> Vec<(f64, String)> {
+        let steps = self.steps();
+        let duration = self.progress.as_secs_f64();
+        let mut frames = (0..=steps)
+            .map(|step| (duration * step as f64 / steps as f64, self.frame(step)))
+            .collect::<Vec<(f64, String)>>();
+        if let Some((_, last)) = frames.last_mut() {
+            last.push_str("\r\n");
+        }
+        frames
+    }
+}
+
+/// A hidden input, like a password, typed after the command is submitted.
+///
+/// Only the number of characters is kept, so no real secret is ever stored.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Secret {
+    /// The text printed before the secret is typed, i.e. "Password: ".
+    pub prompt: String,
+    /// The number of characters typed.
+    pub length: usize,
+    /// The character printed for each typed character, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<char>,
 }
 
 impl Command {
+    /// A command for the input with the default prompt and no outputs.
+    pub fn new(input: &str) -> Self {
+        Self::from(input)
+    }
+
+    /// A builder for a command with the input.
+    pub fn builder(input: &str) -> CommandBuilder {
+        CommandBuilder::new(input)
+    }
+
+    pub fn set_input(&mut self, i: &str) -> &mut Self {
+        self.input = i.to_owned();
+        self
+    }
+
     pub fn set_comment(&mut self, c: Option<&str>) -> &mut Self {
         self.comment = c.map(|s| s.to_owned());
         self
@@ -219,22 +1118,276 @@ impl Command {
         self.comment.as_deref()
     }
 
+    /// Sets the delay after the comment, if different from the
+    /// --comment-delay of the sequence.
+    pub fn set_comment_delay(&mut self, d: Option<Delay>) -> &mut Self {
+        self.comment_delay = d;
+        self
+    }
+
+    pub fn comment_delay(&self) -> Option<Delay> {
+        self.comment_delay
+    }
+
+    /// Sets the number of columns needed by the command, if different from
+    /// the rest of the sequence.
+    pub fn set_width(&mut self, w: Option<usize>) -> &mut Self {
+        self.width = w;
+        self
+    }
+
+    pub fn width(&self) -> Option<usize> {
+        self.width
+    }
+
+    /// Sets the number of rows needed by the command, if different from the
+    /// rest of the sequence.
+    pub fn set_height(&mut self, h: Option<usize>) -> &mut Self {
+        self.height = h;
+        self
+    }
+
+    pub fn height(&self) -> Option<usize> {
+        self.height
+    }
+
     pub fn set_prompt(&mut self, p: &str) -> &mut Self {
         self.prompt = p.to_owned();
         self
     }
 
+    /// Assigns the command to a persona defined in the sequence, which
+    /// replaces the prompt of the command.
+    pub fn set_persona(&mut self, p: Option<&str>) -> &mut Self {
+        self.persona = p.map(|s| s.to_owned());
+        self
+    }
+
+    pub fn persona(&self) -> Option<&str> {
+        self.persona.as_deref()
+    }
+
+    /// Tags the command as executed on a remote host over SSH, i.e. "host" or
+    /// "user@host".
+    pub fn set_remote(&mut self, r: Option<&str>) -> &mut Self {
+        self.remote = r.map(|s| s.to_owned());
+        self
+    }
+
+    pub fn remote(&self) -> Option<&str> {
+        self.remote.as_deref()
+    }
+
+    /// Sets the working directory where the input is executed. The "{cwd}"
+    /// and "{dir}" segments of the prompt display this directory.
+    pub fn set_cwd(&mut self, c: Option<&str>) -> &mut Self {
+        self.cwd = c.map(|s| s.to_owned());
+        self
+    }
+
+    pub fn cwd(&self) -> Option<&str> {
+        self.cwd.as_deref()
+    }
+
+    /// Sets the times the input is executed again if it fails, if different
+    /// from the --retries of the execution.
+    pub fn set_retries(&mut self, r: Option<usize>) -> &mut Self {
+        self.retries = r;
+        self
+    }
+
+    pub fn retries(&self) -> Option<usize> {
+        self.retries
+    }
+
+    /// Displays the input all at once, like pasting from the clipboard,
+    /// instead of typing it.
+    pub fn set_paste(&mut self, p: bool) -> &mut Self {
+        self.paste = p;
+        self
+    }
+
+    pub fn paste(&self) -> bool {
+        self.paste
+    }
+
+    /// Ends the command with Ctrl+C, i.e. "^C", after the outputs. Without
+    /// outputs, the typed input is canceled instead of submitted.
+    pub fn set_interrupt(&mut self, i: bool) -> &mut Self {
+        self.interrupt = i;
+        self
+    }
+
+    pub fn interrupt(&self) -> bool {
+        self.interrupt
+    }
+
+    /// Sets the exit code of the command. A zero exit code is not kept, as it
+    /// is the exit code of any command without one.
+    pub fn set_status(&mut self, s: Option<i32>) -> &mut Self {
+        self.status = s.filter(|s| *s != 0);
+        self
+    }
+
+    /// The exit code of the command, which is 130 if interrupted.
+    pub fn status(&self) -> i32 {
+        match self.status {
+            Some(status) => status,
+            None if self.interrupt => INTERRUPTED_STATUS,
+            None => 0,
+        }
+    }
+
+    /// Types a hidden input at a password prompt after the command is
+    /// submitted and before the outputs.
+    pub fn set_secret(&mut self, s: Option<Secret>) -> &mut Self {
+        self.secret = s;
+        self
+    }
+
+    /// Replaces each match of the patterns in the comment, input, and outputs
+    /// with "[REDACTED]", including those of the REPL steps and responses.
+    pub fn redact(&mut self, patterns: &[Regex]) -> &mut Self {
+        if patterns.is_empty() {
+            return self;
+        }
+        self.comment = self.comment.as_deref().map(|c| redact::redact(c, patterns));
+        self.input = redact::redact(&self.input, patterns);
+        if let Some(repl) = &mut self.repl {
+            for step in &mut repl.steps {
+                step.input = redact::redact(&step.input, patterns);
+            }
+        }
+        for response in &mut self.responses {
+            response.send = redact::redact(&response.send, patterns);
+            response.prompt = response
+                .prompt
+                .as_deref()
+                .map(|p| redact::redact(p, patterns));
+        }
+        self.map_outputs(&|text| redact::redact(text, patterns))
+    }
+
+    /// Removes the ANSI escape sequences, like colors, from the outputs,
+    /// including those of the REPL steps and responses.
+    pub fn strip_ansi(&mut self) -> &mut Self {
+        self.map_outputs(&|text| ansi().replace_all(text, "").into_owned())
+    }
+
+    /// Replaces the text of each output, including those of the REPL steps
+    /// and responses, with the result of the function.
+    fn map_outputs(&mut self, f: &dyn Fn(&str) -> String) -> &mut Self {
+        let steps = self.repl.iter_mut().flat_map(|r| r.steps.iter_mut());
+        for output in self
+            .outputs
+            .iter_mut()
+            .chain(steps.flat_map(|s| s.outputs.iter_mut()))
+            .chain(self.responses.iter_mut().flat_map(|r| r.outputs.iter_mut()))
+        {
+            output.map_text(f);
+        }
+        self
+    }
+
+    /// Sets the most lines displayed for each output, if different from the
+    /// --max-output-lines of the recording.
+    pub fn set_max_output_lines(&mut self, m: Option<usize>) -> &mut Self {
+        self.max_output_lines = m;
+        self
+    }
+
+    pub fn max_output_lines(&self) -> Option<usize> {
+        self.max_output_lines
+    }
+
+    pub fn secret(&self) -> Option<&Secret> {
+        self.secret.as_ref()
+    }
+
+    /// Continues the command with the steps of an interactive program.
+    pub fn set_repl(&mut self, r: Option<Repl>) -> &mut Self {
+        self.repl = r;
+        self
+    }
+
+    pub fn repl(&self) -> Option<&Repl> {
+        self.repl.as_ref()
+    }
+
+    /// Sets the responses typed when the command prompts for them.
+    pub fn set_responses(&mut self, r: Vec<Response>) -> &mut Self {
+        self.responses = r;
+        self
+    }
+
+    pub fn responses(&self) -> &[Response] {
+        &self.responses
+    }
+
+    /// Adds the outputs of the executed command, which are split at the
+    /// prompts of the responses. The output up to the line of the first
+    /// prompt is an output of the command, and the output after each response
+    /// up to the line of the next prompt is an output of the response.
+    pub fn append_responded(&mut self, outputs: &mut Vec<String>) -> Result<&mut Self> {
+        if self.responses.is_empty() {
+            return Ok(self.append(outputs));
+        }
+        let mut next = 0;
+        for output in outputs.drain(..) {
+            let mut rest = output.as_str();
+            while let Some(response) = self.responses.get(next) {
+                let start = match rest.find(&response.expect) {
+                    Some(start) => start,
+                    None => break,
+                };
+                let end = start + response.expect.len();
+                let end = rest.len() - rest[end..].trim_start_matches([' ', '\t']).len();
+                let line = rest[..start].rfind('\n').map_or(0, |i| i + 1);
+                self.add_responded(next, &rest[..line]);
+                self.responses[next].prompt = Some(rest[line..end].to_owned());
+                rest = &rest[end..];
+                next += 1;
+            }
+            self.add_responded(next, rest);
+        }
+        if let Some(response) = self.responses.get(next) {
+            return Err(Error::Execution(format!(
+                "The output does not contain the prompt '{}'",
+                response.expect
+            )));
+        }
+        Ok(self)
+    }
+
+    /// Adds a non-empty output after the previous response, or to the command
+    /// before the first response.
+    fn add_responded(&mut self, next: usize, output: &str) {
+        if output.is_empty() {
+            return;
+        }
+        let outputs = match next.checked_sub(1) {
+            Some(previous) => &mut self.responses[previous].outputs,
+            None => &mut self.outputs,
+        };
+        outputs.push(Output::from(output));
+    }
+
     pub fn prompt(&self) -> &str {
         &self.prompt
     }
 
     pub fn add(&mut self, output: &str) -> &mut Self {
-        self.outputs.push(output.to_owned());
+        self.outputs.push(Output::from(output));
+        self
+    }
+
+    pub fn add_output(&mut self, output: Output) -> &mut Self {
+        self.outputs.push(output);
         self
     }
 
     pub fn append(&mut self, outputs: &mut Vec<String>) -> &mut Self {
-        self.outputs.append(outputs);
+        self.outputs.extend(outputs.drain(..).map(Output::from));
         self
     }
 
@@ -242,12 +1395,62 @@ impl Command {
         &self.input
     }
 
-    pub fn outputs(&self) -> &Vec<String> {
+    /// The input without any tab completion markers, as executed.
+    pub fn text(&self) -> String {
+        strip_tabs(&self.input)
+    }
+
+    /// The parts of the input typed by hand and completed with a tab.
+    pub fn parts(&self) -> Vec<InputPart<'_>> {
+        let mut parts = Vec::new();
+        let mut rest = self.input.as_str();
+        while let Some((start, marker)) = TAB_MARKERS
+            .iter()
+            .filter_map(|m| rest.find(m).map(|i| (i, m)))
+            .min()
+        {
+            if start != 0 {
+                parts.push(InputPart::Typed(&rest[..start]));
+            }
+            rest = &rest[start + marker.len()..];
+            let end = rest
+                .find(|c: char| c.is_whitespace())
+                .into_iter()
+                .chain(TAB_MARKERS.iter().filter_map(|m| rest.find(m)))
+                .min()
+                .unwrap_or(rest.len());
+            if end != 0 {
+                parts.push(InputPart::Completed(&rest[..end]));
+            }
+            rest = &rest[end..];
+        }
+        if !rest.is_empty() {
+            parts.push(InputPart::Typed(rest));
+        }
+        parts
+    }
+
+    pub fn outputs(&self) -> &[Output] {
         &self.outputs
     }
 
-    pub fn into_outputs(self) -> Vec<String> {
+    /// The outputs, including those after the responses and of the REPL
+    /// steps.
+    fn outputs_mut(&mut self) -> impl Iterator<Item = &mut Output> {
         self.outputs
+            .iter_mut()
+            .chain(self.responses.iter_mut().flat_map(|r| r.outputs.iter_mut()))
+            .chain(
+                self.repl
+                    .iter_mut()
+                    .flat_map(|repl| repl.steps.iter_mut())
+                    .flat_map(|step| step.outputs.iter_mut()),
+            )
+    }
+
+    /// The outputs as they appear after they are done.
+    pub fn into_outputs(self) -> Vec<String> {
+        self.outputs.iter().map(Output::text).collect()
     }
 }
 
@@ -255,9 +1458,23 @@ impl From<String> for Command {
     fn from(s: String) -> Self {
         Self {
             comment: None,
+            comment_delay: None,
             prompt: String::from(DEFAULT_PROMPT),
             input: s,
             outputs: Vec::new(),
+            width: None,
+            height: None,
+            persona: None,
+            remote: None,
+            cwd: None,
+            retries: None,
+            paste: false,
+            interrupt: false,
+            status: None,
+            max_output_lines: None,
+            secret: None,
+            repl: None,
+            responses: Vec::new(),
         }
     }
 }
@@ -266,9 +1483,330 @@ impl<'a> From<&'a str> for Command {
     fn from(s: &'a str) -> Self {
         Self {
             comment: None,
+            comment_delay: None,
             prompt: String::from(DEFAULT_PROMPT),
             input: s.to_owned(),
             outputs: Vec::new(),
+            width: None,
+            height: None,
+            persona: None,
+            remote: None,
+            cwd: None,
+            retries: None,
+            paste: false,
+            interrupt: false,
+            status: None,
+            max_output_lines: None,
+            secret: None,
+            repl: None,
+            responses: Vec::new(),
         }
     }
 }
+
+/// Constructs a command in one expression, i.e.
+///
+/// ```
+/// use germ::Command;
+///
+/// let command = Command::builder("echo Hello World")
+///     .comment("Greets the world")
+///     .prompt("> ")
+///     .output("Hello World\n")
+///     .build();
+/// assert_eq!(command.prompt(), "> ");
+/// ```
+#[derive(Debug, Clone)]
+pub struct CommandBuilder {
+    command: Command,
+}
+
+impl CommandBuilder {
+    pub fn new(input: &str) -> Self {
+        Self {
+            command: Command::from(input),
+        }
+    }
+
+    pub fn input(mut self, i: &str) -> Self {
+        self.command.set_input(i);
+        self
+    }
+
+    pub fn prompt(mut self, p: &str) -> Self {
+        self.command.set_prompt(p);
+        self
+    }
+
+    pub fn comment(mut self, c: &str) -> Self {
+        self.command.set_comment(Some(c));
+        self
+    }
+
+    pub fn comment_delay(mut self, d: Delay) -> Self {
+        self.command.set_comment_delay(Some(d));
+        self
+    }
+
+    /// Adds an output, printed after the outputs already added.
+    pub fn output(mut self, o: impl Into<Output>) -> Self {
+        self.command.add_output(o.into());
+        self
+    }
+
+    pub fn outputs<I, O>(mut self, outputs: I) -> Self
+    where
+        I: IntoIterator<Item = O>,
+        O: Into<Output>,
+    {
+        for output in outputs {
+            self.command.add_output(output.into());
+        }
+        self
+    }
+
+    pub fn width(mut self, w: usize) -> Self {
+        self.command.set_width(Some(w));
+        self
+    }
+
+    pub fn height(mut self, h: usize) -> Self {
+        self.command.set_height(Some(h));
+        self
+    }
+
+    pub fn persona(mut self, p: &str) -> Self {
+        self.command.set_persona(Some(p));
+        self
+    }
+
+    pub fn remote(mut self, r: &str) -> Self {
+        self.command.set_remote(Some(r));
+        self
+    }
+
+    pub fn cwd(mut self, c: &str) -> Self {
+        self.command.set_cwd(Some(c));
+        self
+    }
+
+    pub fn retries(mut self, r: usize) -> Self {
+        self.command.set_retries(Some(r));
+        self
+    }
+
+    pub fn paste(mut self, p: bool) -> Self {
+        self.command.set_paste(p);
+        self
+    }
+
+    pub fn interrupt(mut self, i: bool) -> Self {
+        self.command.set_interrupt(i);
+        self
+    }
+
+    pub fn status(mut self, s: i32) -> Self {
+        self.command.set_status(Some(s));
+        self
+    }
+
+    pub fn max_output_lines(mut self, m: usize) -> Self {
+        self.command.set_max_output_lines(Some(m));
+        self
+    }
+
+    pub fn secret(mut self, s: Secret) -> Self {
+        self.command.set_secret(Some(s));
+        self
+    }
+
+    pub fn repl(mut self, r: Repl) -> Self {
+        self.command.set_repl(Some(r));
+        self
+    }
+
+    /// Adds a response, typed after the responses already added.
+    pub fn response(mut self, r: Response) -> Self {
+        self.command.responses.push(r);
+        self
+    }
+
+    pub fn build(self) -> Command {
+        self.command
+    }
+}
+
+impl From<CommandBuilder> for Command {
+    fn from(b: CommandBuilder) -> Self {
+        b.build()
+    }
+}
+
+/// The simulated state of the shell used to resolve the segments of a prompt
+/// template, i.e. "{cwd} ({git_branch}) $ ".
+///
+/// The segments are:
+///
+/// - `{cwd}`: the working directory, starting at "~" and changed by `cd`.
+/// - `{dir}`: the last component of the working directory.
+/// - `{git_branch}`: the branch, starting at "main" and changed by
+///   `git checkout` and `git switch`.
+/// - `{status}`: the exit code of the previous command, starting at 0.
+/// - `{status_mark}`: a red "✗ " if the previous command failed, otherwise
+///   nothing.
+///
+/// Any other text, including unknown segments, is displayed as it is.
+#[derive(Debug, Clone)]
+struct Shell {
+    cwd: String,
+    previous: String,
+    git_branch: String,
+    status: i32,
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Self {
+            cwd: String::from(DEFAULT_CWD),
+            previous: String::from(DEFAULT_CWD),
+            git_branch: String::from(DEFAULT_GIT_BRANCH),
+            status: 0,
+        }
+    }
+}
+
+impl Shell {
+    fn resolve(&self, template: &str) -> String {
+        let dir = match self.cwd.rsplit('/').next() {
+            Some("") | None => "/",
+            Some(dir) => dir,
+        };
+        template
+            .replace("{cwd}", &self.cwd)
+            .replace("{dir}", dir)
+            .replace("{git_branch}", &self.git_branch)
+            .replace("{status}", &self.status.to_string())
+            .replace(
+                "{status_mark}",
+                if self.status == 0 { "" } else { STATUS_MARK },
+            )
+    }
+
+    /// Updates the state for the commands of the input, which are separated
+    /// by new lines, `;`, `&&`, or `||`.
+    fn run(&mut self, input: &str) {
+        for part in input.split(['\n', ';', '&', '|']) {
+            let words = part.split_whitespace().collect::<Vec<&str>>();
+            match words.as_slice() {
+                ["cd"] => self.cd("~"),
+                ["cd", target, ..] => self.cd(target),
+                ["git", "checkout" | "switch", args @ ..] if !args.contains(&"--") => {
+                    if let Some(branch) = args.iter().rev().find(|a| !a.starts_with('-')) {
+                        self.git_branch = (*branch).to_owned();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Changes to a directory relative to the starting directory, like a
+    /// command with its own working directory.
+    fn enter(&mut self, dir: &str) {
+        let previous = std::mem::replace(&mut self.cwd, String::from(DEFAULT_CWD));
+        self.cd(dir);
+        self.previous = previous;
+    }
+
+    fn cd(&mut self, target: &str) {
+        let cwd = if target == "-" {
+            self.previous.clone()
+        } else {
+            let (root, base) = if target == "~" || target.starts_with("~/") {
+                ("~", "")
+            } else if target.starts_with('/') {
+                ("", "")
+            } else if self.cwd.starts_with('~') {
+                ("~", self.cwd.trim_start_matches('~'))
+            } else {
+                ("", self.cwd.as_str())
+            };
+            let mut components = Vec::new();
+            for component in base
+                .split('/')
+                .chain(target.trim_start_matches('~').split('/'))
+            {
+                match component {
+                    "" | "." => {}
+                    ".." => {
+                        components.pop();
+                    }
+                    c => components.push(c),
+                }
+            }
+            match (root, components.is_empty()) {
+                ("~", true) => String::from("~"),
+                (_, true) => String::from("/"),
+                _ => format!("{}/{}", root, components.join("/")),
+            }
+        };
+        self.previous = std::mem::replace(&mut self.cwd, cwd);
+    }
+}
+
+fn remote_prompt(remote: &str) -> String {
+    format!("{}:~$ ", remote)
+}
+
+fn exit_command(remote: &str) -> Command {
+    let host = remote.rsplit('@').next().unwrap_or(remote);
+    let mut exit = Command::from("exit");
+    exit.set_prompt(&remote_prompt(remote));
+    exit.add("logout");
+    exit.add(&format!("Connection to {} closed.", host));
+    exit
+}
+
+/// Replaces the `$VAR` and `${VAR}` references in an input with the values of
+/// the environment variables. A reference to an unset variable is kept.
+#[cfg(feature = "exec")]
+pub fn expand_env(input: &str) -> String {
+    let reference = Regex::new(r"\$(?:\{([A-Za-z_][A-Za-z0-9_]*)\}|([A-Za-z_][A-Za-z0-9_]*))")
+        .expect("Valid regex");
+    reference
+        .replace_all(input, |captures: &regex::Captures| {
+            let name = captures.get(1).or_else(|| captures.get(2)).unwrap();
+            std::env::var(name.as_str()).unwrap_or_else(|_| captures[0].to_owned())
+        })
+        .into_owned()
+}
+
+/// Removes the tab completion markers from an input.
+pub fn strip_tabs(input: &str) -> String {
+    TAB_MARKERS
+        .iter()
+        .fold(input.to_owned(), |input, marker| input.replace(marker, ""))
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+/// The expression of the escape sequences, which is compiled once since it is
+/// matched against every output line.
+pub(crate) fn ansi() -> &'static Regex {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    ANSI.get_or_init(|| Regex::new(ANSI_ESCAPE).expect("Valid regex"))
+}
+
+/// A step of the SplitMix64 generator, which spreads similar seeds apart.
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -14,8 +14,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "exec")]
+use std::io::Write;
 
-use crate::sequence::Sequence;
+#[cfg(feature = "exec")]
+use crate::error::Result;
+#[cfg(feature = "exec")]
+use crate::json;
+use crate::sequence::{Output, Sequence};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Command {
@@ -26,7 +32,7 @@ pub struct Command {
 impl From<crate::sequence::Command> for Command {
     fn from(c: crate::sequence::Command) -> Self {
         Self {
-            input: c.input().to_owned(),
+            input: c.text(),
             output: c.into_outputs(),
         }
     }
@@ -35,8 +41,8 @@ impl From<crate::sequence::Command> for Command {
 impl<'a> From<&'a crate::sequence::Command> for Command {
     fn from(c: &'a crate::sequence::Command) -> Self {
         Self {
-            input: c.input().to_owned(),
-            output: c.outputs().clone(),
+            input: c.text(),
+            output: c.outputs().iter().map(Output::text).collect(),
         }
     }
 }
@@ -70,3 +76,16 @@ impl From<Vec<Command>> for Sequence {
         )
     }
 }
+
+/// Reads the commands of a TermSheets JSON document.
+#[cfg(feature = "exec")]
+pub fn from_slice(buffer: &[u8]) -> Result<Vec<Command>> {
+    json::from_slice(buffer)
+}
+
+/// Writes the commands of the sequence as a TermSheets JSON document.
+#[cfg(feature = "exec")]
+pub fn write_to<W: Write>(sequence: &Sequence, writer: W) -> Result<()> {
+    serde_json::to_writer(writer, &Vec::<Command>::from(sequence))?;
+    Ok(())
+}
@@ -0,0 +1,261 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::Theme;
+use crate::error::Error;
+#[cfg(feature = "fs")]
+use crate::json;
+use anyhow::{bail, Result};
+#[cfg(feature = "fs")]
+use serde::Deserialize;
+#[cfg(feature = "fs")]
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "cli")]
+use structopt::StructOpt;
+#[cfg(feature = "cli")]
+use strum::VariantNames;
+use strum::{Display, EnumString, EnumVariantNames};
+
+#[derive(Debug, Clone, Copy, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ThemePresets {
+    Dracula,
+    SolarizedDark,
+    SolarizedLight,
+    Monokai,
+    Gruvbox,
+    Nord,
+}
+
+impl ThemePresets {
+    pub fn theme(&self) -> Theme {
+        let (foreground, background, palette) = match self {
+            Self::Dracula => (
+                "#f8f8f2",
+                "#282a36",
+                "#21222c:#ff5555:#50fa7b:#f1fa8c:#bd93f9:#ff79c6:#8be9fd:#f8f8f2:\
+                 #6272a4:#ff6e6e:#69ff94:#ffffa5:#d6acff:#ff92df:#a4ffff:#ffffff",
+            ),
+            Self::SolarizedDark => (
+                "#839496",
+                "#002b36",
+                "#073642:#dc322f:#859900:#b58900:#268bd2:#d33682:#2aa198:#eee8d5:\
+                 #002b36:#cb4b16:#586e75:#657b83:#839496:#6c71c4:#93a1a1:#fdf6e3",
+            ),
+            Self::SolarizedLight => (
+                "#657b83",
+                "#fdf6e3",
+                "#073642:#dc322f:#859900:#b58900:#268bd2:#d33682:#2aa198:#eee8d5:\
+                 #002b36:#cb4b16:#586e75:#657b83:#839496:#6c71c4:#93a1a1:#fdf6e3",
+            ),
+            Self::Monokai => (
+                "#f8f8f2",
+                "#272822",
+                "#272822:#f92672:#a6e22e:#f4bf75:#66d9ef:#ae81ff:#a1efe4:#f8f8f2:\
+                 #75715e:#f92672:#a6e22e:#f4bf75:#66d9ef:#ae81ff:#a1efe4:#f9f8f5",
+            ),
+            Self::Gruvbox => (
+                "#ebdbb2",
+                "#282828",
+                "#282828:#cc241d:#98971a:#d79921:#458588:#b16286:#689d6a:#a89984:\
+                 #928374:#fb4934:#b8bb26:#fabd2f:#83a598:#d3869b:#8ec07c:#ebdbb2",
+            ),
+            Self::Nord => (
+                "#d8dee9",
+                "#2e3440",
+                "#3b4252:#bf616a:#a3be8c:#ebcb8b:#81a1c1:#b48ead:#88c0d0:#e5e9f0:\
+                 #4c566a:#bf616a:#a3be8c:#ebcb8b:#81a1c1:#b48ead:#8fbcbb:#eceff4",
+            ),
+        };
+        Theme {
+            foreground: foreground.to_owned(),
+            background: background.to_owned(),
+            palette: palette.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(StructOpt))]
+pub struct ThemeOptions {
+    /// A built-in color theme for players.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            possible_values = ThemePresets::VARIANTS,
+            case_insensitive = true,
+            value_name = "name",
+            env = "GERM_THEME"
+        )
+    )]
+    pub theme: Option<ThemePresets>,
+
+    /// A color theme for players from a JSON or TOML file.
+    ///
+    /// The file has "fg" and "bg" colors and a "palette" of 8 or 16 colors,
+    /// either as a list or a colon-separated string. A file with the .toml
+    /// extension is read as TOML, otherwise as JSON.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(
+            long,
+            value_name = "file",
+            parse(from_os_str),
+            conflicts_with = "theme",
+            env = "GERM_THEME_FILE"
+        )
+    )]
+    pub theme_file: Option<PathBuf>,
+
+    /// The foreground color of the theme, i.e. "#rrggbb".
+    ///
+    /// This overrides the foreground color of the --theme preset.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long, value_name = "color", parse(try_from_str = parse_color))
+    )]
+    pub theme_fg: Option<String>,
+
+    /// The background color of the theme, i.e. "#rrggbb".
+    ///
+    /// This overrides the background color of the --theme preset.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long, value_name = "color", parse(try_from_str = parse_color))
+    )]
+    pub theme_bg: Option<String>,
+
+    /// The colon-separated list of 8 or 16 colors of the theme.
+    ///
+    /// This overrides the palette of the --theme preset.
+    #[cfg_attr(
+        feature = "cli",
+        structopt(long, value_name = "colors", parse(try_from_str = parse_palette))
+    )]
+    pub theme_palette: Option<String>,
+}
+
+impl ThemeOptions {
+    /// The theme from the preset and overrides, if any are used.
+    pub fn to_theme(&self) -> crate::Result<Option<Theme>> {
+        let mut theme = match (self.theme, &self.theme_file) {
+            (Some(preset), _) => preset.theme(),
+            (None, Some(theme_file)) => read_theme_file(theme_file).map_err(|err| {
+                Error::Invalid(format!(
+                    "Failed to read '{}': {:#}",
+                    theme_file.display(),
+                    err
+                ))
+            })?,
+            (None, None)
+                if self.theme_fg.is_none()
+                    && self.theme_bg.is_none()
+                    && self.theme_palette.is_none() =>
+            {
+                return Ok(None)
+            }
+            (None, None) => match (&self.theme_fg, &self.theme_bg, &self.theme_palette) {
+                (Some(fg), Some(bg), Some(palette)) => Theme {
+                    foreground: fg.clone(),
+                    background: bg.clone(),
+                    palette: palette.clone(),
+                },
+                _ => {
+                    return Err(Error::Invalid(String::from(
+                        "A theme needs a foreground, background, and palette, use --theme or \
+                         all of --theme-fg, --theme-bg, and --theme-palette",
+                    )))
+                }
+            },
+        };
+        if let Some(fg) = &self.theme_fg {
+            theme.foreground = fg.clone();
+        }
+        if let Some(bg) = &self.theme_bg {
+            theme.background = bg.clone();
+        }
+        if let Some(palette) = &self.theme_palette {
+            theme.palette = palette.clone();
+        }
+        Ok(Some(theme))
+    }
+}
+
+#[cfg(feature = "fs")]
+#[derive(Debug, Deserialize)]
+struct ThemeFile {
+    #[serde(alias = "foreground")]
+    fg: String,
+
+    #[serde(alias = "background")]
+    bg: String,
+
+    palette: Palette,
+}
+
+#[cfg(feature = "fs")]
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Palette {
+    Joined(String),
+    Colors(Vec<String>),
+}
+
+#[cfg(feature = "fs")]
+fn read_theme_file(path: &Path) -> Result<Theme> {
+    let content = fs::read(path)?;
+    let file: ThemeFile = if path.extension().is_some_and(|e| e == "toml") {
+        toml::from_slice(&content)?
+    } else {
+        json::from_slice(&content)?
+    };
+    let palette = match file.palette {
+        Palette::Joined(p) => p,
+        Palette::Colors(c) => c.join(":"),
+    };
+    Ok(Theme {
+        foreground: parse_color(&file.fg)?,
+        background: parse_color(&file.bg)?,
+        palette: parse_palette(&palette)?,
+    })
+}
+
+#[cfg(not(feature = "fs"))]
+fn read_theme_file(_path: &Path) -> Result<Theme> {
+    bail!("Reading theme files requires the \"fs\" feature")
+}
+
+#[cfg(any(feature = "cli", feature = "fs"))]
+fn parse_color(src: &str) -> Result<String> {
+    let hex = src.strip_prefix('#').unwrap_or_default();
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("'{}' is not a color in the #rrggbb format", src);
+    }
+    Ok(src.to_owned())
+}
+
+#[cfg(any(feature = "cli", feature = "fs"))]
+fn parse_palette(src: &str) -> Result<String> {
+    let colors = src
+        .split(':')
+        .map(parse_color)
+        .collect::<Result<Vec<String>>>()?;
+    if colors.len() != 8 && colors.len() != 16 {
+        bail!("The palette has {} colors, expected 8 or 16", colors.len());
+    }
+    Ok(src.to_owned())
+}
@@ -0,0 +1,116 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::{self, Asciicast, CTRL_C_ECHO};
+use crate::captions::{self, SECONDS_IN_AN_HOUR, SECONDS_IN_A_MINUTE};
+use crate::sequence::{self, Command, Sequence};
+use anyhow::Result;
+use regex::Regex;
+use std::io::Write;
+
+/// Writes a plain text transcript of the sequence, i.e. for screen readers
+/// and search engines.
+///
+/// Each command starts with the time at which it is displayed in the
+/// asciicast, i.e. "[01:05] $ ls", after its comment, if any. The outputs are
+/// written as they appear when done, and the prompts and outputs are written
+/// without ANSI escape sequences or animations. A secret is never written.
+pub fn write_to<W: Write>(
+    asciicast: &mut Asciicast,
+    sequence: &Sequence,
+    mut writer: W,
+) -> Result<()> {
+    let ansi = sequence::ansi();
+    let mut sequence = sequence.clone();
+    sequence.strip_ansi();
+    for (i, timed) in captions::timed(asciicast, &sequence).iter().enumerate() {
+        if i != 0 {
+            writeln!(writer)?;
+        }
+        if let Some(comment) = timed.command.comment() {
+            for line in comment.lines() {
+                writeln!(writer, "{}", line)?;
+            }
+        }
+        let stamp = format!("[{}] ", timestamp(timed.start));
+        write_command(&mut writer, ansi, &timed.command, &timed.prompt, &stamp)?;
+    }
+    Ok(())
+}
+
+/// Writes the input after the stamp and prompt, followed by the outputs,
+/// responses, and REPL steps of the command. The escape sequences of the
+/// prompts, like colors, are removed.
+fn write_command<W: Write>(
+    writer: &mut W,
+    ansi: &Regex,
+    command: &Command,
+    prompt: &str,
+    stamp: &str,
+) -> Result<()> {
+    let input = command.text();
+    let mut lines = input.lines();
+    writeln!(
+        writer,
+        "{}{}{}",
+        stamp,
+        ansi.replace_all(prompt, ""),
+        lines.next().unwrap_or_default()
+    )?;
+    for line in lines {
+        writeln!(writer, "{}{}", " ".repeat(stamp.len()), line)?;
+    }
+    if let Some(secret) = command.secret() {
+        writeln!(writer, "{}", ansi.replace_all(&secret.prompt, ""))?;
+    }
+    for output in command.outputs() {
+        let text = output.text();
+        for line in text.lines() {
+            writeln!(writer, "{}", asciicast::displayed(line))?;
+        }
+    }
+    for response in command.responses() {
+        let response = response.command();
+        write_command(writer, ansi, &response, response.prompt(), "")?;
+    }
+    if let Some(repl) = command.repl() {
+        for step in repl.iter() {
+            write_command(writer, ansi, &step, &repl.prompt, "")?;
+        }
+    }
+    if command.interrupt() {
+        write!(writer, "{}", CTRL_C_ECHO.replace('\r', ""))?;
+    }
+    Ok(())
+}
+
+/// The time as minutes and seconds, i.e. "01:05", with the hours if needed.
+fn timestamp(seconds: f64) -> String {
+    let secs = seconds as u64;
+    if secs >= SECONDS_IN_AN_HOUR {
+        format!(
+            "{}:{:02}:{:02}",
+            secs / SECONDS_IN_AN_HOUR,
+            secs % SECONDS_IN_AN_HOUR / SECONDS_IN_A_MINUTE,
+            secs % SECONDS_IN_A_MINUTE
+        )
+    } else {
+        format!(
+            "{:02}:{:02}",
+            secs / SECONDS_IN_A_MINUTE,
+            secs % SECONDS_IN_A_MINUTE
+        )
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright (C) 2021  Christopher R. Field
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::asciicast::{Event, Header};
+use crate::recorder::Sink;
+use anyhow::{anyhow, bail, Context, Result};
+use sha1::{Digest, Sha1};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+const SCHEME: &str = "ws://";
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const RANDOM_SOURCE: &str = "/dev/urandom";
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const CLOSE_NORMAL: u16 = 1000;
+const MAX_RESPONSE_LENGTH: usize = 8192;
+
+/// A minimal WebSocket client for streaming events to a live player.
+///
+/// Only unencrypted `ws://` URLs are supported. Each asciicast line, i.e. the
+/// header or an event, is sent as a text message, which is the live protocol
+/// of asciinema-player's WebSocket driver. The server is not part of germ, so
+/// the player connects to one that relays the messages.
+#[derive(Debug)]
+pub struct WebSocket {
+    stream: TcpStream,
+    random: File,
+}
+
+impl WebSocket {
+    /// Connects to the URL and completes the opening handshake.
+    pub fn connect(url: &str) -> Result<Self> {
+        let (host, path) = url
+            .strip_prefix(SCHEME)
+            .map(|rest| match rest.find('/') {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, "/"),
+            })
+            .filter(|(host, _)| !host.is_empty())
+            .ok_or_else(|| anyhow!("'{}' is not a {}host[:port][/path] URL", url, SCHEME))?;
+        let address = if host.contains(':') {
+            host.to_owned()
+        } else {
+            format!("{}:80", host)
+        };
+        let stream = TcpStream::connect(&address)
+            .with_context(|| format!("Failed to connect to '{}'", url))?;
+        let random = File::open(RANDOM_SOURCE)
+            .with_context(|| format!("Failed to open '{}'", RANDOM_SOURCE))?;
+        let mut websocket = Self { stream, random };
+        websocket
+            .handshake(host, path)
+            .with_context(|| format!("Failed to open a WebSocket to '{}'", url))?;
+        Ok(websocket)
+    }
+
+    pub fn send_text(&mut self, text: &str) -> Result<()> {
+        self.send(OPCODE_TEXT, text.as_bytes())
+    }
+
+    /// Sends the closing frame. The connection is not used afterwards.
+    pub fn close(&mut self) -> Result<()> {
+        self.send(OPCODE_CLOSE, &CLOSE_NORMAL.to_be_bytes())
+    }
+
+    fn handshake(&mut self, host: &str, path: &str) -> Result<()> {
+        let mut nonce = [0; 16];
+        self.random.read_exact(&mut nonce)?;
+        let key = base64(&nonce);
+        write!(
+            self.stream,
+            "GET {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\n\
+             Sec-WebSocket-Version: 13\r\n\r\n",
+            path, host, key
+        )?;
+        self.stream.flush()?;
+        let mut response = Vec::new();
+        let mut byte = [0; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() > MAX_RESPONSE_LENGTH {
+                bail!("The handshake response is too long");
+            }
+            self.stream.read_exact(&mut byte)?;
+            response.push(byte[0]);
+        }
+        let response = String::from_utf8_lossy(&response);
+        let status = response.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("101") {
+            bail!("Unexpected handshake response '{}'", status);
+        }
+        // The server proves it understood the handshake by hashing the key.
+        let expected = base64(&Sha1::digest(format!("{}{}", key, ACCEPT_GUID)));
+        let accept = response.lines().skip(1).find_map(|line| {
+            line.split_once(':')
+                .filter(|(name, _)| name.trim().eq_ignore_ascii_case("Sec-WebSocket-Accept"))
+                .map(|(_, value)| value.trim())
+        });
+        match accept {
+            Some(accept) if accept == expected => Ok(()),
+            Some(accept) => bail!(
+                "Unexpected Sec-WebSocket-Accept '{}', expected '{}'",
+                accept,
+                expected
+            ),
+            None => bail!("Missing the Sec-WebSocket-Accept header"),
+        }
+    }
+
+    fn send(&mut self, opcode: u8, payload: &[u8]) -> Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        // Frames from a client must be masked with an unpredictable key.
+        let mut mask = [0; 4];
+        self.random.read_exact(&mut mask)?;
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+        self.stream.write_all(&frame)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+impl Sink for WebSocket {
+    fn header(&mut self, header: &Header) -> Result<()> {
+        self.send_text(&serde_json::to_string(header)?)
+    }
+
+    fn event(&mut self, mut event: Event) -> Result<()> {
+        let mut line = Vec::new();
+        event.write_to(&mut line)?;
+        self.send_text(std::str::from_utf8(&line)?.trim_end())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.close()
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -1,7 +1,12 @@
 use assert_cmd::Command;
 use assert_fs::prelude::*;
 use assert_fs::TempDir;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use predicates::prelude::*;
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
 
 const TEST_SHELL: &str = "/bin/sh";
 const TEST_TERM: &str = "xterm-256color";
@@ -27,7 +32,7 @@ const HELLO_WORLD_ASCIICAST_OUTPUT: &str = r#"{"version":2,"width":80,"height":2
 [2.16,"o","Hello World\r\n"]
 [3.16,"o",""]
 "#;
-const HELLO_WORLD_GERM_OUTPUT: &str = r#"{"version":1,"timings":{"begin":0.0,"end":1.0,"type_start":750,"type_char":35,"type_submit":350,"output_line":500,"speed":1.0},"commands":[{"prompt":"$ ","input":"echo Hello World","outputs":["Hello World\n"]}]}"#;
+const HELLO_WORLD_GERM_OUTPUT: &str = r#"{"version":2,"timings":{"begin":0,"end":1000,"type_start":750,"type_char":35,"type_submit":350,"output_line":500,"speed":1.0},"commands":[{"prompt":"$ ","input":"echo Hello World","outputs":["Hello World\n"]}]}"#;
 
 fn test_cmd() -> Command {
     let mut cmd = Command::cargo_bin("germ").unwrap();
@@ -39,13 +44,19 @@ fn test_cmd() -> Command {
 fn input_arg_only_works() {
     let mut cmd = test_cmd();
     let assert = cmd.arg("echo Hello World").assert();
-    assert.success().stdout(HELLO_WORLD_ASCIICAST_OUTPUT);
+    assert
+        .success()
+        .stdout(HELLO_WORLD_ASCIICAST_OUTPUT.replacen(
+            r#""height":24,"#,
+            r#""height":24,"command":"echo Hello World","#,
+            1,
+        ));
 }
 
 #[test]
 fn input_arg_with_one_outputs_arg_works() {
     let mut cmd = test_cmd();
-    let assert = cmd.args(&["echo Hello World", "Hello World"]).assert();
+    let assert = cmd.args(["echo Hello World", "Hello World"]).assert();
     assert.success().stdout(HELLO_WORLD_ASCIICAST_OUTPUT);
 }
 
@@ -57,7 +68,7 @@ fn output_file_works() {
     let assert = cmd
         .arg("-o")
         .arg(output_file.path())
-        .args(&["echo Hello World", "Hello World"])
+        .args(["echo Hello World", "Hello World"])
         .assert();
     assert.success();
     output_file.assert(HELLO_WORLD_ASCIICAST_OUTPUT);
@@ -106,3 +117,2200 @@ fn germ_timestamp_now_works() {
         .success()
         .stdout(predicate::str::contains("timestamp"));
 }
+
+#[test]
+fn custom_event_kind_round_trips() {
+    let event: germ::Event = serde_json::from_str(r#"[1.5,"x","data"]"#).unwrap();
+    assert_eq!(event.1, germ::EventKind::Custom(String::from("x")));
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"[1.5,"x","data"]"#
+    );
+}
+
+#[test]
+fn validate_accepts_valid_germ_input() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .arg("--validate")
+        .write_stdin(HELLO_WORLD_GERM_OUTPUT)
+        .assert();
+    assert.success().stdout("");
+}
+
+#[test]
+fn validate_reports_missing_field() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .arg("--validate")
+        .write_stdin(r#"{"version":1,"timings":{"begin":0.0,"end":1.0,"type_start":750,"type_char":35,"type_submit":350,"output_line":500,"speed":1.0},"commands":[{"prompt":"$ ","outputs":[]}]}"#)
+        .assert();
+    assert.failure().stdout("").stderr(predicate::str::contains(
+        "commands[0]: missing field `input`",
+    ));
+}
+
+#[test]
+fn validate_reports_asciicast_event_line() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--validate", "-I", "asciicast"])
+        .write_stdin(
+            "{\"version\":2,\"width\":80,\"height\":24}\n[0.0,\"o\",\"$ \"]\n[0.5,\"o\",3]\n",
+        )
+        .assert();
+    assert
+        .failure()
+        .stderr(predicate::str::contains("line 3, column 10"));
+}
+
+#[test]
+fn convert_recursive_mirrors_directories() {
+    let tmp_dir = TempDir::new().unwrap();
+    tmp_dir
+        .child("demos/nested/hello.json")
+        .write_str(HELLO_WORLD_GERM_OUTPUT)
+        .unwrap();
+    tmp_dir
+        .child("demos/notes.md")
+        .write_str("ignored")
+        .unwrap();
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .current_dir(tmp_dir.path())
+        .args(["convert", "--recursive", "demos", "--to", "asciicast"])
+        .args(["--out-dir", "site"])
+        .assert();
+    assert.success();
+    tmp_dir
+        .child("site/nested/hello.cast")
+        .assert(HELLO_WORLD_ASCIICAST_OUTPUT);
+    tmp_dir
+        .child("site/notes.cast")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn convert_walks_compressed_files() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input_file = tmp_dir.child("demos/hello.germ.gz");
+    input_file.touch().unwrap();
+    let mut encoder = GzEncoder::new(
+        std::fs::File::create(input_file.path()).unwrap(),
+        Compression::default(),
+    );
+    encoder
+        .write_all(HELLO_WORLD_GERM_OUTPUT.as_bytes())
+        .unwrap();
+    encoder.finish().unwrap();
+    tmp_dir
+        .child("demos/notes.md.gz")
+        .write_str("ignored")
+        .unwrap();
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .current_dir(tmp_dir.path())
+        .args(["convert", "demos", "--to", "asciicast", "--out-dir", "site"])
+        .assert();
+    assert.success();
+    tmp_dir
+        .child("site/hello.cast")
+        .assert(HELLO_WORLD_ASCIICAST_OUTPUT);
+    tmp_dir
+        .child("site/notes.cast")
+        .assert(predicate::path::missing());
+}
+
+#[test]
+fn convert_expands_glob_patterns() {
+    let tmp_dir = TempDir::new().unwrap();
+    for name in ["demos/hello.germ", "demos/goodbye.germ"] {
+        tmp_dir
+            .child(name)
+            .write_str(HELLO_WORLD_GERM_OUTPUT)
+            .unwrap();
+    }
+    let mut cmd = test_cmd();
+    cmd.current_dir(tmp_dir.path())
+        .args([
+            "convert",
+            "demos/*.germ",
+            "--out-dir",
+            "casts",
+            "-O",
+            "asciicast",
+        ])
+        .assert()
+        .success();
+    tmp_dir
+        .child("casts/hello.cast")
+        .assert(HELLO_WORLD_ASCIICAST_OUTPUT);
+    tmp_dir
+        .child("casts/goodbye.cast")
+        .assert(HELLO_WORLD_ASCIICAST_OUTPUT);
+}
+
+#[test]
+fn convert_with_jobs_reports_in_order() {
+    let tmp_dir = TempDir::new().unwrap();
+    for name in ["a", "b", "c", "d"] {
+        tmp_dir
+            .child(format!("demos/{}.germ", name))
+            .write_str(HELLO_WORLD_GERM_OUTPUT)
+            .unwrap();
+    }
+    let mut cmd = test_cmd();
+    cmd.current_dir(tmp_dir.path())
+        .args(["convert", "--jobs", "3", "demos"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{}\n{}\n{}\n{}\n",
+            "demos/a.germ -> demos/a.cast",
+            "demos/b.germ -> demos/b.cast",
+            "demos/c.germ -> demos/c.cast",
+            "demos/d.germ -> demos/d.cast"
+        ));
+    tmp_dir
+        .child("demos/d.cast")
+        .assert(HELLO_WORLD_ASCIICAST_OUTPUT);
+}
+
+#[test]
+fn build_renders_project_demos() {
+    let tmp_dir = TempDir::new().unwrap();
+    tmp_dir
+        .child("germ.toml")
+        .write_str("[render]\nformats = [\"asciicast\"]\n")
+        .unwrap();
+    tmp_dir
+        .child("demos/hello.json")
+        .write_str(&HELLO_WORLD_GERM_OUTPUT.replace(
+            r#""commands""#,
+            r#""render":{"formats":["asciicast","termsheets"]},"commands""#,
+        ))
+        .unwrap();
+    tmp_dir
+        .child("demos/nested/world.germ")
+        .write_str(HELLO_WORLD_GERM_OUTPUT)
+        .unwrap();
+    let mut cmd = test_cmd();
+    let assert = cmd.current_dir(tmp_dir.path()).arg("build").assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("Built 3 file(s) from 2 source(s)"));
+    tmp_dir
+        .child("build/hello.cast")
+        .assert(HELLO_WORLD_ASCIICAST_OUTPUT);
+    tmp_dir
+        .child("build/hello.json")
+        .assert(predicate::path::exists());
+    tmp_dir
+        .child("build/nested/world.cast")
+        .assert(HELLO_WORLD_ASCIICAST_OUTPUT);
+}
+
+#[test]
+fn info_prints_statistics() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .arg("info")
+        .write_stdin(HELLO_WORLD_GERM_OUTPUT)
+        .assert();
+    assert.success().stdout(
+        "Duration: 3.160 s\nCommands: 1\nEvents: 20\nLongest idle: 1.000 s (at 2.160 s)\n\
+         Command durations:\n  1. 2.160 s  echo Hello World\n",
+    );
+}
+
+#[test]
+fn markers_flag_adds_marker_for_comment() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args([
+            "--markers",
+            "-c",
+            "Say hello",
+            "echo Hello World",
+            "Hello World",
+        ])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[0.0,\"m\",\"Say hello\"]\n[0.0,\"o\",\"# Say hello\\r\\n\"]",
+    ));
+}
+
+#[test]
+fn command_width_adds_resize_events() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--command-width", "120", "echo Hello World", "Hello World"])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::starts_with(
+            "{\"version\":2,\"width\":80,\"height\":24,\"env\":{\"SHELL\":\"/bin/sh\",\"TERM\":\"xterm-256color\"}}\n[0.0,\"r\",\"120x24\"]\n",
+        ))
+        .stdout(predicate::str::contains("[2.16,\"r\",\"80x24\"]"));
+}
+
+#[test]
+fn command_height_adds_resize_events() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--command-width", "120", "--command-height", "40"])
+        .args(["echo Hello World", "Hello World"])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("[0.0,\"r\",\"120x40\"]"))
+        .stdout(predicate::str::contains("[2.16,\"r\",\"80x24\"]"));
+}
+
+#[test]
+fn typing_profile_replaces_delays() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args([
+            "--typing-profile",
+            "fast",
+            "echo Hello World",
+            "Hello World",
+        ])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[0.25,\"o\",\"e\"]\n[0.265,\"o\",\"c\"]",
+    ));
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args([
+            "-G",
+            "--typing-profile",
+            "human",
+            "echo Hello World",
+            "Hello World",
+        ])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        r#""type_start":750,"type_char":60,"type_jitter":40,"type_submit":400"#,
+    ));
+}
+
+#[test]
+fn typing_jitter_keeps_characters_in_order() {
+    let mut cmd = test_cmd();
+    let output = cmd
+        .args(["--delay-type-char", "5", "--delay-type-jitter", "40"])
+        .arg("echo abcdefgh")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let typed: String = stdout
+        .lines()
+        .skip(2)
+        .take_while(|line| !line.ends_with(r#""\r\n"]"#))
+        .map(|line| &line[line.find(r#","o",""#).unwrap() + 6..line.len() - 2])
+        .collect();
+    assert_eq!(typed, "echo abcdefgh");
+}
+
+#[test]
+fn typing_unit_word_types_words() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--typing-unit", "word", "echo Hello World", "Hello World"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[0.75,\"o\",\"echo \"]\n[0.925,\"o\",\"Hello \"]\n[1.135,\"o\",\"World\"]\n[2.16,",
+    ));
+}
+
+#[test]
+fn paste_displays_input_at_once() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--paste", "echo Hello World", "Hello World"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[0.75,\"o\",\"echo Hello World\"]\n[1.6,\"o\",\"\\r\\n\"]",
+    ));
+}
+
+#[test]
+fn typing_uses_grapheme_clusters() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args([
+            "--size",
+            "fit",
+            "echo \u{1f44d}\u{1f3fd}e\u{301}",
+            "\u{1f44d}\u{1f3fd}",
+        ])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::starts_with(
+            r#"{"version":2,"width":10,"height":3,"#,
+        ))
+        .stdout(predicate::str::contains(
+            "[0.925,\"o\",\"\u{1f44d}\u{1f3fd}\"]\n[0.96,\"o\",\"e\u{301}\"]\n[1.845,",
+        ));
+}
+
+#[test]
+fn typing_layout_varies_delays() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args([
+            "--typing-layout",
+            "qwerty",
+            "echo Hello World",
+            "Hello World",
+        ])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[0.75,\"o\",\"e\"]\n[0.782,\"o\",\"c\"]",
+    ));
+}
+
+#[test]
+fn tab_marker_completes_word() {
+    let mut cmd = test_cmd();
+    let assert = cmd.arg("echo Hel{tab}lo World").assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(
+            "[0.995,\"o\",\"l\"]\n[1.38,\"o\",\"lo\"]\n[1.415,\"o\",\" \"]",
+        ))
+        .stdout(predicate::str::contains("\"Hello World\\r\\n\"]"));
+}
+
+#[test]
+fn interrupt_cancels_input() {
+    let mut cmd = test_cmd();
+    let assert = cmd.args(["--interrupt", "sleep 100"]).assert();
+    assert.success().stdout(predicate::str::contains(
+        "[1.415,\"o\",\"^C\\r\\n\"]\n[2.415,",
+    ));
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--interrupt", "echo Hello World", "Hello World"])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("[2.66,\"o\",\"^C\\r\\n\"]"));
+}
+
+#[test]
+fn secret_input_is_hidden() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--secret-prompt", "Password: ", "--secret-length", "3"])
+        .args(["sudo ls", "a.txt"])
+        .assert();
+    assert.success().stdout(predicate::str::ends_with(
+        "[1.345,\"o\",\"\\r\\nPassword: \"]\n[2.55,\"o\",\"\\r\\n\"]\n[3.05,\"o\",\"a.txt\\r\\n\"]\n[4.05,\"o\",\"\"]\n",
+    ));
+}
+
+#[test]
+fn heredoc_lines_use_continuation_prompt() {
+    let mut cmd = test_cmd();
+    let assert = cmd.arg("cat <<EOF\nHi\nEOF").assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(
+            "[1.03,\"o\",\"F\"]\n[1.415,\"o\",\"\\r\\n> \"]\n[1.45,\"o\",\"H\"]",
+        ))
+        .stdout(predicate::str::contains("\"Hi\\r\\n\"]"));
+}
+
+#[test]
+fn repl_steps_use_program_prompt() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .write_stdin(HELLO_WORLD_GERM_OUTPUT.replace(
+            r#""outputs":["Hello World\n"]"#,
+            r#""outputs":["Hello World\n"],"repl":{"prompt":">>> ","steps":[{"input":"1+1","outputs":["2\n"]}]}"#,
+        ))
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(
+            "[2.16,\"o\",\"Hello World\\r\\n\"]\n[2.16,\"o\",\">>> \"]\n[2.91,\"o\",\"1\"]",
+        ))
+        .stdout(predicate::str::contains(
+            "[3.865,\"o\",\"2\\r\\n\"]\n[4.865,\"o\",\"\"]",
+        ));
+}
+
+#[test]
+fn progress_bar_overwrites_frames() {
+    let mut cmd = test_cmd();
+    let assert = cmd.args(["--progress", "1", "make", "Building"]).assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(
+            "[2.24,\"o\",\"\\r[------------------------------]   0%\"]",
+        ))
+        .stdout(predicate::str::ends_with(
+            "[3.24,\"o\",\"\\r[##############################] 100%\\r\\n\"]\n[4.24,\"o\",\"\"]\n",
+        ));
+}
+
+#[test]
+fn spinner_is_erased_when_done() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--spinner", "0.2", "--spinner-frames", "|/", "make", "Done"])
+        .assert();
+    assert.success().stdout(predicate::str::ends_with(
+        "[2.24,\"o\",\"\\r|\"]\n[2.32,\"o\",\"\\r/\"]\n[2.4,\"o\",\"\\r|\"]\n[2.44,\"o\",\"\\r\\u001b[K\"]\n[3.44,\"o\",\"\"]\n",
+    ));
+}
+
+#[test]
+fn invalid_durations_of_animated_outputs_are_rejected() {
+    let mut cmd = test_cmd();
+    cmd.args(["--progress=-1", "make", "Building"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The delay must be zero or more seconds, found '-1'",
+        ));
+    let mut cmd = test_cmd();
+    cmd.args(["--spinner", "inf", "make", "Done"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The delay must be zero or more seconds, found 'inf'",
+        ));
+    let tmp_dir = TempDir::new().unwrap();
+    let input_file = tmp_dir.child("negative.germ");
+    input_file
+        .write_str(&HELLO_WORLD_GERM_OUTPUT.replace(
+            r#""outputs":["Hello World\n"]"#,
+            r#""outputs":["Hello World\n",{"spinner":-0.5}]"#,
+        ))
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("-i")
+        .arg(input_file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+        "commands[0].outputs[1]: invalid value: floating point `-0.5`, expected a delay of zero or more milliseconds",
+    ));
+    for (outputs, message) in [
+        (
+            r#""responses":[{"expect":"[y/n]","send":"y","outputs":[{"progress":-0.25}]}]"#,
+            "commands[0].responses[0].outputs[0]: invalid value: floating point `-0.25`, expected a delay of zero or more milliseconds",
+        ),
+        (
+            r#""repl":{"prompt":">>> ","steps":[{"input":"1","outputs":[{"spinner":-0.75}]}]}"#,
+            "commands[0].repl.steps[0].outputs[0]: invalid value: floating point `-0.75`, expected a delay of zero or more milliseconds",
+        ),
+    ] {
+        input_file
+            .write_str(&HELLO_WORLD_GERM_OUTPUT.replace(
+                r#""outputs":["Hello World\n"]"#,
+                &format!(r#""outputs":["Hello World\n"],{}"#, outputs),
+            ))
+            .unwrap();
+        let mut cmd = test_cmd();
+        cmd.arg("-i")
+            .arg(input_file.path())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(message));
+    }
+}
+
+#[test]
+fn comment_delay_holds_comment() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--comment-delay", "1000", "-c", "Say hi", "ls", "a"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[0.0,\"o\",\"# Say hi\\r\\n\"]\n[1.0,\"o\",\"$ \"]",
+    ));
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .write_stdin(HELLO_WORLD_GERM_OUTPUT.replace(
+            r#"{"prompt""#,
+            r#"{"comment":"Say hi","comment_delay":500,"prompt""#,
+        ))
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[0.0,\"o\",\"# Say hi\\r\\n\"]\n[0.5,\"o\",\"$ \"]",
+    ));
+}
+
+#[test]
+fn comment_style_and_prefix_work() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--comment-prefix=-- ", "--comment-style", "dim"])
+        .args(["-c", "Say hi", "ls", "a"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[0.0,\"o\",\"\\u001b[2m-- Say hi\\u001b[0m\\r\\n\"]",
+    ));
+}
+
+#[test]
+fn prompt_template_tracks_cd() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .write_stdin(
+            HELLO_WORLD_GERM_OUTPUT
+                .replace(r#""prompt":"$ ""#, r#""prompt":"{cwd} ({git_branch}) $ ""#)
+                .replace(
+                    r#""commands":["#,
+                    r#""commands":[{"prompt":"$ ","input":"cd src && git switch dev","outputs":[]},"#,
+                ),
+        )
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("\"~/src (dev) $ \""));
+}
+
+#[test]
+fn prompt_escapes_are_colors() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--size", "fit", "-p", "\\e[32m$\\e[0m ", "ls", "a"])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(r#""width":4,"#))
+        .stdout(predicate::str::contains(
+            "[0.0,\"o\",\"\\u001b[32m$\\u001b[0m \"]",
+        ));
+}
+
+#[test]
+fn prompt_is_only_for_appended_command() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["-G", "-p", "# ", "whoami", "root"])
+        .write_stdin(HELLO_WORLD_GERM_OUTPUT)
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        r##"[{"prompt":"$ ","input":"echo Hello World","outputs":["Hello World\n"]},{"prompt":"# ","input":"whoami","outputs":["root"]}]"##,
+    ));
+}
+
+#[test]
+fn failed_status_is_in_next_prompt() {
+    let mut cmd = test_cmd();
+    let assert = cmd.args(["-G", "false"]).assert();
+    assert.success().stdout(predicate::str::contains(
+        r#""input":"false","outputs":[""],"status":1"#,
+    ));
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["-p", "{status_mark}$ ", "ls", "a"])
+        .write_stdin(HELLO_WORLD_GERM_OUTPUT.replace(r#""outputs""#, r#""status":2,"outputs""#))
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("\"\\u001b[31m✗\\u001b[0m $ \""));
+}
+
+#[test]
+fn redact_replaces_matches() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["-G", "--redact-preset", "bearer", "--redact", "hunter\\d"])
+        .args(["curl -H 'Authorization: Bearer abc.123' -p hunter2", "OK"])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("Bearer [REDACTED]' -p [REDACTED]"))
+        .stdout(predicate::str::contains("abc.123").not())
+        .stdout(predicate::str::contains("hunter2").not());
+}
+
+#[test]
+fn strip_ansi_removes_escapes() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["-G", "--strip-ansi", "ls", "\u{1b}[1;34msrc\u{1b}[0m"])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(r#""outputs":["src"]"#));
+}
+
+#[test]
+fn wrap_splits_long_output_lines() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["-W", "10", "--wrap", "ls", "0123456789abc"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "[1.67,\"o\",\"0123456789\\r\\n\"]\n[1.67,\"o\",\"abc\\r\\n\"]",
+    ));
+}
+
+#[test]
+fn max_output_lines_omits_middle_lines() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--max-output-lines", "3", "seq 5", "1\n2\n3\n4\n5\n"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        "\"2\\r\\n\"]\n[1.775,\"o\",\"… (2 lines omitted)\\r\\n\"]\n[1.775,\"o\",\"5\\r\\n\"]",
+    ));
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .write_stdin(HELLO_WORLD_GERM_OUTPUT.replace(
+            r#""outputs":["Hello World\n"]"#,
+            r#""outputs":["Hello\nWorld\n"],"max_output_lines":1"#,
+        ))
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("\"… (1 line omitted)\\r\\n\""));
+}
+
+#[test]
+fn at_file_output_is_file_contents() {
+    let tmp_dir = TempDir::new().unwrap();
+    let output_file = tmp_dir.child("hello.txt");
+    output_file.write_str("Hello\nWorld\n").unwrap();
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .arg("-G")
+        .arg("echo Hello World")
+        .arg(format!("@{}", output_file.path().display()))
+        .arg("@@home")
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        r#""outputs":["Hello\nWorld\n","@home"]"#,
+    ));
+}
+
+#[test]
+fn outputs_from_stdin_works() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["-G", "--outputs-from", "-", "mytool --help"])
+        .write_stdin("Usage: mytool\n")
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        r#""input":"mytool --help","outputs":["Usage: mytool\n"]"#,
+    ));
+}
+
+#[test]
+fn multiple_input_files_are_concatenated() {
+    let tmp_dir = TempDir::new().unwrap();
+    let setup = tmp_dir.child("setup.germ");
+    setup.write_str(HELLO_WORLD_GERM_OUTPUT).unwrap();
+    let feature = tmp_dir.child("feature.germ");
+    feature
+        .write_str(&HELLO_WORLD_GERM_OUTPUT.replace("Hello World", "Goodbye"))
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("-G")
+        .arg("-i")
+        .arg(setup.path())
+        .arg("-i")
+        .arg(feature.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""outputs":["Hello World\n"]},{"prompt":"$ ","input":"echo Goodbye""#,
+        ));
+}
+
+#[test]
+fn append_adds_events_after_existing_cast() {
+    let tmp_dir = TempDir::new().unwrap();
+    let output_file = tmp_dir.child("demo.cast");
+    for (input, output) in [("echo Hello World", "Hello World"), ("pwd", "/x")] {
+        let mut cmd = test_cmd();
+        cmd.arg("-a")
+            .arg("-o")
+            .arg(output_file.path())
+            .args([input, output])
+            .assert()
+            .success();
+    }
+    output_file.assert(predicate::str::starts_with(HELLO_WORLD_ASCIICAST_OUTPUT));
+    output_file.assert(predicate::str::ends_with(
+        "[3.16,\"o\",\"$ \"]\n[3.91,\"o\",\"p\"]\n[3.945,\"o\",\"w\"]\n[3.98,\"o\",\"d\"]\n[4.865,\"o\",\"\\r\\n\"]\n[4.865,\"o\",\"/x\\r\\n\"]\n[5.865,\"o\",\"\"]\n",
+    ));
+}
+
+#[test]
+fn output_file_with_gz_extension_is_compressed() {
+    let tmp_dir = TempDir::new().unwrap();
+    let output_file = tmp_dir.child("demo.cast.gz");
+    let mut cmd = test_cmd();
+    cmd.arg("-o")
+        .arg(output_file.path())
+        .args(["echo Hello World", "Hello World"])
+        .assert()
+        .success();
+    let mut decoded = String::new();
+    GzDecoder::new(std::fs::File::open(output_file.path()).unwrap())
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, HELLO_WORLD_ASCIICAST_OUTPUT);
+}
+
+#[test]
+fn compressed_input_file_is_decompressed() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input_file = tmp_dir.child("demo.json.gz");
+    let mut encoder = GzEncoder::new(
+        std::fs::File::create(input_file.path()).unwrap(),
+        Compression::default(),
+    );
+    encoder
+        .write_all(HELLO_WORLD_GERM_OUTPUT.as_bytes())
+        .unwrap();
+    encoder.finish().unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("-i")
+        .arg(input_file.path())
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_ASCIICAST_OUTPUT);
+}
+
+#[test]
+fn watch_requires_input_file() {
+    let tmp_dir = TempDir::new().unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("--watch")
+        .arg("-o")
+        .arg(tmp_dir.child("demo.cast").path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--input <file>"));
+}
+
+#[test]
+fn concat_rebases_second_file() {
+    let tmp_dir = TempDir::new().unwrap();
+    let first = tmp_dir.child("first.cast");
+    first.write_str(HELLO_WORLD_ASCIICAST_OUTPUT).unwrap();
+    let second = tmp_dir.child("second.json");
+    second.write_str(HELLO_WORLD_GERM_OUTPUT).unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("concat")
+        .arg(first.path())
+        .arg(second.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(HELLO_WORLD_ASCIICAST_OUTPUT))
+        .stdout(predicate::str::ends_with(
+            "[5.32,\"o\",\"Hello World\\r\\n\"]\n[6.32,\"o\",\"\"]\n",
+        ));
+}
+
+#[test]
+fn cut_drops_events_outside_range() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("input.cast");
+    input.write_str(HELLO_WORLD_ASCIICAST_OUTPUT).unwrap();
+    let mut cmd = test_cmd();
+    cmd.args(["cut", "--from", "2", "--to", "3"])
+        .arg(input.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "}\n[0.16,\"o\",\"\\r\\n\"]\n[0.16,\"o\",\"Hello World\\r\\n\"]\n",
+        ));
+}
+
+#[test]
+fn max_idle_clamps_gaps_of_cast_input() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("input.cast");
+    input
+        .write_str(
+            r#"{"version":2,"width":80,"height":24}
+[0.5,"o","a"]
+[4.0,"o","b"]
+[4.5,"o","c"]
+"#,
+        )
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.args(["--max-idle", "1.5", "cut"])
+        .arg(input.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "[0.5,\"o\",\"a\"]\n[2.0,\"o\",\"b\"]\n[2.5,\"o\",\"c\"]\n",
+        ));
+}
+
+#[test]
+fn max_idle_and_idle_time_limit_must_be_positive() {
+    for (option, value) in [("--max-idle", "-1"), ("--idle-time-limit", "0")] {
+        let mut cmd = test_cmd();
+        cmd.arg(format!("{}={}", option, value))
+            .args(["echo Hello World", "Hello World"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "The limit must be greater than zero seconds, found '{}'",
+                value
+            )));
+    }
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("input.cast");
+    input
+        .write_str(
+            r#"{"version":2,"width":80,"height":24,"idle_time_limit":-1.0}
+[0.5,"o","a"]
+"#,
+        )
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("cut")
+        .arg(input.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "idle_time_limit: The limit must be greater than zero seconds, found '-1'",
+        ));
+}
+
+#[test]
+fn normalize_recovers_commands_from_cast() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("input.cast");
+    input
+        .write_str(
+            r#"{"version":2,"width":80,"height":24}
+[0.1,"o","Welcome\r\n$ "]
+[1.0,"o","ecoh"]
+[2.5,"o","\b\b\u001b[Kho Hello World"]
+[6.0,"o","\r\nHello World\r\n$ "]
+"#,
+        )
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "normalize"])
+        .arg(input.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""commands":[{"prompt":"$ ","input":"echo Hello World","outputs":["Hello World"]}]"#,
+        ));
+}
+
+#[test]
+fn merge_events_combines_output_lines() {
+    let mut cmd = test_cmd();
+    cmd.args(["--merge-events", "echo Hello World", "Hello World\nBye"])
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(
+            "[2.16,\"o\",\"\\r\\nHello World\\r\\nBye\\r\\n\"]\n[3.16,\"o\",\"\"]\n",
+        ));
+}
+
+#[test]
+fn chunk_size_packs_output_lines() {
+    let mut cmd = test_cmd();
+    cmd.args(["--chunk-size", "8", "echo Hello World", "Hello World\nab"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[2.16,\"o\",\"Hello Wo\"]\n[2.16,\"o\",\"rld\\r\\nab\\r\"]\n[2.16,\"o\",\"\\n\"]\n",
+        ));
+}
+
+#[test]
+fn deterministic_ignores_inherited_environment() {
+    let mut cmd = test_cmd();
+    cmd.env("SHELL", "/bin/zsh")
+        .env("TERM", "alacritty")
+        .args(["--deterministic", "echo Hello World", "Hello World"])
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_ASCIICAST_OUTPUT);
+}
+
+#[test]
+fn expand_env_executes_values_but_types_literally() {
+    let mut cmd = test_cmd();
+    cmd.env("GREETING", "Hello World")
+        .args(["-O", "germ", "--expand-env", "execute", "echo '$GREETING'"])
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_GERM_OUTPUT.replace(
+            r#""input":"echo Hello World""#,
+            r#""input":"echo '$GREETING'""#,
+        ));
+}
+
+#[test]
+fn cwd_executes_and_displays_in_directory() {
+    let tmp_dir = TempDir::new().unwrap();
+    tmp_dir.child("demo/hello.txt").touch().unwrap();
+    let mut cmd = test_cmd();
+    cmd.args(["-p", "{dir} $ ", "--cwd"])
+        .arg(tmp_dir.child("demo").path())
+        .arg("ls")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#"[0.0,"o","demo $ "]"#))
+        .stdout(predicate::str::contains(r#""o","hello.txt\r\n"]"#));
+}
+
+#[test]
+fn docker_backend_requires_image() {
+    let mut cmd = test_cmd();
+    cmd.args(["--exec-backend", "docker", "echo Hello World"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--image <image>"));
+}
+
+#[test]
+fn expect_sends_responses_to_prompts() {
+    let mut cmd = test_cmd();
+    cmd.args([
+        "-O",
+        "germ",
+        "--expect",
+        "[y/n]",
+        "--send",
+        "y",
+        r#"printf "Continue? [y/n] "; read a; echo "got $a""#,
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(
+        r#""responses":[{"expect":"[y/n]","send":"y","prompt":"Continue? [y/n] ","outputs":["got y\n"]}]"#,
+    ));
+}
+
+#[test]
+fn expect_responds_before_output_and_times_out() {
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "--expect", "", "--send", "bob"])
+        .arg(r#"read n; echo "hi $n""#)
+        .timeout(std::time::Duration::from_secs(5))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""responses":[{"expect":"","send":"bob","prompt":"","outputs":["hi bob\n"]}]"#,
+        ));
+    let mut cmd = test_cmd();
+    cmd.args([
+        "--response-timeout",
+        "0.2",
+        "--expect",
+        "Name:",
+        "--send",
+        "bob",
+    ])
+    .arg(r#"read n; echo "hi $n""#)
+    .timeout(std::time::Duration::from_secs(5))
+    .assert()
+    .failure()
+    .stderr(predicate::str::contains(
+        "prompt 'Name:' not seen after 0.2s",
+    ));
+}
+
+#[test]
+fn no_execute_and_allow_restrict_execution() {
+    let mut cmd = test_cmd();
+    cmd.args(["--no-execute", "echo Hello World"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--no-execute forbids executing it",
+        ));
+    let mut cmd = test_cmd();
+    cmd.args(["--allow", "echo", "echo Hello World; true"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The program 'true' is not allowed by --allow",
+        ));
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "--allow", "echo", "echo Hello World"])
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_GERM_OUTPUT);
+}
+
+#[test]
+fn allow_rejects_other_programs_with_the_same_name() {
+    let tmp_dir = TempDir::new().unwrap();
+    for name in ["ls", "rm"] {
+        let script = tmp_dir.child(name);
+        script.write_str("#!/bin/sh\necho PWNED\n").unwrap();
+        std::fs::set_permissions(script.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    for variable in ["PATH=.", "LD_PRELOAD=./ls", "BASH_ENV=./ls", "IFS=/"] {
+        let mut cmd = test_cmd();
+        cmd.args(["--allow", "ls", "--cwd"])
+            .arg(tmp_dir.path())
+            .arg(format!("{} ls", variable))
+            .assert()
+            .failure()
+            .stdout(predicate::str::contains("PWNED").not())
+            .stderr(predicate::str::contains(format!(
+                "sets '{}' before a program, which --allow forbids",
+                variable
+            )));
+    }
+    let mut cmd = test_cmd();
+    cmd.args(["--allow", "ls", "--cwd"])
+        .arg(tmp_dir.path())
+        .arg("./ls")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The program './ls' is not allowed by --allow",
+        ));
+    let rm = tmp_dir.child("rm");
+    let mut cmd = test_cmd();
+    cmd.args(["--allow", "rm"])
+        .arg(rm.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not allowed by --allow"));
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "--allow", "ls", "--cwd"])
+        .arg(tmp_dir.path())
+        .arg("ls")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""outputs":["ls\nrm\n"]"#));
+}
+
+#[test]
+fn no_execute_and_allow_restrict_execution_in_interactive_mode() {
+    let germ = assert_cmd::cargo::cargo_bin("germ");
+    for (line, error) in [
+        (
+            "--no-execute \"echo Hello World\"",
+            "--no-execute forbids executing it",
+        ),
+        (
+            "--allow echo \"echo Hello World; true\"",
+            "The program 'true' is not allowed by --allow",
+        ),
+    ] {
+        // Interactive mode needs a terminal for stdin, which script provides.
+        let mut cmd = Command::new("script");
+        cmd.env("SHELL", TEST_SHELL)
+            .env("TERM", TEST_TERM)
+            .arg("-qec")
+            .arg(germ.display().to_string())
+            .arg("/dev/null")
+            .write_stdin(format!("{}\n", line))
+            .assert()
+            .failure()
+            .stdout(predicate::str::contains(error))
+            .stdout(predicate::str::contains("Hello World\r\n").not());
+    }
+}
+
+#[test]
+fn shell_and_term_are_set_separately_in_interactive_mode() {
+    let mut cmd = Command::new("script");
+    cmd.env("SHELL", TEST_SHELL)
+        .env("TERM", TEST_TERM)
+        .arg("-qec")
+        .arg(assert_cmd::cargo::cargo_bin("germ").display().to_string())
+        .arg("/dev/null")
+        .write_stdin("-S /bin/bash -T vt100 \"echo Hello World\" \"Hello World\"\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""env":{"SHELL":"/bin/bash","TERM":"vt100"}"#,
+        ));
+}
+
+#[test]
+fn fail_fast_fails_on_non_zero_exit_code() {
+    let mut cmd = test_cmd();
+    cmd.args(["--fail-fast", "false"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains(
+            "The input 'false' failed with exit code 1",
+        ));
+    let mut cmd = test_cmd();
+    cmd.args(["--fail-fast", "--status", "1", "false"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn retries_executes_failed_input_again() {
+    let tmp_dir = TempDir::new().unwrap();
+    let mut cmd = test_cmd();
+    cmd.args([
+        "-O",
+        "germ",
+        "--retries",
+        "2",
+        "--retry-delay",
+        "0",
+        "--cwd",
+    ])
+    .arg(tmp_dir.path())
+    .arg("test -e tried && echo Hello World || { touch tried; false; }")
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(r#""outputs":["Hello World\n"]"#))
+    .stderr(predicate::str::contains("Retrying"));
+}
+
+#[test]
+fn retries_of_command_are_preferred_in_interactive_mode() {
+    let tmp_dir = TempDir::new().unwrap();
+    let output = tmp_dir.child("demo.germ");
+    // Interactive mode needs a terminal for stdin, which script provides.
+    let germ = assert_cmd::cargo::cargo_bin("germ");
+    let mut cmd = Command::new("script");
+    cmd.env("SHELL", TEST_SHELL)
+        .env("TERM", TEST_TERM)
+        .arg("-qec")
+        .arg(format!(
+            "{} -O germ --retry-delay 0 -o {}",
+            germ.display(),
+            output.path().display()
+        ))
+        .arg("/dev/null")
+        .write_stdin(format!(
+            "--retries 2 --cwd {} \"test -e tried && echo Hello World || {{ touch tried; false; }}\"\n",
+            tmp_dir.path().display()
+        ))
+        .assert()
+        .success();
+    output.assert(predicate::str::contains(r#""retries":2"#));
+    output.assert(predicate::str::contains(r#""outputs":["Hello World\n"]"#));
+}
+
+#[test]
+fn execute_string_flag_is_detected_from_shell() {
+    let tmp_dir = TempDir::new().unwrap();
+    let shell = tmp_dir.child("pwsh");
+    shell.write_str("#!/bin/sh\necho \"$1\"\n").unwrap();
+    std::fs::set_permissions(shell.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "-S"])
+        .arg(shell.path())
+        .arg("Get-Date")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""outputs":["-Command\n"]"#));
+}
+
+#[test]
+fn powershell_flavor_sets_prompt_and_term() {
+    let mut cmd = test_cmd();
+    cmd.env("TERM", "screen")
+        .args(["--flavor", "powershell", "Get-Date", "Monday"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""TERM":"xterm-256color""#))
+        .stdout(predicate::str::contains(r#"[0.0,"o","PS C:\\> "]"#));
+}
+
+#[test]
+fn root_prompt_preset_ends_with_hash() {
+    let mut cmd = test_cmd();
+    cmd.args(["--prompt-preset", "root", "-O", "germ", "whoami", "root"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""prompt":"root@host:{cwd}# ""#));
+}
+
+#[test]
+fn command_builder_constructs_command() {
+    let command = germ::Command::builder("echo Hello")
+        .comment("Greets")
+        .prompt("> ")
+        .output("Hello\n")
+        .input("echo Hello World")
+        .outputs(vec!["World\n"])
+        .build();
+    assert_eq!(command.input(), "echo Hello World");
+    assert_eq!(command.prompt(), "> ");
+    assert_eq!(command.comment(), Some("Greets"));
+    assert_eq!(
+        command.into_outputs(),
+        vec![String::from("Hello\n"), String::from("World\n")]
+    );
+}
+
+#[test]
+fn unsupported_asciicast_version_is_matchable_error() {
+    let cast = "{\"version\":1,\"width\":80,\"height\":24}\n";
+    match germ::Asciicast::read_from(cast.as_bytes()) {
+        Err(germ::Error::UnsupportedVersion {
+            format, found: 1, ..
+        }) => assert_eq!(format, "asciicast"),
+        other => panic!("Expected an unsupported version error: {:?}", other.err()),
+    }
+}
+
+#[test]
+fn sequence_converts_to_asciicast() {
+    let sequence = germ::Sequence::from(vec![germ::Command::builder("echo Hello World")
+        .output("Hello World\n")
+        .build()]);
+    let mut settings = germ::Asciicast::default();
+    settings.size = Some(germ::Sizes::Fit);
+    let asciicast = sequence.to_asciicast(&settings);
+    assert!(asciicast.events().iter().any(|e| e.2 == "Hello World\r\n"));
+    assert_eq!(asciicast.header.width, 18);
+}
+
+#[test]
+fn cast_reader_yields_events_lazily() {
+    let cast =
+        "{\"version\":2,\"width\":80,\"height\":24}\n[0.5,\"o\",\"a\"]\n\n[1.0,\"o\",\"b\"]\n";
+    let mut reader = germ::CastReader::new(cast.as_bytes()).unwrap();
+    assert_eq!(reader.header().width, 80);
+    let events = reader
+        .by_ref()
+        .collect::<germ::Result<Vec<germ::Event>>>()
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].2, "b");
+}
+
+#[test]
+fn events_iterator_matches_converted_asciicast() {
+    let sequence = germ::Sequence::from(vec![
+        germ::Command::builder("echo Hello")
+            .output("Hello\n")
+            .build(),
+        germ::Command::builder("echo World")
+            .output("World\n")
+            .build(),
+    ]);
+    let mut settings = germ::Asciicast::default();
+    settings.merge_events = true;
+    let streamed: Vec<germ::Event> = sequence.events(&settings).collect();
+    let asciicast = sequence.to_asciicast(&settings);
+    assert_eq!(streamed.len(), asciicast.events().len());
+    for (s, e) in streamed.iter().zip(asciicast.events()) {
+        assert_eq!((s.0, &s.2), (e.0, &e.2));
+    }
+}
+
+#[test]
+fn ctrl_c_interrupts_executed_input_and_keeps_partial_output() {
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("germ"))
+        .env("SHELL", TEST_SHELL)
+        .env("TERM", TEST_TERM)
+        .arg("-O")
+        .arg("germ")
+        .arg("--keep-interrupted")
+        .arg("echo partial; exec sleep 10")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    std::process::Command::new("kill")
+        .arg("-INT")
+        .arg(child.id().to_string())
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(r#""outputs":["partial\n"],"interrupt":true"#));
+}
+
+#[test]
+fn verbose_logs_executed_inputs_and_quiet_hides_warnings() {
+    let mut cmd = test_cmd();
+    cmd.arg("-v")
+        .arg("echo Hello World")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Executing 'echo Hello World' with '/bin/sh'",
+        ))
+        .stderr(predicate::str::contains("Executed 'echo Hello World' in"));
+    let mut cmd = test_cmd();
+    cmd.arg("-q")
+        .arg("--retries")
+        .arg("1")
+        .arg("--retry-delay")
+        .arg("0")
+        .arg("false")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn parse_failure_reports_offending_line() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input_file = tmp_dir.child("bad.germ");
+    input_file
+        .write_str(
+            "{\n  \"version\": 1,\n  \"commands\": [\n    {\"prompt\": \"$ \", \"input\": 1, \"outputs\": []}\n  ]\n}\n",
+        )
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("-i")
+        .arg(input_file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "commands[0].input: invalid type: integer `1`, expected a string",
+        ))
+        .stderr(predicate::str::contains(format!(
+            "--> {}:4:31",
+            input_file.path().display()
+        )))
+        .stderr(predicate::str::contains(
+            r#"4 |     {"prompt": "$ ", "input": 1, "outputs": []}"#,
+        ))
+        .stderr(predicate::str::contains("^ invalid type"));
+}
+
+#[test]
+fn completions_subcommand_completes_format_and_theme_values() {
+    let mut cmd = test_cmd();
+    cmd.arg("completions")
+        .arg("bash")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("germ termsheets asciicast"))
+        .stdout(predicate::str::contains("dracula solarized-dark"));
+}
+
+#[test]
+fn nonsensical_timings_are_rejected() {
+    let mut cmd = test_cmd();
+    cmd.arg("--speed")
+        .arg("0")
+        .arg("echo Hello World")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The speed must be a factor greater than zero, found '0'",
+        ));
+    let tmp_dir = TempDir::new().unwrap();
+    let input_file = tmp_dir.child("negative.germ");
+    input_file
+        .write_str(&HELLO_WORLD_GERM_OUTPUT.replace(r#""speed":1.0"#, r#""speed":0.0"#))
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("-i")
+        .arg(input_file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "timings.speed: The speed must be a factor greater than zero, found '0'",
+        ));
+}
+
+#[test]
+fn legacy_begin_and_end_seconds_are_written_as_milliseconds() {
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "-I", "germ"])
+        .write_stdin(
+            HELLO_WORLD_GERM_OUTPUT
+                .replace(r#""version":2"#, r#""version":1"#)
+                .replace(r#""begin":0,"end":1000"#, r#""begin":0.0,"end":1.0"#),
+        )
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_GERM_OUTPUT);
+    // Whole seconds are still seconds in a version 1 file.
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "-I", "germ"])
+        .write_stdin(
+            HELLO_WORLD_GERM_OUTPUT
+                .replace(r#""version":2"#, r#""version":1"#)
+                .replace(r#""end":1000"#, r#""end":2"#),
+        )
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_GERM_OUTPUT.replace(r#""end":1000"#, r#""end":2000"#));
+    // So are the durations of the animated outputs, but not the interval.
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "-I", "germ"])
+        .write_stdin(
+            HELLO_WORLD_GERM_OUTPUT
+                .replace(r#""version":2"#, r#""version":1"#)
+                .replace(r#""end":1000"#, r#""end":1"#)
+                .replace(
+                    r#""outputs":["Hello World\n"]"#,
+                    r#""outputs":["Hello World\n",{"progress":1.5},{"spinner":0.5,"interval":100}],"responses":[{"expect":"?","send":"y","outputs":[{"progress":2}]}]"#,
+                ),
+        )
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_GERM_OUTPUT.replace(
+            r#""outputs":["Hello World\n"]"#,
+            r#""outputs":["Hello World\n",{"progress":1500},{"spinner":500,"interval":100}],"responses":[{"expect":"?","send":"y","outputs":[{"progress":2000}]}]"#,
+        ));
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "-I", "germ"])
+        .write_stdin(HELLO_WORLD_GERM_OUTPUT.replace(r#""end":1000"#, r#""end":-1"#))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "timings.end: invalid value: integer `-1`, expected a delay of zero or more milliseconds",
+        ));
+}
+
+#[test]
+fn stdin_logs_enter_keypress_at_submit() {
+    let mut cmd = test_cmd();
+    cmd.args(["--stdin", "--typing-profile", "instant", "echo hi", "hi"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[0.0,\"i\",\"i\"]\n[0.0,\"o\",\"i\"]\n[0.0,\"i\",\"\\r\"]\n[0.5,\"o\",\"\\r\\n\"]\n",
+        ));
+}
+
+#[test]
+fn echo_latency_trails_keypresses() {
+    let mut cmd = test_cmd();
+    cmd.args(["--stdin", "--echo-latency", "20", "echo hi", "hi"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[0.75,\"i\",\"e\"]\n[0.77,\"o\",\"e\"]\n[0.785,\"i\",\"c\"]\n",
+        ));
+}
+
+#[test]
+fn embed_output_format_references_cast_file() {
+    let tmp_dir = TempDir::new().unwrap();
+    let output_file = tmp_dir.child("demo.html");
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "embed", "--embed-speed", "2", "-o"])
+        .arg(output_file.path())
+        .arg("echo Hello World")
+        .assert()
+        .success();
+    output_file.assert(predicate::str::contains(
+        r#"AsciinemaPlayer.create("demo.cast", document.getElementById("germ-demo"), {"speed":2.0});"#,
+    ));
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "embed", "echo Hello World"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--embed-src"));
+}
+
+#[test]
+fn caption_output_formats_time_comments() {
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "webvtt", "--comment", "Say <hi>", "echo hi"])
+        .assert()
+        .success()
+        .stdout("WEBVTT\n\n00:00:00.000 --> 00:00:01.845\nSay &lt;hi&gt;\n");
+    let mut cmd = test_cmd();
+    cmd.args([
+        "-O",
+        "srt",
+        "--caption-inputs",
+        "--comment",
+        "Say hi",
+        "echo hi",
+    ])
+    .assert()
+    .success()
+    .stdout("1\n00:00:00,000 --> 00:00:01,845\nSay hi\n$ echo hi\n");
+}
+
+#[test]
+fn chapters_output_formats_map_commands_to_titles() {
+    let input = HELLO_WORLD_GERM_OUTPUT.replace(
+        r#""commands":[{"#,
+        r#""commands":[{"comment":"Greet = wave","prompt":"$ ","input":"ls","outputs":[]},{"#,
+    );
+    let mut cmd = test_cmd();
+    cmd.args(["-I", "germ", "-O", "chapters"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(concat!(
+            r#"[{"start":0.0,"end":1.17,"title":"Greet = wave"},"#,
+            r#"{"start":1.17,"end":3.33,"title":"echo Hello World"}]"#,
+            "\n"
+        ));
+    let mut cmd = test_cmd();
+    cmd.args(["-I", "germ", "-O", "ffmetadata"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            ";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1170\ntitle=Greet \\= wave\n",
+        ));
+}
+
+#[test]
+fn transcript_output_format_times_inputs() {
+    let input = HELLO_WORLD_GERM_OUTPUT.replace(
+        r#""commands":[{"#,
+        r#""commands":[{"comment":"Greet","prompt":"$ ","input":"ls","outputs":["\u001b[31mred\u001b[0m\n"]},{"#,
+    );
+    let mut cmd = test_cmd();
+    cmd.args(["-I", "germ", "-O", "transcript"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("Greet\n[00:00] $ ls\nred\n\n[00:01] $ echo Hello World\nHello World\n");
+}
+
+#[test]
+fn transcript_output_format_strips_colored_prompts() {
+    let mut cmd = test_cmd();
+    cmd.args(["--prompt-preset", "color", "-O", "transcript"])
+        .args(["echo Hello World", "Hello World"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("[00:00] user@host:"))
+        .stdout(predicate::str::contains(
+            "$ echo Hello World\nHello World\n",
+        ))
+        .stdout(predicate::str::contains("\x1b").not());
+}
+
+#[test]
+fn cat_prints_final_screen() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("input.germ");
+    input.write_str(HELLO_WORLD_GERM_OUTPUT).unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("cat")
+        .arg(input.path())
+        .assert()
+        .success()
+        .stdout("$ echo Hello World\nHello World\n");
+}
+
+#[test]
+fn png_renders_frames_at_times() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("demo.germ");
+    input.write_str(HELLO_WORLD_GERM_OUTPUT).unwrap();
+    let mut cmd = test_cmd();
+    cmd.args(["png", "--at", "0.5", "--at", "2", "--scale", "1", "-o"])
+        .arg(tmp_dir.path())
+        .arg(input.path())
+        .assert()
+        .success();
+    tmp_dir
+        .child("demo-000500.png")
+        .assert(predicate::path::exists());
+    let image = std::fs::read(tmp_dir.child("demo-002000.png").path()).unwrap();
+    assert_eq!(&image[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&image[12..16], b"IHDR");
+    // An 80x24 terminal with cells of 8x16 pixels.
+    assert_eq!(&image[16..24], &[0, 0, 2, 128, 0, 0, 1, 128]);
+}
+
+#[test]
+fn video_pipes_raw_frames_to_ffmpeg() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("demo.germ");
+    input.write_str(HELLO_WORLD_GERM_OUTPUT).unwrap();
+    // Stands in for ffmpeg by writing the raw frames to the output file.
+    let ffmpeg = tmp_dir.child("ffmpeg");
+    ffmpeg
+        .write_str("#!/bin/sh\nfor last; do :; done\ncat > \"$last\"\n")
+        .unwrap();
+    std::fs::set_permissions(ffmpeg.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let output = tmp_dir.child("demo.webm");
+    let mut cmd = test_cmd();
+    cmd.args(["video", "--fps", "2", "--scale", "1", "--ffmpeg"])
+        .arg(ffmpeg.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg(input.path())
+        .assert()
+        .success();
+    // The 3.16 seconds are 8 frames of 640x384 pixels.
+    let frames = std::fs::metadata(output.path()).unwrap().len();
+    assert_eq!(frames, 8 * 640 * 384 * 3);
+}
+
+#[test]
+fn hold_last_repeats_final_frame() {
+    let mut cmd = test_cmd();
+    cmd.args(["--hold-last", "2", "echo Hello World", "Hello World"])
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with("[5.16,\"o\",\"\"]\n"));
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("demo.germ");
+    input.write_str(HELLO_WORLD_GERM_OUTPUT).unwrap();
+    let mut cmd = test_cmd();
+    cmd.args([
+        "png",
+        "--every",
+        "1",
+        "--hold-last",
+        "2",
+        "--scale",
+        "1",
+        "-o",
+    ])
+    .arg(tmp_dir.path())
+    .arg(input.path())
+    .assert()
+    .success();
+    // The frames after the end of the 3.16 seconds are the last frame.
+    let last = std::fs::read(tmp_dir.child("demo-004000.png").path()).unwrap();
+    let held = std::fs::read(tmp_dir.child("demo-005000.png").path()).unwrap();
+    assert_eq!(last, held);
+    let ffmpeg = tmp_dir.child("ffmpeg");
+    ffmpeg
+        .write_str("#!/bin/sh\nfor last; do :; done\ncat > \"$last\"\n")
+        .unwrap();
+    std::fs::set_permissions(ffmpeg.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let output = tmp_dir.child("demo.webm");
+    let mut cmd = test_cmd();
+    cmd.args(["video", "--fps", "2", "--scale", "1", "--hold-last", "2"])
+        .arg("--ffmpeg")
+        .arg(ffmpeg.path())
+        .arg("-o")
+        .arg(output.path())
+        .arg(input.path())
+        .assert()
+        .success();
+    // The 8 frames of the 3.16 seconds and 4 frames of the last one.
+    let frames = std::fs::metadata(output.path()).unwrap().len();
+    assert_eq!(frames, 12 * 640 * 384 * 3);
+}
+
+#[test]
+fn highlight_paints_matches_of_outputs_in_events() {
+    let mut cmd = test_cmd();
+    cmd.args(["--highlight", r"W\w+=red", "--highlight", "o=blue"])
+        .args(["echo Hello World", "Hello World"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#"[2.16,"o","Hell\u001b[34mo\u001b[0m \u001b[31mWorld\u001b[0m\r\n"]"#,
+        ));
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "--highlight", r"W\w+=red"])
+        .args(["echo Hello World", "Hello World"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""outputs":["Hello World"]"#));
+}
+
+#[test]
+fn intro_title_precedes_first_prompt() {
+    let mut cmd = test_cmd();
+    cmd.args(["--intro-title", "Germ Demo", "--intro-duration", "2"])
+        .args(["echo Hello World", "Hello World"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#"[0.0,"o","\u001b[?25l\u001b[12;36HGerm Demo"]
+[2.0,"o","\u001b[2J\u001b[H\u001b[?25h"]
+[2.0,"o","$ "]"#,
+        ));
+}
+
+#[test]
+fn intro_options_work_in_interactive_mode() {
+    let tmp_dir = TempDir::new().unwrap();
+    let output = tmp_dir.child("demo.cast");
+    // Interactive mode needs a terminal for stdin, which script provides.
+    let germ = assert_cmd::cargo::cargo_bin("germ");
+    let mut cmd = Command::new("script");
+    cmd.env("SHELL", TEST_SHELL)
+        .env("TERM", TEST_TERM)
+        .arg("-qec")
+        .arg(format!("{} -o {}", germ.display(), output.path().display()))
+        .arg("/dev/null")
+        .write_stdin(
+            "--intro-title Demo --intro-duration 2\n\"echo Hello World\" \"Hello World\"\n",
+        )
+        .assert()
+        .success();
+    output.assert(predicate::str::contains(
+        r#"[0.0,"o","\u001b[?25l\u001b[12;39HDemo"]
+[2.0,"o","\u001b[2J\u001b[H\u001b[?25h"]
+[2.0,"o","$ "]"#,
+    ));
+}
+
+#[test]
+fn include_prepends_commands_of_other_files() {
+    let tmp_dir = TempDir::new().unwrap();
+    tmp_dir
+        .child("preamble.json")
+        .write_str(HELLO_WORLD_GERM_OUTPUT)
+        .unwrap();
+    let input = tmp_dir.child("demos/demo.json");
+    input
+        .write_str(
+            &HELLO_WORLD_GERM_OUTPUT
+                .replace(
+                    r#""commands""#,
+                    r#""include":["../preamble.json"],"commands""#,
+                )
+                .replace("Hello World", "Goodbye"),
+        )
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.args(["-O", "germ", "-i"])
+        .arg(input.path())
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_GERM_OUTPUT.replace(
+            "}]}",
+            r#"},{"prompt":"$ ","input":"echo Goodbye","outputs":["Goodbye\n"]}]}"#,
+        ));
+}
+
+#[test]
+fn verify_reports_output_differences() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("demo.json");
+    input
+        .write_str(&HELLO_WORLD_GERM_OUTPUT.replace(
+            r#"]}]}"#,
+            r#"]},{"prompt":"$ ","input":"echo Goodbye","outputs":["Hello"]}]}"#,
+        ))
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("verify")
+        .arg(input.path())
+        .assert()
+        .failure()
+        .stdout(
+            "ok: echo Hello World\nFAILED: echo Goodbye\n  -Hello\n  +Goodbye\n1 passed, 1 failed, 0 skipped\n",
+        );
+}
+
+#[test]
+fn verify_fails_commands_killed_by_a_signal() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input = tmp_dir.child("demo.json");
+    input
+        .write_str(&HELLO_WORLD_GERM_OUTPUT.replace(
+            r#"]}]}"#,
+            r#"]},{"prompt":"$ ","input":"kill -9 $$","outputs":[]}]}"#,
+        ))
+        .unwrap();
+    let mut cmd = test_cmd();
+    cmd.arg("verify")
+        .arg(input.path())
+        .assert()
+        .failure()
+        .stdout(
+            "ok: echo Hello World\nFAILED: kill -9 $$\n  expected status 0, found signal 9\n1 passed, 1 failed, 0 skipped\n",
+        );
+}
+
+#[test]
+fn idle_time_limit_works() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args([
+            "--idle-time-limit",
+            "2.5",
+            "echo Hello World",
+            "Hello World",
+        ])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(r#""idle_time_limit":2.5"#));
+}
+
+#[test]
+fn theme_preset_with_override_works() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--theme", "gruvbox", "--theme-bg", "#000000"])
+        .args(["echo Hello World", "Hello World"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        r##""theme":{"fg":"#ebdbb2","bg":"#000000","palette":"#282828:#cc241d"##,
+    ));
+}
+
+#[test]
+fn theme_override_without_preset_fails() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--theme-fg", "#ffffff", "echo Hello World", "Hello World"])
+        .assert();
+    assert.failure();
+}
+
+#[test]
+fn manifest_records_output_file() {
+    let tmp_dir = TempDir::new().unwrap();
+    let output_file = tmp_dir.child("test.cast");
+    let manifest_file = tmp_dir.child("manifest.json");
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .arg("-o")
+        .arg(output_file.path())
+        .arg("--manifest")
+        .arg(manifest_file.path())
+        .args(["echo Hello World", "Hello World"])
+        .assert();
+    assert.success();
+    manifest_file.assert(predicate::str::contains(
+        r#""format":"asciicast","duration":3.16,"source_hash":""#,
+    ));
+}
+
+#[test]
+fn manifest_records_appended_file() {
+    let tmp_dir = TempDir::new().unwrap();
+    let output_file = tmp_dir.child("demo.cast");
+    let manifest_file = tmp_dir.child("manifest.json");
+    for (input, output) in [("echo Hello World", "Hello World"), ("pwd", "/x")] {
+        let mut cmd = test_cmd();
+        cmd.arg("-a")
+            .arg("-o")
+            .arg(output_file.path())
+            .arg("--manifest")
+            .arg(manifest_file.path())
+            .args([input, output])
+            .assert()
+            .success();
+    }
+    manifest_file.assert(predicate::str::contains(format!(
+        r#""path":{:?},"format":"asciicast","duration":5.865,"source_hash":""#,
+        output_file.path()
+    )));
+}
+
+#[test]
+fn theme_file_works() {
+    let tmp_dir = TempDir::new().unwrap();
+    let theme_file = tmp_dir.child("brand.toml");
+    theme_file
+        .write_str(
+            "fg = \"#ffffff\"\nbg = \"#101010\"\npalette = [\"#000000\", \"#aa0000\", \"#00aa00\", \"#aaaa00\", \"#0000aa\", \"#aa00aa\", \"#00aaaa\", \"#aaaaaa\"]\n",
+        )
+        .unwrap();
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .arg("--theme-file")
+        .arg(theme_file.path())
+        .args(["echo Hello World", "Hello World"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        r##""theme":{"fg":"#ffffff","bg":"#101010","palette":"#000000:#aa0000:#00aa00:#aaaa00:#0000aa:#aa00aa:#00aaaa:#aaaaaa"}"##,
+    ));
+}
+
+#[test]
+fn render_settings_are_used_unless_overridden() {
+    let tmp_dir = TempDir::new().unwrap();
+    let input_file = tmp_dir.child("demo.germ");
+    input_file
+        .write_str(&HELLO_WORLD_GERM_OUTPUT.replace(
+            r#""commands""#,
+            r#""render":{"formats":["germ","asciicast"],"theme":"nord"},"commands""#,
+        ))
+        .unwrap();
+    let output_file = tmp_dir.child("demo.out");
+    let mut cmd = test_cmd();
+    cmd.arg("-i")
+        .arg(input_file.path())
+        .arg("-o")
+        .arg(output_file.path())
+        .assert()
+        .success();
+    tmp_dir
+        .child("demo.json")
+        .assert(predicate::str::contains(r#""render""#));
+    tmp_dir
+        .child("demo.cast")
+        .assert(predicate::str::contains(r##""theme":{"fg":"#d8dee9""##));
+    let mut cmd = test_cmd();
+    cmd.arg("-i")
+        .arg(input_file.path())
+        .args(["-O", "asciicast", "--theme", "dracula"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r##""theme":{"fg":"#f8f8f2""##));
+}
+
+#[test]
+fn persona_prompts_work() {
+    let mut cmd = test_cmd();
+    let first = cmd
+        .args(["-G", "--persona", "alice=alice@web$ ", "--as", "alice"])
+        .args(["hostname", "web"])
+        .output()
+        .unwrap();
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--persona", "bob=bob@db$ ", "--as", "bob", "hostname", "db"])
+        .write_stdin(first.stdout)
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains("[0.0,\"o\",\"alice@web$ \"]"))
+        .stdout(predicate::str::contains("[1.88,\"o\",\"bob@db$ \"]"));
+}
+
+#[test]
+fn unknown_persona_fails() {
+    let mut cmd = test_cmd();
+    let assert = cmd.args(["--as", "carol", "hostname", "web"]).assert();
+    assert
+        .failure()
+        .stderr(predicate::str::contains("unknown persona 'carol'"));
+}
+
+#[test]
+fn header_command_works() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args([
+            "--header-command",
+            "demo.sh",
+            "echo Hello World",
+            "Hello World",
+        ])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(r#""command":"demo.sh""#));
+}
+
+#[test]
+fn extra_env_vars_work() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .env("GERM_TEST_LANG", "en_US.UTF-8")
+        .args(["--env", "EDITOR=vim", "--env-capture", "GERM_TEST_LANG"])
+        .args(["echo Hello World", "Hello World"])
+        .assert();
+    assert.success().stdout(predicate::str::contains(
+        r#""env":{"SHELL":"/bin/sh","TERM":"xterm-256color","EDITOR":"vim","GERM_TEST_LANG":"en_US.UTF-8"}"#,
+    ));
+}
+
+#[test]
+fn remote_commands_are_framed_by_ssh_session() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--remote", "admin@web", "uptime", "up 3 days"])
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::contains(
+            "[2.054,\"o\",\"Welcome to web\\r\\n\"]",
+        ))
+        .stdout(predicate::str::contains("[2.054,\"o\",\"admin@web:~$ \"]"))
+        .stdout(predicate::str::contains(
+            "\"o\",\"Connection to web closed.\\r\\n\"]",
+        ));
+}
+
+#[test]
+fn size_auto_without_terminal_fails() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--size", "auto", "echo Hello World", "Hello World"])
+        .write_stdin("")
+        .assert();
+    assert
+        .failure()
+        .stderr(predicate::str::contains("Failed to detect the size"));
+}
+
+#[test]
+fn size_fit_crops_to_content() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--size", "fit", "echo Hello World", "Hello World"])
+        .write_stdin("")
+        .assert();
+    assert.success().stdout(predicate::str::starts_with(
+        r#"{"version":2,"width":18,"height":3,"#,
+    ));
+}
+
+#[test]
+fn size_fit_keeps_size_of_commands() {
+    let mut cmd = test_cmd();
+    let assert = cmd
+        .args(["--size", "fit", "--command-width", "40"])
+        .args(["echo Hello World", "Hello World"])
+        .write_stdin("")
+        .assert();
+    assert
+        .success()
+        .stdout(predicate::str::starts_with(
+            r#"{"version":2,"width":18,"height":3,"#,
+        ))
+        .stdout(predicate::str::contains(r#"[0.0,"r","40x24"]"#))
+        .stdout(predicate::str::contains(r#"[2.16,"r","18x3"]"#));
+}
+
+#[test]
+fn recorder_streams_events_to_writer() {
+    let mut asciicast = germ::Asciicast::default();
+    asciicast.header.env.shell = String::from(TEST_SHELL);
+    asciicast.header.env.term = String::from(TEST_TERM);
+    let mut buffer = Vec::new();
+    {
+        let mut recorder = germ::Recorder::new(
+            asciicast,
+            germ::Timings::default(),
+            germ::WriteSink(&mut buffer),
+        )
+        .unwrap();
+        recorder
+            .record(germ::Command::from("echo Hello World").add("Hello World\n"))
+            .unwrap();
+    }
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        HELLO_WORLD_ASCIICAST_OUTPUT
+    );
+}
+
+#[test]
+fn recorder_sends_events_to_channel() {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut recorder =
+        germ::Recorder::new(germ::Asciicast::default(), germ::Timings::default(), sender).unwrap();
+    recorder.record(&germ::Command::from("ls")).unwrap();
+    assert_eq!(receiver.try_iter().count(), 3);
+    recorder.finish().unwrap();
+    let end = receiver.try_recv().unwrap();
+    assert_eq!(end.2, "");
+    assert!(receiver.try_recv().is_err());
+}
+
+/// Reads the opening handshake of a WebSocket client and answers it with the
+/// accept value, if any, or the one computed from the key.
+fn accept_websocket(
+    listener: std::net::TcpListener,
+    accept: Option<&str>,
+) -> std::io::BufReader<std::net::TcpStream> {
+    use sha1::{Digest, Sha1};
+    use std::io::BufRead;
+
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = std::io::BufReader::new(stream);
+    let mut key = String::new();
+    let mut line = String::new();
+    while line != "\r\n" {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        if let Some(value) = line.strip_prefix("Sec-WebSocket-Key:") {
+            key = value.trim().to_owned();
+        }
+    }
+    let digest = Sha1::digest(format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key));
+    // A SHA-1 digest is 20 bytes, so only the last group is padded.
+    let mut expected = String::new();
+    for chunk in digest.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            expected.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    expected.push('=');
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 101 Switching Protocols\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept.unwrap_or(&expected)
+    )
+    .unwrap();
+    reader
+}
+
+#[test]
+fn stream_sends_lines_to_websocket() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}/live", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let mut reader = accept_websocket(listener, None);
+        let mut messages = Vec::new();
+        loop {
+            let mut head = [0; 2];
+            reader.read_exact(&mut head).unwrap();
+            let len = match head[1] & 0x7f {
+                126 => {
+                    let mut len = [0; 2];
+                    reader.read_exact(&mut len).unwrap();
+                    u16::from_be_bytes(len) as usize
+                }
+                len => len as usize,
+            };
+            let mut mask = [0; 4];
+            reader.read_exact(&mut mask).unwrap();
+            let mut payload = vec![0; len];
+            reader.read_exact(&mut payload).unwrap();
+            if head[0] & 0x0f == 0x8 {
+                return messages;
+            }
+            let text = payload
+                .iter()
+                .zip(mask.iter().cycle())
+                .map(|(b, m)| b ^ m)
+                .collect::<Vec<u8>>();
+            messages.push(String::from_utf8(text).unwrap());
+        }
+    });
+    let mut cmd = test_cmd();
+    cmd.args(["--stream", &url, "echo Hello World", "Hello World"])
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout(HELLO_WORLD_ASCIICAST_OUTPUT);
+    let messages = server.join().unwrap();
+    assert_eq!(
+        messages,
+        HELLO_WORLD_ASCIICAST_OUTPUT.lines().collect::<Vec<&str>>()
+    );
+}
+
+#[test]
+fn stream_rejects_a_server_without_the_accept_of_the_key() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}/live", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        accept_websocket(listener, Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+    });
+    let mut cmd = test_cmd();
+    cmd.args(["--stream", &url, "echo Hello World", "Hello World"])
+        .write_stdin("")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unexpected Sec-WebSocket-Accept 's3pPLMBiTxaQ9kYGzzhZRbK+xOo='",
+        ));
+    server.join().unwrap();
+}